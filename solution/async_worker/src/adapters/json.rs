@@ -0,0 +1,470 @@
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use serde::Deserialize;
+use serde_json::Value;
+
+use common::dates::DateParser;
+use common::xml_models::{BasePlan, Plan, SellModeEnum, Zone};
+use common::zones::ZoneParser;
+
+use super::{ParsedRecord, ProviderAdapter, RecordStream};
+use crate::error::{AdapterError, RecordError};
+use crate::fetch::Payload;
+
+pub const JSON: &str = "json";
+pub const NDJSON: &str = "ndjson";
+
+/// Field mapping from a provider JSON document to the internal event model.
+/// Every value is a dot separated path, relative to the enclosing object.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FieldMapping {
+    /// Path to the base plans array. An empty path means the document itself. Ignored for NDJSON.
+    pub base_plans: String,
+    pub base_plan: BasePlanMapping,
+    pub plan: PlanMapping,
+    pub zone: ZoneMapping,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BasePlanMapping {
+    pub base_plan_id: String,
+    pub sell_mode: String,
+    pub organizer_company_id: String,
+    pub title: String,
+    pub plans: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PlanMapping {
+    pub plan_start_date: String,
+    pub plan_end_date: String,
+    pub plan_id: String,
+    pub sell_from: String,
+    pub sell_to: String,
+    pub sold_out: String,
+    pub zones: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ZoneMapping {
+    pub zone_id: String,
+    pub capacity: String,
+    pub price: String,
+    pub name: String,
+    pub numbered: String,
+    pub currency: String,
+}
+
+impl Default for FieldMapping {
+    fn default() -> Self {
+        FieldMapping {
+            base_plans: "base_plans".to_string(),
+            base_plan: BasePlanMapping::default(),
+            plan: PlanMapping::default(),
+            zone: ZoneMapping::default(),
+        }
+    }
+}
+
+impl Default for BasePlanMapping {
+    fn default() -> Self {
+        BasePlanMapping {
+            base_plan_id: "base_plan_id".to_string(),
+            sell_mode: "sell_mode".to_string(),
+            organizer_company_id: "organizer_company_id".to_string(),
+            title: "title".to_string(),
+            plans: "plans".to_string(),
+        }
+    }
+}
+
+impl Default for PlanMapping {
+    fn default() -> Self {
+        PlanMapping {
+            plan_start_date: "plan_start_date".to_string(),
+            plan_end_date: "plan_end_date".to_string(),
+            plan_id: "plan_id".to_string(),
+            sell_from: "sell_from".to_string(),
+            sell_to: "sell_to".to_string(),
+            sold_out: "sold_out".to_string(),
+            zones: "zones".to_string(),
+        }
+    }
+}
+
+impl Default for ZoneMapping {
+    fn default() -> Self {
+        ZoneMapping {
+            zone_id: "zone_id".to_string(),
+            capacity: "capacity".to_string(),
+            price: "price".to_string(),
+            name: "name".to_string(),
+            numbered: "numbered".to_string(),
+            currency: "currency".to_string(),
+        }
+    }
+}
+
+/// `providers.adapter_config` of the JSON adapters.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct JsonAdapterConfig {
+    mapping: FieldMapping,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum JsonFormat {
+    /// A single JSON document holding every base plan.
+    Document,
+    /// Newline delimited JSON, one base plan per line.
+    Lines,
+}
+
+/// Adapter for JSON and NDJSON feeds, driven by a per-provider field mapping.
+pub struct JsonAdapter {
+    format: JsonFormat,
+    mapping: FieldMapping,
+    dates: DateParser,
+    zones: ZoneParser,
+}
+
+impl JsonAdapter {
+    pub fn json(
+        config: Option<&Value>,
+        dates: DateParser,
+        zones: ZoneParser,
+    ) -> Result<Self, AdapterError> {
+        Self::new(JsonFormat::Document, config, dates, zones)
+    }
+
+    pub fn ndjson(
+        config: Option<&Value>,
+        dates: DateParser,
+        zones: ZoneParser,
+    ) -> Result<Self, AdapterError> {
+        Self::new(JsonFormat::Lines, config, dates, zones)
+    }
+
+    fn new(
+        format: JsonFormat,
+        config: Option<&Value>,
+        dates: DateParser,
+        zones: ZoneParser,
+    ) -> Result<Self, AdapterError> {
+        let config: JsonAdapterConfig = match config {
+            Some(config) => serde_json::from_value(config.clone())
+                .map_err(|e| AdapterError::InvalidConfig(e.to_string()))?,
+            None => JsonAdapterConfig::default(),
+        };
+        Ok(JsonAdapter {
+            format,
+            mapping: config.mapping,
+            dates,
+            zones,
+        })
+    }
+
+    /// Records of the base plans array of a JSON document.
+    fn document_records(&self, document: &Value) -> Vec<ParsedRecord> {
+        lookup_list(document, &self.mapping.base_plans)
+            .into_iter()
+            .map(|base_plan| {
+                ParsedRecord::new(base_plan.to_string(), self.map_base_plan(base_plan))
+            })
+            .collect()
+    }
+
+    fn map_base_plan(&self, value: &Value) -> Result<BasePlan, RecordError> {
+        let mapping = &self.mapping.base_plan;
+        let sell_mode = match lookup_string(value, &mapping.sell_mode) {
+            Some(sell_mode) => Some(
+                serde_json::from_value::<SellModeEnum>(Value::String(sell_mode.to_lowercase()))
+                    .map_err(|_| {
+                        RecordError::invalid_value(
+                            &mapping.sell_mode,
+                            format!("Unknown sell_mode: {}", sell_mode),
+                        )
+                    })?,
+            ),
+            None => None,
+        };
+        let base_plan = BasePlan {
+            base_plan_id: lookup_string(value, &mapping.base_plan_id),
+            sell_mode,
+            organizer_company_id: lookup_string(value, &mapping.organizer_company_id),
+            title: required(value, &mapping.title)?,
+            plans: required_list(value, &mapping.plans)?
+                .into_iter()
+                .map(|plan| self.map_plan(plan))
+                .collect::<Result<_, _>>()?,
+        };
+        self.dates.check(&base_plan)?;
+        self.zones.check(&base_plan)?;
+        Ok(base_plan)
+    }
+
+    fn map_plan(&self, value: &Value) -> Result<Plan, RecordError> {
+        let mapping = &self.mapping.plan;
+        Ok(Plan {
+            plan_start_date: required(value, &mapping.plan_start_date)?,
+            plan_end_date: required(value, &mapping.plan_end_date)?,
+            plan_id: lookup_string(value, &mapping.plan_id),
+            sell_from: lookup_string(value, &mapping.sell_from),
+            sell_to: lookup_string(value, &mapping.sell_to),
+            sold_out: lookup_bool(value, &mapping.sold_out),
+            zones: lookup_list(value, &mapping.zones)
+                .into_iter()
+                .map(|zone| self.map_zone(zone))
+                .collect(),
+        })
+    }
+
+    fn map_zone(&self, value: &Value) -> Zone {
+        let mapping = &self.mapping.zone;
+        Zone {
+            zone_id: lookup_string(value, &mapping.zone_id),
+            capacity: lookup_string(value, &mapping.capacity),
+            price: lookup_string(value, &mapping.price),
+            name: lookup_string(value, &mapping.name),
+            numbered: lookup_bool(value, &mapping.numbered),
+            currency: lookup_string(value, &mapping.currency),
+        }
+    }
+}
+
+#[async_trait]
+impl ProviderAdapter for JsonAdapter {
+    fn parse(&self, payload: &str) -> Result<Vec<ParsedRecord>, AdapterError> {
+        match self.format {
+            JsonFormat::Document => Ok(self.document_records(&parse_document(payload)?)),
+            JsonFormat::Lines => Ok(payload
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| ParsedRecord::new(line.to_string(), self.parse_record(line)))
+                .collect()),
+        }
+    }
+
+    fn parse_record(&self, raw: &str) -> Result<BasePlan, RecordError> {
+        let base_plan: Value = serde_json::from_str(raw)?;
+        self.map_base_plan(&base_plan)
+    }
+
+    async fn stream_page(
+        &self,
+        payload: Payload,
+        cursor_path: &str,
+    ) -> Result<(RecordStream, Option<String>), AdapterError> {
+        if self.format != JsonFormat::Document {
+            return Err(AdapterError::Pagination(
+                "cursor pagination requires a JSON document feed".to_string(),
+            ));
+        }
+        let document = parse_document(&payload.into_string().await?)?;
+        let records = self.document_records(&document);
+        let cursor = lookup_string(&document, cursor_path);
+        Ok((stream::iter(records.into_iter().map(Ok)).boxed(), cursor))
+    }
+}
+
+fn parse_document(payload: &str) -> Result<Value, AdapterError> {
+    serde_json::from_str(payload).map_err(|e| AdapterError::Parse(e.to_string()))
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.is_empty() {
+        return Some(value);
+    }
+    path.split('.').try_fold(value, |value, key| match value {
+        Value::Object(map) => map.get(key),
+        Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => None,
+    })
+}
+
+/// Values found at `path`. A single object is treated as a one element list.
+fn lookup_list<'a>(value: &'a Value, path: &str) -> Vec<&'a Value> {
+    match lookup(value, path) {
+        Some(Value::Array(items)) => items.iter().collect(),
+        Some(Value::Null) | None => Vec::new(),
+        Some(item) => vec![item],
+    }
+}
+
+pub fn lookup_string(value: &Value, path: &str) -> Option<String> {
+    match lookup(value, path)? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn lookup_bool(value: &Value, path: &str) -> Option<bool> {
+    match lookup(value, path)? {
+        Value::Bool(b) => Some(*b),
+        Value::String(s) => match s.to_lowercase().as_str() {
+            "true" | "1" => Some(true),
+            "false" | "0" => Some(false),
+            _ => None,
+        },
+        Value::Number(n) => n.as_i64().map(|n| n != 0),
+        _ => None,
+    }
+}
+
+fn required(value: &Value, path: &str) -> Result<String, RecordError> {
+    lookup_string(value, path).ok_or_else(|| RecordError::missing_field(path))
+}
+
+/// A list that must hold at least one element, e.g. the plans of a base plan.
+fn required_list<'a>(value: &'a Value, path: &str) -> Result<Vec<&'a Value>, RecordError> {
+    let list = lookup_list(value, path);
+    if list.is_empty() {
+        return Err(RecordError::missing_field(path));
+    }
+    Ok(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::RecordErrorKind;
+    use serde_json::json;
+
+    fn valid(records: Vec<ParsedRecord>) -> Vec<BasePlan> {
+        records
+            .into_iter()
+            .map(|record| match record {
+                ParsedRecord::Valid(base_plan) => base_plan,
+                ParsedRecord::Rejected(rejected) => panic!("Unexpected rejection: {:?}", rejected),
+            })
+            .collect()
+    }
+
+    fn mapping() -> Value {
+        json!({
+            "mapping": {
+                "base_plans": "data.events",
+                "base_plan": { "base_plan_id": "id", "title": "name", "sell_mode": "channel", "plans": "sessions" },
+                "plan": { "plan_id": "id", "plan_start_date": "starts_at", "plan_end_date": "ends_at", "zones": "areas" },
+                "zone": { "zone_id": "id", "price": "price.amount", "numbered": "seated" }
+            }
+        })
+    }
+
+    #[test]
+    fn test_parse_json_with_mapping() {
+        let payload = json!({
+            "data": { "events": [{
+                "id": 291,
+                "name": "Camela en concierto",
+                "channel": "ONLINE",
+                "sessions": [{
+                    "id": "291",
+                    "starts_at": "2021-06-30T21:00:00",
+                    "ends_at": "2021-06-30T21:30:00",
+                    "sold_out": false,
+                    "areas": [
+                        { "id": 40, "name": "Platea", "capacity": 243, "price": { "amount": "20.00" }, "seated": "true" },
+                        { "id": 38, "name": "Grada 2", "capacity": 100, "price": { "amount": 15.5 }, "seated": false }
+                    ]
+                }]
+            }]}
+        });
+        let adapter = JsonAdapter::json(
+            Some(&mapping()),
+            DateParser::default(),
+            ZoneParser::default(),
+        )
+        .unwrap();
+        let base_plans = valid(adapter.parse(&payload.to_string()).unwrap());
+
+        assert_eq!(base_plans.len(), 1);
+        let base_plan = &base_plans[0];
+        assert_eq!(base_plan.base_plan_id.as_deref(), Some("291"));
+        assert_eq!(base_plan.sell_mode, Some(SellModeEnum::Online));
+        assert_eq!(base_plan.plans[0].sold_out, Some(false));
+        let zones = &base_plan.plans[0].zones;
+        assert_eq!(zones[0].price.as_deref(), Some("20.00"));
+        assert_eq!(zones[0].numbered, Some(true));
+        assert_eq!(zones[1].price.as_deref(), Some("15.5"));
+        assert_eq!(zones[1].capacity.as_deref(), Some("100"));
+    }
+
+    #[test]
+    fn test_parse_ndjson_with_default_mapping() {
+        let payload = concat!(
+            r#"{"base_plan_id":"1","title":"A","sell_mode":"online","plans":[{"plan_id":"1","plan_start_date":"2021-06-30T21:00:00","plan_end_date":"2021-06-30T22:00:00","zones":{"zone_id":"1","price":"10.00"}}]}"#,
+            "\n\n",
+            r#"{"base_plan_id":"2","title":"B","sell_mode":"offline","plans":{"plan_start_date":"2021-07-31T20:00:00","plan_end_date":"2021-07-31T21:00:00"}}"#,
+            "\n",
+            r#"{"base_plan_id":"3","title":"C","sell_mode":"online","plans":[]}"#,
+            "\n"
+        );
+        let adapter =
+            JsonAdapter::ndjson(None, DateParser::default(), ZoneParser::default()).unwrap();
+        let mut records = adapter.parse(payload).unwrap();
+        let Some(ParsedRecord::Rejected(rejected)) = records.pop() else {
+            panic!("Expected a rejected record");
+        };
+        assert_eq!(rejected.error, RecordError::missing_field("plans"));
+        let base_plans = valid(records);
+
+        assert_eq!(base_plans.len(), 2);
+        assert_eq!(base_plans[0].plans[0].zones.len(), 1);
+        assert_eq!(base_plans[1].sell_mode, Some(SellModeEnum::Offline));
+        assert_eq!(base_plans[1].plans.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_json_rejects_record_missing_required_field() {
+        let adapter =
+            JsonAdapter::json(None, DateParser::default(), ZoneParser::default()).unwrap();
+        let records = adapter
+            .parse(r#"{"base_plans":[{"base_plan_id":"1"},{"base_plan_id":"2","title":"B"},{"base_plan_id":"3","title":"C","plans":{"plan_start_date":"2021-06-30T21:00:00","plan_end_date":"2021-06-30T22:00:00"}}]}"#)
+            .unwrap();
+        let ParsedRecord::Rejected(rejected) = &records[0] else {
+            panic!("Expected a rejected record");
+        };
+        assert_eq!(rejected.error, RecordError::missing_field("title"));
+        assert_eq!(
+            adapter.parse_record(&rejected.raw),
+            Err(rejected.error.clone())
+        );
+        let ParsedRecord::Rejected(rejected) = &records[1] else {
+            panic!("Expected a rejected record");
+        };
+        assert_eq!(rejected.error, RecordError::missing_field("plans"));
+        assert!(matches!(records[2], ParsedRecord::Valid(_)));
+    }
+
+    #[test]
+    fn test_parse_ndjson_rejects_malformed_line() {
+        let adapter =
+            JsonAdapter::ndjson(None, DateParser::default(), ZoneParser::default()).unwrap();
+        let records = adapter
+            .parse(concat!(
+                r#"{"title":"A","plans":{"plan_start_date":"2021-06-30T21:00:00","plan_end_date":"2021-06-30T22:00:00"}}"#,
+                "\n{\"title\":\n"
+            ))
+            .unwrap();
+        assert!(matches!(records[0], ParsedRecord::Valid(_)));
+        let ParsedRecord::Rejected(rejected) = &records[1] else {
+            panic!("Expected a rejected record");
+        };
+        assert_eq!(rejected.error.kind, RecordErrorKind::Malformed);
+    }
+
+    #[test]
+    fn test_parse_json_invalid_document() {
+        let adapter =
+            JsonAdapter::json(None, DateParser::default(), ZoneParser::default()).unwrap();
+        let result = adapter.parse(r#"{"base_plans":["#);
+        assert!(matches!(result, Err(AdapterError::Parse(_))));
+    }
+}
@@ -0,0 +1,129 @@
+use async_trait::async_trait;
+use common::dates::DateParser;
+use common::timezone::parse_timezone;
+use common::xml_models::BasePlan;
+use common::zones::ZoneParser;
+use futures::stream::{self, BoxStream, StreamExt};
+use storage::models::providers::Provider;
+
+use crate::context::WorkerContext;
+use crate::error::{AdapterError, RecordError};
+use crate::fetch::{fetch_payload, FeedAccess, FetchOutcome, Payload, Validators};
+
+pub mod json;
+pub mod xml;
+
+/// One record of a provider payload: a base plan, or the raw fragment that failed to parse.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParsedRecord {
+    Valid(BasePlan),
+    Rejected(RejectedRecord),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RejectedRecord {
+    pub raw: String,
+    pub error: RecordError,
+}
+
+impl ParsedRecord {
+    pub fn new(raw: String, parsed: Result<BasePlan, RecordError>) -> Self {
+        match parsed {
+            Ok(base_plan) => ParsedRecord::Valid(base_plan),
+            Err(error) => ParsedRecord::Rejected(RejectedRecord { raw, error }),
+        }
+    }
+}
+
+/// Records yielded one at a time while a provider payload is being read. An `Err` means the
+/// payload as a whole cannot be read any further.
+pub type RecordStream = BoxStream<'static, Result<ParsedRecord, AdapterError>>;
+
+/// Fetches a provider payload and normalizes it into the internal event model
+/// consumed by `common::persist::persist_base_plans`.
+#[async_trait]
+pub trait ProviderAdapter: Send + Sync {
+    /// Downloads the raw payload of the provider, unless it is unchanged since `validators`.
+    async fn fetch(
+        &self,
+        context: &WorkerContext,
+        url: &str,
+        validators: &Validators,
+        access: FeedAccess<'_>,
+    ) -> Result<FetchOutcome, AdapterError> {
+        Ok(fetch_payload(
+            context.client_for(access),
+            url,
+            &context.retry_policy,
+            validators,
+            access,
+            context.max_body_bytes,
+        )
+        .await?)
+    }
+
+    /// Normalizes a raw payload into records. Invalid records are returned as rejected
+    /// rather than failing the whole payload.
+    fn parse(&self, payload: &str) -> Result<Vec<ParsedRecord>, AdapterError>;
+
+    /// Parses the raw fragment of a single record, as kept for rejected records.
+    fn parse_record(&self, raw: &str) -> Result<BasePlan, RecordError>;
+
+    /// Streams the records of a fetched payload so they can be persisted incrementally.
+    /// By default the whole payload is parsed before the first record is yielded.
+    async fn stream(&self, payload: Payload) -> Result<RecordStream, AdapterError> {
+        let payload = payload.into_string().await?;
+        let records = self.parse(&payload)?;
+        Ok(stream::iter(records.into_iter().map(Ok)).boxed())
+    }
+
+    /// Streams the records of a page of a cursor paginated feed, along with the cursor of the
+    /// next page read at `cursor_path` of the same document. Only JSON documents carry one.
+    async fn stream_page(
+        &self,
+        _payload: Payload,
+        _cursor_path: &str,
+    ) -> Result<(RecordStream, Option<String>), AdapterError> {
+        Err(AdapterError::Pagination(
+            "cursor pagination requires a JSON document feed".to_string(),
+        ))
+    }
+}
+
+/// Returns the adapter selected by the `providers.adapter` column,
+/// configured from `providers.adapter_config`. Records whose required dates cannot be parsed
+/// with the date formats of the provider, or whose zone capacities, prices or currencies
+/// cannot be stored, are rejected.
+pub fn adapter_for(provider: &Provider) -> Result<Box<dyn ProviderAdapter>, AdapterError> {
+    let config = provider.adapter_config.as_ref();
+    let dates = date_parser_for(provider)?;
+    let zones = zone_parser_for(provider)?;
+    match provider.adapter.trim() {
+        "" | xml::FEVERUP_XML => Ok(Box::new(xml::FeverUpXmlAdapter::new(dates, zones))),
+        json::JSON => Ok(Box::new(json::JsonAdapter::json(config, dates, zones)?)),
+        json::NDJSON => Ok(Box::new(json::JsonAdapter::ndjson(config, dates, zones)?)),
+        other => Err(AdapterError::UnknownAdapter(other.to_string())),
+    }
+}
+
+/// Returns the parser of the zone values in the provider feed, whose prices default to the
+/// `providers.currency` column.
+pub fn zone_parser_for(provider: &Provider) -> Result<ZoneParser, AdapterError> {
+    ZoneParser::new(&provider.currency)
+        .map_err(|e| AdapterError::InvalidConfig(format!("currency: {}", e)))
+}
+
+/// Returns the parser of the dates in the provider feed, configured by the
+/// `providers.date_formats` and `providers.timezone` columns.
+pub fn date_parser_for(provider: &Provider) -> Result<DateParser, AdapterError> {
+    let timezone = parse_timezone(&provider.timezone).map_err(AdapterError::InvalidConfig)?;
+    let formats = provider
+        .date_formats
+        .as_ref()
+        .map(|formats| {
+            serde_json::from_value::<Vec<String>>(formats.clone())
+                .map_err(|e| AdapterError::InvalidConfig(format!("date_formats: {}", e)))
+        })
+        .transpose()?;
+    DateParser::new(formats, timezone).map_err(AdapterError::InvalidConfig)
+}
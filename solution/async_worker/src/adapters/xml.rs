@@ -0,0 +1,659 @@
+use async_trait::async_trait;
+use encoding_rs::{Encoding, UTF_8};
+use futures::stream::{self, StreamExt};
+use quick_xml::de::from_str;
+use quick_xml::events::Event;
+use quick_xml::{Reader, Writer};
+use serde::de::value::{Error as ValueError, StrDeserializer};
+use serde::Deserialize;
+use tokio::fs::File;
+use tokio::io::{AsyncBufRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader};
+
+use common::dates::DateParser;
+use common::xml_models::{BasePlan, Plan, SellModeEnum, Zone};
+use common::zones::ZoneParser;
+
+use super::{ParsedRecord, ProviderAdapter, RecordStream};
+use crate::error::{AdapterError, FetchError, RecordError};
+use crate::fetch::Payload;
+
+pub const FEVERUP_XML: &str = "feverup_xml";
+
+const BASE_PLAN_TAG: &[u8] = b"base_plan";
+
+/// Deepest element nesting accepted in a payload.
+const MAX_DEPTH: usize = 64;
+
+/// Size of the payload head searched for a byte order mark or an XML declaration.
+const PROLOG_SIZE: usize = 1024;
+
+/// Adapter for the `<planList><output><base_plan>` XML dialect of FeverUp.
+/// The spooled payload is parsed one `<base_plan>` at a time,
+/// so memory stays bounded regardless of the size of the feed.
+/// Payloads declaring another encoding than UTF-8 are transcoded first, and DTDs are
+/// rejected so that entity expansion cannot blow up a fragment.
+#[derive(Default)]
+pub struct FeverUpXmlAdapter {
+    dates: DateParser,
+    zones: ZoneParser,
+}
+
+impl FeverUpXmlAdapter {
+    pub fn new(dates: DateParser, zones: ZoneParser) -> Self {
+        FeverUpXmlAdapter { dates, zones }
+    }
+}
+
+#[async_trait]
+impl ProviderAdapter for FeverUpXmlAdapter {
+    fn parse(&self, payload: &str) -> Result<Vec<ParsedRecord>, AdapterError> {
+        let mut reader = Reader::from_str(payload);
+        let mut collector = BasePlanCollector::default();
+        let mut records = Vec::new();
+        loop {
+            let event = reader.read_event().map_err(xml_error)?;
+            let eof = matches!(event, Event::Eof);
+            if let Some(fragment) = collector.feed(event)? {
+                records.push(parse_fragment(fragment, &self.dates, &self.zones));
+            }
+            if eof {
+                return Ok(records);
+            }
+        }
+    }
+
+    fn parse_record(&self, raw: &str) -> Result<BasePlan, RecordError> {
+        parse_base_plan(raw, &self.dates, &self.zones)
+    }
+
+    async fn stream(&self, payload: Payload) -> Result<RecordStream, AdapterError> {
+        let payload = transcode(payload).await?;
+        Ok(stream_records(
+            BufReader::new(payload.file),
+            self.dates.clone(),
+            self.zones.clone(),
+        ))
+    }
+}
+
+/// Rewrites a payload declared in another encoding than UTF-8, by a byte order mark or by
+/// the `encoding` of its XML declaration, as UTF-8. Other payloads are returned untouched.
+async fn transcode(mut payload: Payload) -> Result<Payload, AdapterError> {
+    let io_error = |e: std::io::Error| AdapterError::Fetch(FetchError::Body(e.to_string()));
+    let mut head = Vec::with_capacity(PROLOG_SIZE);
+    (&mut payload.file)
+        .take(PROLOG_SIZE as u64)
+        .read_to_end(&mut head)
+        .await
+        .map_err(io_error)?;
+    payload.file.rewind().await.map_err(io_error)?;
+    let Some(encoding) = declared_encoding(&head)? else {
+        return Ok(payload);
+    };
+
+    let mut decoder = encoding.new_decoder_with_bom_removal();
+    let mut file = File::from_std(tempfile::tempfile().map_err(io_error)?);
+    let mut buf = vec![0; 8192];
+    let mut decoded = String::new();
+    let mut size = 0;
+    loop {
+        let read = payload.file.read(&mut buf).await.map_err(io_error)?;
+        let last = read == 0;
+        decoded.clear();
+        decoded.reserve(
+            decoder
+                .max_utf8_buffer_length(read)
+                .ok_or_else(|| AdapterError::Parse("Payload chunk too large".to_string()))?,
+        );
+        let _ = decoder.decode_to_string(&buf[..read], &mut decoded, last);
+        file.write_all(decoded.as_bytes()).await.map_err(io_error)?;
+        size += decoded.len() as u64;
+        if last {
+            break;
+        }
+    }
+    file.flush().await.map_err(io_error)?;
+    file.rewind().await.map_err(io_error)?;
+    Ok(Payload {
+        file,
+        size,
+        ..payload
+    })
+}
+
+/// Encoding of a payload that has to be transcoded, or `None` when it is already UTF-8.
+fn declared_encoding(head: &[u8]) -> Result<Option<&'static Encoding>, AdapterError> {
+    if let Some((encoding, _)) = Encoding::for_bom(head) {
+        return Ok(Some(encoding));
+    }
+    let Some(label) = declaration_encoding(head) else {
+        return Ok(None);
+    };
+    match Encoding::for_label(label) {
+        Some(encoding) if encoding == UTF_8 => Ok(None),
+        Some(encoding) => Ok(Some(encoding)),
+        None => Err(AdapterError::Parse(format!(
+            "Unsupported encoding {}",
+            String::from_utf8_lossy(label)
+        ))),
+    }
+}
+
+/// Value of the `encoding` pseudo-attribute of the `<?xml ...?>` declaration.
+fn declaration_encoding(head: &[u8]) -> Option<&[u8]> {
+    let declaration = head.strip_prefix(b"<?xml")?;
+    let end = declaration.windows(2).position(|w| w == b"?>")?;
+    let declaration = &declaration[..end];
+    let start = declaration.windows(8).position(|w| w == b"encoding")? + "encoding".len();
+    let value = declaration[start..].trim_ascii_start().strip_prefix(b"=")?;
+    let value = value.trim_ascii_start();
+    let quote = *value.first().filter(|q| **q == b'"' || **q == b'\'')?;
+    let value = &value[1..];
+    let end = value.iter().position(|b| *b == quote)?;
+    Some(&value[..end])
+}
+
+/// Parses records out of an XML byte stream as it is being read.
+fn stream_records<R>(body: R, dates: DateParser, zones: ZoneParser) -> RecordStream
+where
+    R: AsyncBufRead + Unpin + Send + 'static,
+{
+    let state = StreamState {
+        reader: Reader::from_reader(body),
+        collector: BasePlanCollector::default(),
+        dates,
+        zones,
+        buf: Vec::new(),
+        done: false,
+    };
+
+    stream::unfold(state, |mut state| async move {
+        if state.done {
+            return None;
+        }
+        loop {
+            state.buf.clear();
+            let event = match state.reader.read_event_into_async(&mut state.buf).await {
+                Ok(event) => event.into_owned(),
+                Err(e) => {
+                    state.done = true;
+                    return Some((Err(xml_error(e)), state));
+                }
+            };
+            let eof = matches!(event, Event::Eof);
+            match state.collector.feed(event) {
+                Ok(Some(fragment)) => {
+                    state.done = eof;
+                    let record = parse_fragment(fragment, &state.dates, &state.zones);
+                    return Some((Ok(record), state));
+                }
+                Ok(None) if eof => return None,
+                Ok(None) => continue,
+                Err(e) => {
+                    state.done = true;
+                    return Some((Err(e), state));
+                }
+            }
+        }
+    })
+    .boxed()
+}
+
+struct StreamState<R> {
+    reader: Reader<R>,
+    collector: BasePlanCollector,
+    dates: DateParser,
+    zones: ZoneParser,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+/// Rebuilds the raw XML of each `<base_plan>` element from a sequence of reader events.
+#[derive(Default)]
+struct BasePlanCollector {
+    writer: Option<Writer<Vec<u8>>>,
+    depth: usize,
+    /// Nesting of the current element within the whole document.
+    document_depth: usize,
+}
+
+impl BasePlanCollector {
+    /// Feeds one event. Returns the raw XML of a base plan once its closing tag is reached.
+    fn feed(&mut self, event: Event) -> Result<Option<String>, AdapterError> {
+        match &event {
+            Event::DocType(_) => {
+                return Err(AdapterError::Parse(
+                    "DOCTYPE declarations are not allowed".to_string(),
+                ))
+            }
+            Event::Start(_) => {
+                self.document_depth += 1;
+                if self.document_depth > MAX_DEPTH {
+                    return Err(AdapterError::Parse(format!(
+                        "Elements are nested deeper than {} levels",
+                        MAX_DEPTH
+                    )));
+                }
+            }
+            Event::End(_) => self.document_depth = self.document_depth.saturating_sub(1),
+            _ => {}
+        }
+
+        let Some(writer) = self.writer.as_mut() else {
+            return match event {
+                Event::Start(start) if start.local_name().as_ref() == BASE_PLAN_TAG => {
+                    let mut writer = Writer::new(Vec::new());
+                    writer.write_event(Event::Start(start))?;
+                    self.writer = Some(writer);
+                    self.depth = 1;
+                    Ok(None)
+                }
+                Event::Empty(empty) if empty.local_name().as_ref() == BASE_PLAN_TAG => {
+                    let mut writer = Writer::new(Vec::new());
+                    writer.write_event(Event::Empty(empty))?;
+                    into_fragment(writer).map(Some)
+                }
+                _ => Ok(None),
+            };
+        };
+
+        match &event {
+            Event::Start(_) => self.depth += 1,
+            Event::End(_) => self.depth -= 1,
+            Event::Eof => {
+                return Err(AdapterError::Parse(
+                    "Unexpected end of payload inside base_plan".to_string(),
+                ))
+            }
+            _ => {}
+        }
+        writer.write_event(event)?;
+        if self.depth > 0 {
+            return Ok(None);
+        }
+        match self.writer.take() {
+            Some(writer) => into_fragment(writer).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+fn into_fragment(writer: Writer<Vec<u8>>) -> Result<String, AdapterError> {
+    String::from_utf8(writer.into_inner()).map_err(|e| AdapterError::Parse(e.to_string()))
+}
+
+fn parse_fragment(fragment: String, dates: &DateParser, zones: &ZoneParser) -> ParsedRecord {
+    let parsed = parse_base_plan(&fragment, dates, zones);
+    ParsedRecord::new(fragment, parsed)
+}
+
+fn parse_base_plan(
+    fragment: &str,
+    dates: &DateParser,
+    zones: &ZoneParser,
+) -> Result<BasePlan, RecordError> {
+    let base_plan = from_str::<RawBasePlan>(fragment)?.into_base_plan()?;
+    dates.check(&base_plan)?;
+    zones.check(&base_plan)?;
+    Ok(base_plan)
+}
+
+/// A `<base_plan>` as sent by the provider. Every attribute is read as optional text, so that
+/// the deserializer only fails on malformed fragments, and missing or invalid values are
+/// rejected while converting it.
+#[derive(Deserialize)]
+struct RawBasePlan {
+    #[serde(rename = "@base_plan_id")]
+    base_plan_id: Option<String>,
+    #[serde(rename = "@sell_mode")]
+    sell_mode: Option<String>,
+    #[serde(rename = "@organizer_company_id")]
+    organizer_company_id: Option<String>,
+    #[serde(rename = "@title")]
+    title: Option<String>,
+    #[serde(rename = "plan")]
+    plans: Option<Vec<RawPlan>>,
+}
+
+#[derive(Deserialize)]
+struct RawPlan {
+    #[serde(rename = "@plan_start_date")]
+    plan_start_date: Option<String>,
+    #[serde(rename = "@plan_end_date")]
+    plan_end_date: Option<String>,
+    #[serde(rename = "@plan_id")]
+    plan_id: Option<String>,
+    #[serde(rename = "@sell_from")]
+    sell_from: Option<String>,
+    #[serde(rename = "@sell_to")]
+    sell_to: Option<String>,
+    #[serde(rename = "@sold_out")]
+    sold_out: Option<String>,
+    #[serde(rename = "zone")]
+    zones: Option<Vec<RawZone>>,
+}
+
+#[derive(Deserialize)]
+struct RawZone {
+    #[serde(rename = "@zone_id")]
+    zone_id: Option<String>,
+    #[serde(rename = "@capacity")]
+    capacity: Option<String>,
+    #[serde(rename = "@price")]
+    price: Option<String>,
+    #[serde(rename = "@name")]
+    name: Option<String>,
+    #[serde(rename = "@numbered")]
+    numbered: Option<String>,
+    #[serde(rename = "@currency")]
+    currency: Option<String>,
+}
+
+impl RawBasePlan {
+    fn into_base_plan(self) -> Result<BasePlan, RecordError> {
+        let sell_mode = match self.sell_mode {
+            Some(sell_mode) => Some(
+                SellModeEnum::deserialize(StrDeserializer::<ValueError>::new(&sell_mode)).map_err(
+                    |_| {
+                        RecordError::invalid_value(
+                            "sell_mode",
+                            format!("Unknown sell_mode: {}", sell_mode),
+                        )
+                    },
+                )?,
+            ),
+            None => None,
+        };
+        Ok(BasePlan {
+            base_plan_id: self.base_plan_id,
+            sell_mode,
+            organizer_company_id: self.organizer_company_id,
+            title: required("title", self.title)?,
+            plans: required("plan", self.plans)?
+                .into_iter()
+                .map(RawPlan::into_plan)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl RawPlan {
+    fn into_plan(self) -> Result<Plan, RecordError> {
+        Ok(Plan {
+            plan_start_date: required("plan_start_date", self.plan_start_date)?,
+            plan_end_date: required("plan_end_date", self.plan_end_date)?,
+            plan_id: self.plan_id,
+            sell_from: self.sell_from,
+            sell_to: self.sell_to,
+            sold_out: parse_bool("sold_out", self.sold_out)?,
+            zones: required("zone", self.zones)?
+                .into_iter()
+                .map(RawZone::into_zone)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl RawZone {
+    fn into_zone(self) -> Result<Zone, RecordError> {
+        Ok(Zone {
+            zone_id: self.zone_id,
+            capacity: self.capacity,
+            price: self.price,
+            name: self.name,
+            numbered: parse_bool("numbered", self.numbered)?,
+            currency: self.currency,
+        })
+    }
+}
+
+fn required<T>(field: &str, value: Option<T>) -> Result<T, RecordError> {
+    value.ok_or_else(|| RecordError::missing_field(field))
+}
+
+/// Boolean attribute, spelled as the XML Schema `boolean` type.
+fn parse_bool(field: &str, value: Option<String>) -> Result<Option<bool>, RecordError> {
+    match value.as_deref().map(str::trim) {
+        None => Ok(None),
+        Some("true" | "1") => Ok(Some(true)),
+        Some("false" | "0") => Ok(Some(false)),
+        Some(other) => Err(RecordError::invalid_value(
+            field,
+            format!("Invalid boolean: {}", other),
+        )),
+    }
+}
+
+fn xml_error(err: quick_xml::Error) -> AdapterError {
+    match err {
+        quick_xml::Error::Io(e) => AdapterError::Fetch(FetchError::Body(e.to_string())),
+        e => AdapterError::Parse(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::RecordErrorKind;
+    use common::xml_models::SellModeEnum;
+    use futures::TryStreamExt;
+    use std::io;
+
+    const PAYLOAD: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<planList version="1.0">
+   <output>
+      <base_plan base_plan_id="291" sell_mode="online" title="Camela en concierto">
+         <plan plan_start_date="2021-06-30T21:00:00" plan_end_date="2021-06-30T21:30:00" plan_id="291" sell_from="2020-07-01T00:00:00" sell_to="2021-06-30T20:00:00" sold_out="false">
+            <zone zone_id="40" capacity="243" price="20.00" name="Platea" numbered="true" />
+            <zone zone_id="38" capacity="100" price="15.00" name="Grada 2" numbered="false" />
+         </plan>
+      </base_plan>
+      <base_plan base_plan_id="322" sell_mode="offline" organizer_company_id="2" title="Pantomima Full">
+         <plan plan_start_date="2021-02-10T20:00:00" plan_end_date="2021-02-10T21:30:00" plan_id="1642" sell_from="2021-01-01T00:00:00" sell_to="2021-02-09T19:50:00" sold_out="false">
+            <zone zone_id="311" capacity="2" price="55.00" name="A28" numbered="true" />
+         </plan>
+      </base_plan>
+   </output>
+</planList>"#;
+
+    fn valid(records: Vec<ParsedRecord>) -> Vec<BasePlan> {
+        records
+            .into_iter()
+            .map(|record| match record {
+                ParsedRecord::Valid(base_plan) => base_plan,
+                ParsedRecord::Rejected(rejected) => panic!("Unexpected rejection: {:?}", rejected),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_feverup_xml() {
+        let base_plans = valid(
+            FeverUpXmlAdapter::default()
+                .parse(PAYLOAD)
+                .expect("Expected Ok result"),
+        );
+        assert_eq!(base_plans.len(), 2);
+        assert_eq!(base_plans[0].base_plan_id.as_deref(), Some("291"));
+        assert_eq!(base_plans[0].sell_mode, Some(SellModeEnum::Online));
+        assert_eq!(base_plans[0].plans[0].zones.len(), 2);
+        assert_eq!(base_plans[1].organizer_company_id.as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn test_parse_matches_full_document_deserialization() {
+        let plan_list: common::xml_models::PlanList = from_str(PAYLOAD).unwrap();
+        let base_plans = valid(FeverUpXmlAdapter::default().parse(PAYLOAD).unwrap());
+        assert_eq!(base_plans, plan_list.output.base_plan);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_records_only() {
+        let payload = PAYLOAD
+            .replacen(r#"sell_mode="online""#, r#"sell_mode="presale""#, 1)
+            .replacen(r#" title="Pantomima Full""#, "", 1);
+        let records = FeverUpXmlAdapter::default().parse(&payload).unwrap();
+        assert_eq!(records.len(), 2);
+
+        let ParsedRecord::Rejected(unknown_variant) = &records[0] else {
+            panic!("Expected a rejected record");
+        };
+        assert_eq!(unknown_variant.error.kind, RecordErrorKind::InvalidValue);
+        assert!(unknown_variant
+            .raw
+            .starts_with(r#"<base_plan base_plan_id="291""#));
+        assert_eq!(
+            FeverUpXmlAdapter::default().parse_record(&unknown_variant.raw),
+            Err(unknown_variant.error.clone())
+        );
+
+        let ParsedRecord::Rejected(missing_title) = &records[1] else {
+            panic!("Expected a rejected record");
+        };
+        assert_eq!(missing_title.error.kind, RecordErrorKind::MissingField);
+        assert_eq!(missing_title.error.field.as_deref(), Some("title"));
+    }
+
+    #[test]
+    fn test_parse_record_classifies_rejections() {
+        let adapter = FeverUpXmlAdapter::default();
+        let invalid_bool = adapter.parse_record(
+            r#"<base_plan title="x"><plan plan_start_date="a" plan_end_date="b" sold_out="maybe"><zone/></plan></base_plan>"#,
+        );
+        let error = invalid_bool.unwrap_err();
+        assert_eq!(error.kind, RecordErrorKind::InvalidValue);
+        assert_eq!(error.field.as_deref(), Some("sold_out"));
+
+        let missing_plans = adapter
+            .parse_record(r#"<base_plan title="x"/>"#)
+            .unwrap_err();
+        assert_eq!(missing_plans, RecordError::missing_field("plan"));
+
+        let malformed = adapter
+            .parse_record(r#"<base_plan title="x">"#)
+            .unwrap_err();
+        assert_eq!(malformed.kind, RecordErrorKind::Malformed);
+    }
+
+    #[tokio::test]
+    async fn test_stream_yields_each_record() {
+        let body = io::Cursor::new(PAYLOAD.as_bytes().to_vec());
+        let records: Vec<ParsedRecord> =
+            stream_records(body, DateParser::default(), ZoneParser::default())
+                .try_collect()
+                .await
+                .unwrap();
+        assert_eq!(
+            records,
+            FeverUpXmlAdapter::default().parse(PAYLOAD).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_reports_truncated_payload() {
+        let second = PAYLOAD.rfind("<base_plan ").unwrap();
+        let truncated = &PAYLOAD[..second + 60];
+        let body = io::Cursor::new(truncated.as_bytes().to_vec());
+        let results: Vec<_> = stream_records(body, DateParser::default(), ZoneParser::default())
+            .collect()
+            .await;
+        assert!(matches!(results[0], Ok(ParsedRecord::Valid(_))));
+        assert!(matches!(results.last(), Some(Err(AdapterError::Parse(_)))));
+    }
+
+    #[test]
+    fn test_parse_invalid_payload() {
+        let result =
+            FeverUpXmlAdapter::default().parse("<planList><output><base_plan title=\"x\">");
+        assert!(matches!(result, Err(AdapterError::Parse(_))));
+    }
+
+    #[test]
+    fn test_parse_rejects_unparseable_dates() {
+        let payload = PAYLOAD.replacen("2021-06-30T21:00:00", "30/06/2021 21:00", 1);
+        let records = FeverUpXmlAdapter::default().parse(&payload).unwrap();
+        let ParsedRecord::Rejected(rejected) = &records[0] else {
+            panic!("Expected a rejected record");
+        };
+        assert_eq!(rejected.error.kind, RecordErrorKind::InvalidValue);
+        assert_eq!(rejected.error.field.as_deref(), Some("plan_start_date"));
+        assert!(matches!(records[1], ParsedRecord::Valid(_)));
+
+        let formats = vec![
+            "%d/%m/%Y %H:%M".to_string(),
+            "%Y-%m-%dT%H:%M:%S".to_string(),
+        ];
+        let adapter = FeverUpXmlAdapter::new(
+            DateParser::new(Some(formats), Default::default()).unwrap(),
+            ZoneParser::default(),
+        );
+        assert!(adapter.parse_record(&rejected.raw).is_ok());
+    }
+
+    #[test]
+    fn test_parse_rejects_unconvertible_zone_values() {
+        let payload = PAYLOAD.replacen("price=\"20.00\"", "price=\"20,00 EUR\"", 1);
+        let records = FeverUpXmlAdapter::default().parse(&payload).unwrap();
+        let ParsedRecord::Rejected(rejected) = &records[0] else {
+            panic!("Expected a rejected record");
+        };
+        assert_eq!(rejected.error.kind, RecordErrorKind::InvalidValue);
+        assert_eq!(rejected.error.field.as_deref(), Some("price"));
+        assert!(matches!(records[1], ParsedRecord::Valid(_)));
+    }
+
+    #[test]
+    fn test_parse_rejects_doctype() {
+        let payload = r#"<?xml version="1.0"?>
+<!DOCTYPE lolz [<!ENTITY lol "lol"><!ENTITY lol2 "&lol;&lol;&lol;&lol;&lol;">]>
+<planList><output><base_plan title="&lol2;"/></output></planList>"#;
+        let result = FeverUpXmlAdapter::default().parse(payload);
+        assert!(matches!(result, Err(AdapterError::Parse(_))));
+    }
+
+    #[test]
+    fn test_parse_rejects_deep_nesting() {
+        let payload = format!(
+            "<planList>{}{}</planList>",
+            "<a>".repeat(MAX_DEPTH),
+            "</a>".repeat(MAX_DEPTH)
+        );
+        let result = FeverUpXmlAdapter::default().parse(&payload);
+        assert!(matches!(result, Err(AdapterError::Parse(_))));
+    }
+
+    #[test]
+    fn test_declared_encoding() {
+        assert_eq!(declared_encoding(PAYLOAD.as_bytes()).unwrap(), None);
+        assert_eq!(declared_encoding(b"<planList/>").unwrap(), None);
+        assert_eq!(
+            declared_encoding(b"<?xml version='1.0' encoding = 'ISO-8859-1'?><planList/>").unwrap(),
+            Some(encoding_rs::WINDOWS_1252)
+        );
+        assert_eq!(
+            declared_encoding(b"\xFF\xFE<\0").unwrap(),
+            Some(encoding_rs::UTF_16LE)
+        );
+        assert!(declared_encoding(br#"<?xml version="1.0" encoding="EBCDIC-X"?>"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_transcodes_declared_encoding() {
+        let payload = PAYLOAD
+            .replace("UTF-8", "ISO-8859-1")
+            .replace("Camela en concierto", "Canción de otoño");
+        let (latin1, _, _) = encoding_rs::WINDOWS_1252.encode(&payload);
+        let payload = Payload::from_bytes(&latin1, 200).await.unwrap();
+
+        let records: Vec<ParsedRecord> = FeverUpXmlAdapter::default()
+            .stream(payload)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        let base_plans = valid(records);
+        assert_eq!(base_plans.len(), 2);
+        assert_eq!(base_plans[0].title, "Canción de otoño");
+    }
+}
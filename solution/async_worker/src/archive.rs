@@ -0,0 +1,192 @@
+use chrono::Utc;
+use common::pools::Pools;
+use log::{debug, warn};
+use std::path::PathBuf;
+use storage::connections::db::PgPooledConnection;
+use storage::models::payload_archives::{NewPayloadArchive, PayloadArchive, PayloadArchiveEntry};
+use storage::payload_archive::{
+    add_payload_archive, delete_payload_archives_before, get_payload_archive, list_payload_archives,
+};
+use tokio::fs::{self, File};
+use tokio::io::AsyncSeekExt;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::error::ArchiveError;
+use crate::fetch::{Payload, Validators};
+
+/// Where the body of archived payloads is kept. Metadata always lives in `payload_archives`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArchiveBackend {
+    None,
+    /// In the `body` column. The payload is read into memory to be stored.
+    Postgres,
+    /// In a file of the directory, streamed from the spooled payload.
+    Directory(PathBuf),
+}
+
+/// Archives raw provider payloads so they can be inspected and replayed.
+#[derive(Debug, Clone)]
+pub struct PayloadArchiver {
+    backend: ArchiveBackend,
+    retention_days: u32,
+}
+
+impl PayloadArchiver {
+    pub fn from_config(config: &Config) -> Result<Self, ArchiveError> {
+        let backend = match config.payload_archive.trim() {
+            "" | "none" => ArchiveBackend::None,
+            "postgres" => ArchiveBackend::Postgres,
+            "directory" => ArchiveBackend::Directory(PathBuf::from(&config.payload_archive_dir)),
+            other => return Err(ArchiveError::UnknownArchive(other.to_string())),
+        };
+        Ok(PayloadArchiver {
+            backend,
+            retention_days: config.payload_archive_retention_days,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.backend != ArchiveBackend::None
+    }
+
+    /// Archives `payload`, leaving its file positioned at the start so it can still be parsed.
+    pub async fn store(
+        &self,
+        pools: &Pools,
+        provider_id: Uuid,
+        payload: &mut Payload,
+    ) -> Result<Option<PayloadArchiveEntry>, ArchiveError> {
+        let archive_id = Uuid::new_v4();
+        let (body, path) = match &self.backend {
+            ArchiveBackend::None => return Ok(None),
+            ArchiveBackend::Postgres => (Some(payload.read_bytes().await?), None),
+            ArchiveBackend::Directory(dir) => {
+                let dir = dir.join(provider_id.to_string());
+                fs::create_dir_all(&dir).await?;
+                let path = dir.join(format!(
+                    "{}_{}.payload",
+                    Utc::now().format("%Y%m%dT%H%M%S"),
+                    archive_id
+                ));
+                let mut file = File::create(&path).await?;
+                tokio::io::copy(&mut payload.file, &mut file).await?;
+                payload.file.rewind().await?;
+                (None, Some(path))
+            }
+        };
+        let new_archive = NewPayloadArchive {
+            payload_archives_id: archive_id,
+            providers_id: provider_id,
+            status: payload.status.into(),
+            content_hash: payload.content_hash.clone(),
+            size: payload.size as i64,
+            body,
+            path: path.as_ref().map(|p| p.to_string_lossy().into_owned()),
+        };
+        let mut conn = connection(pools).await?;
+        match add_payload_archive(&mut conn, new_archive).await {
+            Ok(entry) => Ok(Some(entry)),
+            Err(e) => {
+                if let Some(path) = path {
+                    let _ = fs::remove_file(path).await;
+                }
+                Err(ArchiveError::Db(e.to_string()))
+            }
+        }
+    }
+
+    /// Loads an archived payload back, ready to be parsed.
+    pub async fn load(
+        &self,
+        pools: &Pools,
+        archive_id: Uuid,
+    ) -> Result<(PayloadArchive, Payload), ArchiveError> {
+        let mut conn = connection(pools).await?;
+        let archive = get_payload_archive(&mut conn, archive_id)
+            .await
+            .map_err(|e| ArchiveError::Db(e.to_string()))?
+            .ok_or_else(|| ArchiveError::NotFound(archive_id.to_string()))?;
+        let status = archive.status as u16;
+        let payload = match (&archive.body, &archive.path) {
+            (Some(body), _) => Payload::from_bytes(body, status).await?,
+            (None, Some(path)) => Payload {
+                file: File::open(path).await?,
+                size: archive.size as u64,
+                content_hash: archive.content_hash.clone(),
+                validators: Validators::default(),
+                status,
+                next_link: None,
+            },
+            (None, None) => return Err(ArchiveError::NotFound(archive_id.to_string())),
+        };
+        Ok((archive, payload))
+    }
+
+    pub async fn list(
+        &self,
+        pools: &Pools,
+        provider_id: Uuid,
+        limit: i64,
+    ) -> Result<Vec<PayloadArchiveEntry>, ArchiveError> {
+        let mut conn = connection(pools).await?;
+        list_payload_archives(&mut conn, provider_id, limit)
+            .await
+            .map_err(|e| ArchiveError::Db(e.to_string()))
+    }
+
+    /// Deletes the payloads archived before the retention period, and their files.
+    pub async fn prune(&self, pools: &Pools) -> Result<usize, ArchiveError> {
+        if self.retention_days == 0 {
+            return Ok(0);
+        }
+        let cutoff = Utc::now().naive_utc() - chrono::Duration::days(self.retention_days.into());
+        let mut conn = connection(pools).await?;
+        let deleted = delete_payload_archives_before(&mut conn, cutoff)
+            .await
+            .map_err(|e| ArchiveError::Db(e.to_string()))?;
+        for path in deleted.iter().filter_map(|entry| entry.path.as_ref()) {
+            if let Err(e) = fs::remove_file(path).await {
+                warn!("Failed to remove archived payload {}: {}", path, e);
+            }
+        }
+        debug!("Pruned {} archived payloads", deleted.len());
+        Ok(deleted.len())
+    }
+}
+
+async fn connection(pools: &Pools) -> Result<PgPooledConnection, ArchiveError> {
+    pools
+        .db_connection()
+        .await
+        .ok_or_else(|| ArchiveError::Db("Failed to get DB connection".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(payload_archive: &str) -> Config {
+        envy::from_iter::<_, Config>(vec![(
+            "PAYLOAD_ARCHIVE".to_string(),
+            payload_archive.to_string(),
+        )])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_archive_backend_from_config() {
+        let archiver = PayloadArchiver::from_config(&config("directory")).unwrap();
+        assert_eq!(
+            archiver.backend,
+            ArchiveBackend::Directory(PathBuf::from("payload_archive"))
+        );
+        assert!(!PayloadArchiver::from_config(&config("none"))
+            .unwrap()
+            .is_enabled());
+        assert!(matches!(
+            PayloadArchiver::from_config(&config("s3")),
+            Err(ArchiveError::UnknownArchive(_))
+        ));
+    }
+}
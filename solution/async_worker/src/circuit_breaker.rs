@@ -0,0 +1,189 @@
+use crate::config::Config;
+use common::pools::SharedCache;
+use log::{error, info, warn};
+use std::time::Duration;
+use storage::connections::cache::{CircuitBreakerRecord, CircuitState};
+use uuid::Uuid;
+
+/// Outcome of checking a provider circuit breaker before fetching.
+#[derive(Debug, PartialEq)]
+enum Admission {
+    Allow,
+    Probe,
+    Reject,
+}
+
+/// Per-provider circuit breaker. Its state lives in Redis so every worker instance shares it.
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    cache: SharedCache,
+    failure_threshold: u32,
+    cool_down: Duration,
+}
+
+impl CircuitBreaker {
+    pub fn new(cache: SharedCache, config: &Config) -> Self {
+        CircuitBreaker {
+            cache,
+            failure_threshold: config.circuit_breaker_failure_threshold.max(1),
+            cool_down: Duration::from_secs(config.circuit_breaker_cool_down_sec),
+        }
+    }
+
+    /// Whether the provider may be fetched. Once the cool-down of an open circuit has elapsed,
+    /// a single worker instance is allowed through as the half-open probe.
+    /// Redis failures never block fetching.
+    pub async fn allow_request(&self, provider_id: Uuid) -> bool {
+        let id = provider_id.to_string();
+        let Some(cache) = self.cache.get().await else {
+            return true;
+        };
+        let record = match cache.get_circuit_breaker(&id).await {
+            Ok(record) => record,
+            Err(e) => {
+                error!("Failed to read circuit breaker for provider {}: {}", id, e);
+                self.cache.on_error(&e);
+                return true;
+            }
+        };
+
+        match admission(&record, chrono::Utc::now().timestamp(), self.cool_down) {
+            Admission::Allow => true,
+            Admission::Reject => false,
+            Admission::Probe => {
+                let acquired = cache
+                    .try_acquire_circuit_breaker_probe(&id, self.cool_down.as_secs())
+                    .await
+                    .unwrap_or_else(|e| {
+                        error!("Failed to acquire half-open probe for {}: {}", id, e);
+                        false
+                    });
+                if !acquired {
+                    return false;
+                }
+                info!("Circuit half-open for provider {}, sending probe", id);
+                if let Err(e) = cache
+                    .set_circuit_breaker_state(&id, CircuitState::HalfOpen, None)
+                    .await
+                {
+                    error!("Failed to update circuit breaker for {}: {}", id, e);
+                }
+                true
+            }
+        }
+    }
+
+    pub async fn record_success(&self, provider_id: Uuid) {
+        let id = provider_id.to_string();
+        let Some(cache) = self.cache.get().await else {
+            return;
+        };
+        if let Err(e) = cache.reset_circuit_breaker(&id).await {
+            error!("Failed to reset circuit breaker for provider {}: {}", id, e);
+            self.cache.on_error(&e);
+        }
+    }
+
+    pub async fn record_failure(&self, provider_id: Uuid) {
+        let id = provider_id.to_string();
+        let Some(cache) = self.cache.get().await else {
+            return;
+        };
+        let record = match cache.incr_circuit_breaker_failures(&id).await {
+            Ok(record) => record,
+            Err(e) => {
+                error!("Failed to record failure for provider {}: {}", id, e);
+                self.cache.on_error(&e);
+                return;
+            }
+        };
+        if !should_open(&record, self.failure_threshold) {
+            return;
+        }
+        if record.state != CircuitState::Open {
+            warn!(
+                "Opening circuit for provider {} after {} consecutive failures",
+                id, record.failures
+            );
+        }
+        if let Err(e) = cache
+            .set_circuit_breaker_state(
+                &id,
+                CircuitState::Open,
+                Some(chrono::Utc::now().timestamp()),
+            )
+            .await
+        {
+            error!("Failed to open circuit breaker for {}: {}", id, e);
+        }
+    }
+}
+
+fn admission(record: &CircuitBreakerRecord, now: i64, cool_down: Duration) -> Admission {
+    match record.state {
+        CircuitState::Closed => Admission::Allow,
+        CircuitState::Open | CircuitState::HalfOpen => {
+            let cooled_down = record
+                .opened_at
+                .is_none_or(|opened_at| now - opened_at >= cool_down.as_secs() as i64);
+            if cooled_down {
+                Admission::Probe
+            } else {
+                Admission::Reject
+            }
+        }
+    }
+}
+
+/// A failed half-open probe re-opens the circuit straight away.
+fn should_open(record: &CircuitBreakerRecord, failure_threshold: u32) -> bool {
+    record.state != CircuitState::Closed || record.failures >= failure_threshold
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(state: CircuitState, failures: u32, opened_at: Option<i64>) -> CircuitBreakerRecord {
+        CircuitBreakerRecord {
+            state,
+            failures,
+            opened_at,
+        }
+    }
+
+    #[test]
+    fn test_closed_circuit_allows_requests() {
+        let closed = record(CircuitState::Closed, 2, None);
+        assert_eq!(
+            admission(&closed, 1_000, Duration::from_secs(60)),
+            Admission::Allow
+        );
+    }
+
+    #[test]
+    fn test_open_circuit_rejects_until_cool_down() {
+        let open = record(CircuitState::Open, 5, Some(1_000));
+        let cool_down = Duration::from_secs(60);
+        assert_eq!(admission(&open, 1_030, cool_down), Admission::Reject);
+        assert_eq!(admission(&open, 1_060, cool_down), Admission::Probe);
+    }
+
+    #[test]
+    fn test_stale_half_open_circuit_allows_new_probe() {
+        let half_open = record(CircuitState::HalfOpen, 5, Some(1_000));
+        let cool_down = Duration::from_secs(60);
+        assert_eq!(admission(&half_open, 1_010, cool_down), Admission::Reject);
+        assert_eq!(admission(&half_open, 1_100, cool_down), Admission::Probe);
+    }
+
+    #[test]
+    fn test_circuit_opens_on_threshold_or_failed_probe() {
+        assert!(!should_open(&record(CircuitState::Closed, 4, None), 5));
+        assert!(should_open(&record(CircuitState::Closed, 5, None), 5));
+        assert!(should_open(
+            &record(CircuitState::HalfOpen, 1, Some(1_000)),
+            5
+        ));
+    }
+}
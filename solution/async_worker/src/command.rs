@@ -0,0 +1,8 @@
+use uuid::Uuid;
+
+use crate::error::CommandError;
+
+/// Parses an id given as an argument of the `replay`, `quarantine` or `credentials` commands.
+pub fn parse_id(value: &str) -> Result<Uuid, CommandError> {
+    Uuid::parse_str(value).map_err(|_| CommandError::InvalidArguments(format!("id: {}", value)))
+}
@@ -1,14 +1,222 @@
+use common::persist::TransactionScope;
 use dotenv::dotenv;
 use serde::Deserialize;
+use std::time::Duration;
+use storage::connections::db::PoolSettings;
 
 fn async_worker_interval_sec() -> u32 {
     300
 }
 
+fn scheduler_refresh_sec() -> u64 {
+    60
+}
+
+fn fetch_max_attempts() -> u32 {
+    4
+}
+
+fn fetch_base_delay_ms() -> u64 {
+    500
+}
+
+fn fetch_max_delay_ms() -> u64 {
+    10_000
+}
+
+fn fetch_jitter() -> bool {
+    true
+}
+
+fn fetch_connect_timeout_ms() -> u64 {
+    2_000
+}
+
+fn fetch_read_timeout_ms() -> u64 {
+    5_000
+}
+
+fn fetch_total_timeout_ms() -> u64 {
+    15_000
+}
+
+fn fetch_retryable_statuses() -> String {
+    "408,425,429,500,502,503,504".to_string()
+}
+
+fn fetch_honor_retry_after() -> bool {
+    true
+}
+
+fn fetch_max_body_bytes() -> u64 {
+    256 * 1024 * 1024
+}
+
+fn circuit_breaker_failure_threshold() -> u32 {
+    5
+}
+
+fn circuit_breaker_cool_down_sec() -> u64 {
+    60
+}
+
+fn payload_archive() -> String {
+    "directory".to_string()
+}
+
+fn payload_archive_dir() -> String {
+    "payload_archive".to_string()
+}
+
+fn payload_archive_retention_days() -> u32 {
+    7
+}
+
+fn db_pool_max_connections() -> u32 {
+    10
+}
+
+fn db_pool_min_connections() -> u32 {
+    1
+}
+
+fn db_pool_checkout_timeout_ms() -> u64 {
+    5000
+}
+
+fn db_pool_idle_timeout_sec() -> u64 {
+    600
+}
+
+fn persist_batch_size() -> usize {
+    50
+}
+
+fn persist_transaction_scope() -> TransactionScope {
+    TransactionScope::Batch
+}
+
+fn pagination_max_pages() -> usize {
+    100
+}
+
+fn pagination_max_duration_sec() -> u64 {
+    600
+}
+
+fn worker_control_server() -> String {
+    "127.0.0.1:8089".to_string()
+}
+
 #[derive(Deserialize)]
 pub struct Config {
+    /// Polling interval of providers without a schedule of their own.
     #[serde(default = "async_worker_interval_sec")]
     pub async_worker_interval_sec: u32,
+
+    /// How often the provider list is reloaded to pick up schedule changes.
+    #[serde(default = "scheduler_refresh_sec")]
+    pub scheduler_refresh_sec: u64,
+
+    #[serde(default = "fetch_max_attempts")]
+    pub fetch_max_attempts: u32,
+
+    #[serde(default = "fetch_base_delay_ms")]
+    pub fetch_base_delay_ms: u64,
+
+    #[serde(default = "fetch_max_delay_ms")]
+    pub fetch_max_delay_ms: u64,
+
+    #[serde(default = "fetch_jitter")]
+    pub fetch_jitter: bool,
+
+    #[serde(default = "fetch_connect_timeout_ms")]
+    pub fetch_connect_timeout_ms: u64,
+
+    #[serde(default = "fetch_read_timeout_ms")]
+    pub fetch_read_timeout_ms: u64,
+
+    #[serde(default = "fetch_total_timeout_ms")]
+    pub fetch_total_timeout_ms: u64,
+
+    /// Comma separated list of HTTP status codes that are worth retrying.
+    #[serde(default = "fetch_retryable_statuses")]
+    pub fetch_retryable_statuses: String,
+
+    #[serde(default = "fetch_honor_retry_after")]
+    pub fetch_honor_retry_after: bool,
+
+    /// Maximum size of a response body once decompressed.
+    #[serde(default = "fetch_max_body_bytes")]
+    pub fetch_max_body_bytes: u64,
+
+    #[serde(default = "circuit_breaker_failure_threshold")]
+    pub circuit_breaker_failure_threshold: u32,
+
+    #[serde(default = "circuit_breaker_cool_down_sec")]
+    pub circuit_breaker_cool_down_sec: u64,
+
+    #[serde(default = "db_pool_max_connections")]
+    pub db_pool_max_connections: u32,
+
+    #[serde(default = "db_pool_min_connections")]
+    pub db_pool_min_connections: u32,
+
+    #[serde(default = "db_pool_checkout_timeout_ms")]
+    pub db_pool_checkout_timeout_ms: u64,
+
+    /// Idle connections above the minimum are closed after this. `0` keeps them open.
+    #[serde(default = "db_pool_idle_timeout_sec")]
+    pub db_pool_idle_timeout_sec: u64,
+
+    /// Number of streamed base plans handed to persistence at once.
+    #[serde(default = "persist_batch_size")]
+    pub persist_batch_size: usize,
+
+    /// Scope of the persistence transactions: `batch` or `snapshot`.
+    #[serde(default = "persist_transaction_scope")]
+    pub persist_transaction_scope: TransactionScope,
+
+    /// Where fetched payloads are archived: `postgres`, `directory` or `none`.
+    #[serde(default = "payload_archive")]
+    pub payload_archive: String,
+
+    #[serde(default = "payload_archive_dir")]
+    pub payload_archive_dir: String,
+
+    /// Archived payloads older than this are deleted. `0` keeps them forever.
+    #[serde(default = "payload_archive_retention_days")]
+    pub payload_archive_retention_days: u32,
+
+    /// Default maximum number of pages of a paginated feed.
+    #[serde(default = "pagination_max_pages")]
+    pub pagination_max_pages: usize,
+
+    /// Default maximum time spent fetching the pages of a paginated feed.
+    #[serde(default = "pagination_max_duration_sec")]
+    pub pagination_max_duration_sec: u64,
+
+    /// Bind address of the control HTTP server. Empty disables it.
+    #[serde(default = "worker_control_server")]
+    pub worker_control_server: String,
+
+    /// Base64 AES-256 key of the provider credentials. Empty only allows anonymous feeds.
+    #[serde(default)]
+    pub credentials_key: String,
+}
+
+impl Config {
+    pub fn pool_settings(&self) -> PoolSettings {
+        PoolSettings {
+            max_connections: self.db_pool_max_connections,
+            min_connections: self.db_pool_min_connections,
+            checkout_timeout: Duration::from_millis(self.db_pool_checkout_timeout_ms),
+            idle_timeout: match self.db_pool_idle_timeout_sec {
+                0 => None,
+                seconds => Some(Duration::from_secs(seconds)),
+            },
+        }
+    }
 }
 
 pub fn build() -> Config {
@@ -0,0 +1,40 @@
+use crate::archive::PayloadArchiver;
+use crate::auth::CredentialStore;
+use crate::circuit_breaker::CircuitBreaker;
+use crate::fetch::FeedAccess;
+use crate::pagination::PaginationLimits;
+use crate::rate_limit::RateLimiter;
+use crate::retry::RetryPolicy;
+use crate::status::WorkerStatus;
+use common::persist::TransactionScope;
+use common::pools::Pools;
+use reqwest::Client;
+
+/// Shared state handed to every provider task.
+#[derive(Clone)]
+pub struct WorkerContext {
+    pub pools: Pools,
+    pub client: Client,
+    /// Client of the feeds that require credentials, which only follows same-origin redirects.
+    pub authenticated_client: Client,
+    pub retry_policy: RetryPolicy,
+    pub circuit_breaker: CircuitBreaker,
+    pub rate_limiter: RateLimiter,
+    pub pagination_limits: PaginationLimits,
+    pub max_body_bytes: u64,
+    pub persist_batch_size: usize,
+    pub transaction_scope: TransactionScope,
+    pub archive: PayloadArchiver,
+    pub status: WorkerStatus,
+    pub credentials: CredentialStore,
+}
+
+impl WorkerContext {
+    /// Client of a feed request, depending on whether it carries credentials.
+    pub fn client_for(&self, access: FeedAccess<'_>) -> &Client {
+        match access.credentials {
+            Some(_) => &self.authenticated_client,
+            None => &self.client,
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use actix_web::{web, App, HttpResponse, HttpServer, Responder};
+use common::pools::Pools;
+use serde::Serialize;
+use std::time::Duration;
+use storage::error::StorageError;
+use storage::ingestion_run::{get_last_ingestion_run, get_last_successful_ingestion_run};
+use storage::models::ingestion_runs::{IngestionRun, IngestionRunStatus};
+use uuid::Uuid;
+
+use crate::status::{ProviderStatus, WorkerStatus};
+
+/// How long the readiness probe waits for a Redis connection.
+const CACHE_CHECK_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Serialize)]
+struct HealthResponse {
+    status: &'static str,
+}
+
+#[derive(Serialize)]
+struct ReadinessResponse {
+    status: &'static str,
+    database: bool,
+    cache: bool,
+    providers_loaded: bool,
+}
+
+/// Live state of a provider with its last successful and failed runs.
+#[derive(Serialize)]
+struct ProviderReport {
+    #[serde(flatten)]
+    status: ProviderStatus,
+    last_success: Option<IngestionRun>,
+    last_failure: Option<IngestionRun>,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// Starts the control server on `bind` and runs it until the process exits.
+pub fn spawn(bind: &str, status: WorkerStatus, pools: Pools) -> std::io::Result<()> {
+    let status = web::Data::new(status);
+    let pools = web::Data::new(pools);
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(status.clone())
+            .app_data(pools.clone())
+            .configure(configure)
+    })
+    .disable_signals()
+    .workers(1)
+    .bind(bind)?
+    .run();
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            log::error!("Control server stopped: {}", e);
+        }
+    });
+    Ok(())
+}
+
+/// Registers the control routes:
+/// - `GET /health/live`: the process is up.
+/// - `GET /health/ready`: Postgres is reachable and the providers were loaded.
+/// - `GET /providers` and `GET /providers/{id}`: cycle state and last runs per provider.
+/// - `POST /providers/{id}/sync`: runs a provider now, outside its schedule.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/health/live").route(web::get().to(get_liveness)));
+    cfg.service(web::resource("/health/ready").route(web::get().to(get_readiness)));
+    cfg.service(web::resource("/providers").route(web::get().to(list_providers)));
+    cfg.service(web::resource("/providers/{id}").route(web::get().to(get_provider)));
+    cfg.service(web::resource("/providers/{id}/sync").route(web::post().to(sync_provider)));
+}
+
+async fn get_liveness() -> impl Responder {
+    HttpResponse::Ok().json(HealthResponse { status: "ok" })
+}
+
+async fn get_readiness(status: web::Data<WorkerStatus>, pools: web::Data<Pools>) -> HttpResponse {
+    let database = pools.db_connection().await.is_some();
+    // Redis being down only disables caching, so it does not make the worker unready. A
+    // reconnection that hangs must not hang the probe either.
+    let cache = tokio::time::timeout(CACHE_CHECK_TIMEOUT, pools.cache.get())
+        .await
+        .is_ok_and(|cache| cache.is_some());
+    let providers_loaded = status.providers_loaded();
+    let ready = database && providers_loaded;
+    let body = ReadinessResponse {
+        status: if ready { "ready" } else { "not_ready" },
+        database,
+        cache,
+        providers_loaded,
+    };
+    if ready {
+        HttpResponse::Ok().json(body)
+    } else {
+        HttpResponse::ServiceUnavailable().json(body)
+    }
+}
+
+async fn list_providers(status: web::Data<WorkerStatus>, pools: web::Data<Pools>) -> HttpResponse {
+    let statuses = status.list();
+    match reports(&pools, statuses).await {
+        Ok(reports) => HttpResponse::Ok().json(reports),
+        Err(e) => internal_error(e.to_string()),
+    }
+}
+
+async fn get_provider(
+    path: web::Path<String>,
+    status: web::Data<WorkerStatus>,
+    pools: web::Data<Pools>,
+) -> HttpResponse {
+    let provider_id = match parse_provider_id(&path) {
+        Ok(provider_id) => provider_id,
+        Err(response) => return response,
+    };
+    let Some(provider_status) = status.get(provider_id) else {
+        return not_found(provider_id);
+    };
+    match reports(&pools, vec![provider_status]).await {
+        Ok(mut reports) => HttpResponse::Ok().json(reports.remove(0)),
+        Err(e) => internal_error(e.to_string()),
+    }
+}
+
+async fn sync_provider(path: web::Path<String>, status: web::Data<WorkerStatus>) -> HttpResponse {
+    let provider_id = match parse_provider_id(&path) {
+        Ok(provider_id) => provider_id,
+        Err(response) => return response,
+    };
+    if !status.trigger(provider_id) {
+        return not_found(provider_id);
+    }
+    log::info!("Sync of provider {} requested", provider_id);
+    HttpResponse::Accepted().json(HealthResponse { status: "queued" })
+}
+
+/// Adds the last successful and failed runs of each provider, read from `ingestion_runs`.
+async fn reports(
+    pools: &Pools,
+    statuses: Vec<ProviderStatus>,
+) -> Result<Vec<ProviderReport>, StorageError> {
+    let mut conn = pools
+        .db_connection()
+        .await
+        .ok_or_else(|| StorageError::PoolError("Failed to get DB connection".to_string()))?;
+    let mut reports = Vec::with_capacity(statuses.len());
+    for status in statuses {
+        let provider_id = status.providers_id;
+        reports.push(ProviderReport {
+            last_success: get_last_successful_ingestion_run(&mut conn, provider_id).await?,
+            last_failure: get_last_ingestion_run(
+                &mut conn,
+                provider_id,
+                &[IngestionRunStatus::Failed],
+            )
+            .await?,
+            status,
+        });
+    }
+    Ok(reports)
+}
+
+fn parse_provider_id(value: &str) -> Result<Uuid, HttpResponse> {
+    Uuid::parse_str(value).map_err(|_| {
+        HttpResponse::BadRequest().json(ErrorResponse {
+            error: format!("Invalid provider id: {}", value),
+        })
+    })
+}
+
+fn not_found(provider_id: Uuid) -> HttpResponse {
+    HttpResponse::NotFound().json(ErrorResponse {
+        error: format!("Provider {} is not scheduled", provider_id),
+    })
+}
+
+fn internal_error(message: String) -> HttpResponse {
+    log::error!("Control request failed: {}", message);
+    HttpResponse::InternalServerError().json(ErrorResponse { error: message })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::provider;
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use common::pools::SharedCache;
+    use storage::connections::db::establish_connection;
+
+    #[actix_web::test]
+    async fn test_sync_triggers_scheduled_providers_only() {
+        let status = WorkerStatus::default();
+        let provider = provider("Control test");
+        let provider_id = provider.providers_id;
+        let trigger = status.register(&provider);
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(status))
+                .configure(configure),
+        )
+        .await;
+
+        let sync = |id: String| {
+            test::TestRequest::post()
+                .uri(&format!("/providers/{}/sync", id))
+                .to_request()
+        };
+        let response = test::call_service(&app, sync(provider_id.to_string())).await;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        tokio::time::timeout(std::time::Duration::from_secs(1), trigger.notified())
+            .await
+            .expect("Expected a trigger");
+
+        let response = test::call_service(&app, sync(Uuid::new_v4().to_string())).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = test::call_service(&app, sync("not-a-uuid".to_string())).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = test::call_service(
+            &app,
+            test::TestRequest::get().uri("/health/live").to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_readiness_does_not_wait_for_an_unresponsive_cache() {
+        // Accepts connections but never answers the Redis handshake
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let redis_url = format!("redis://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+        let pools = Pools {
+            db: establish_connection().await,
+            cache: SharedCache::new(redis_url),
+        };
+        let status = WorkerStatus::default();
+        status.set_providers_loaded();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(status))
+                .app_data(web::Data::new(pools))
+                .configure(configure),
+        )
+        .await;
+
+        let request = test::TestRequest::get().uri("/health/ready").to_request();
+        let response =
+            tokio::time::timeout(CACHE_CHECK_TIMEOUT * 5, test::call_service(&app, request))
+                .await
+                .expect("Expected the probe to answer");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(body["cache"], false);
+    }
+}
@@ -0,0 +1,242 @@
+use common::error::{DateError, PersistPlansError, ZoneError};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum FetchError {
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+    #[error("Connection error: {0}")]
+    Connect(String),
+    #[error("Timeout error: {0}")]
+    Timeout(String),
+    #[error("Unexpected HTTP status: {0}")]
+    Status(u16),
+    #[error("Failed to read response body: {0}")]
+    Body(String),
+    #[error("Request error: {0}")]
+    Request(String),
+    #[error("Authentication error: {0}")]
+    Auth(String),
+    #[error("Response body exceeds {0} bytes")]
+    BodyTooLarge(u64),
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_builder() {
+            FetchError::InvalidUrl(err.to_string())
+        } else if err.is_timeout() {
+            FetchError::Timeout(err.to_string())
+        } else if err.is_connect() {
+            FetchError::Connect(err.to_string())
+        } else if err.is_body() || err.is_decode() {
+            FetchError::Body(err.to_string())
+        } else if let Some(status) = err.status() {
+            FetchError::Status(status.as_u16())
+        } else {
+            FetchError::Request(err.to_string())
+        }
+    }
+}
+
+impl FetchError {
+    /// Short machine readable kind, stored as the `error_kind` of ingestion runs.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FetchError::InvalidUrl(_) => "invalid_url",
+            FetchError::Connect(_) => "connect",
+            FetchError::Timeout(_) => "timeout",
+            FetchError::Status(_) => "http_status",
+            FetchError::Body(_) => "body",
+            FetchError::Request(_) => "request",
+            FetchError::Auth(_) => "auth",
+            FetchError::BodyTooLarge(_) => "body_too_large",
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AdapterError {
+    #[error("Unknown provider adapter: {0}")]
+    UnknownAdapter(String),
+    #[error("Invalid adapter configuration: {0}")]
+    InvalidConfig(String),
+    #[error(transparent)]
+    Fetch(#[from] FetchError),
+    #[error("Failed to parse payload: {0}")]
+    Parse(String),
+    #[error("Pagination error: {0}")]
+    Pagination(String),
+}
+
+/// Why a single provider record was rejected. Stored as the `reason` of quarantined records.
+#[derive(Debug, Clone, PartialEq, Error, Serialize, Deserialize)]
+#[error("{kind}: {message}")]
+pub struct RecordError {
+    pub kind: RecordErrorKind,
+    /// Offending field, when it is known.
+    pub field: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordErrorKind {
+    MissingField,
+    InvalidValue,
+    Malformed,
+}
+
+impl std::fmt::Display for RecordErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordErrorKind::MissingField => write!(f, "missing field"),
+            RecordErrorKind::InvalidValue => write!(f, "invalid value"),
+            RecordErrorKind::Malformed => write!(f, "malformed record"),
+        }
+    }
+}
+
+impl From<DateError> for RecordError {
+    fn from(err: DateError) -> Self {
+        RecordError::invalid_value(&err.field, err.message)
+    }
+}
+
+impl From<ZoneError> for RecordError {
+    fn from(err: ZoneError) -> Self {
+        RecordError::invalid_value(&err.field, err.message)
+    }
+}
+
+/// Classified by the category of the error: a record that is not well-formed JSON is malformed.
+impl From<serde_json::Error> for RecordError {
+    fn from(err: serde_json::Error) -> Self {
+        let kind = match err.classify() {
+            serde_json::error::Category::Data => RecordErrorKind::InvalidValue,
+            _ => RecordErrorKind::Malformed,
+        };
+        RecordError {
+            kind,
+            field: None,
+            message: err.to_string(),
+        }
+    }
+}
+
+/// XML records are read as optional text, so the deserializer only fails on malformed ones.
+impl From<quick_xml::DeError> for RecordError {
+    fn from(err: quick_xml::DeError) -> Self {
+        RecordError {
+            kind: RecordErrorKind::Malformed,
+            field: None,
+            message: err.to_string(),
+        }
+    }
+}
+
+impl RecordError {
+    pub fn missing_field(field: &str) -> Self {
+        RecordError {
+            kind: RecordErrorKind::MissingField,
+            field: Some(field.to_string()),
+            message: format!("missing field `{}`", field),
+        }
+    }
+
+    pub fn invalid_value(field: &str, message: String) -> Self {
+        RecordError {
+            kind: RecordErrorKind::InvalidValue,
+            field: Some(field.to_string()),
+            message,
+        }
+    }
+}
+
+impl AdapterError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AdapterError::UnknownAdapter(_) => "unknown_adapter",
+            AdapterError::InvalidConfig(_) => "invalid_config",
+            AdapterError::Fetch(e) => e.kind(),
+            AdapterError::Parse(_) => "parse",
+            AdapterError::Pagination(_) => "pagination",
+        }
+    }
+}
+
+impl From<std::io::Error> for AdapterError {
+    fn from(err: std::io::Error) -> Self {
+        AdapterError::Parse(err.to_string())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ScheduleError {
+    #[error("Invalid schedule interval: {0}")]
+    InvalidInterval(i32),
+    #[error("Invalid cron expression {0}: {1}")]
+    InvalidCron(String, String),
+}
+
+#[derive(Debug, Error)]
+pub enum ArchiveError {
+    #[error("Unknown payload archive: {0}")]
+    UnknownArchive(String),
+    #[error("Archived payload not found: {0}")]
+    NotFound(String),
+    #[error("Database error: {0}")]
+    Db(String),
+    #[error("Failed to access archived payload: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Fetch(#[from] FetchError),
+}
+
+#[derive(Debug, Error)]
+pub enum IngestError {
+    #[error(transparent)]
+    Adapter(#[from] AdapterError),
+    #[error("Failed to persist base plans: {0}")]
+    Persist(#[from] PersistPlansError),
+    #[error("Failed to quarantine records: {0}")]
+    Quarantine(String),
+}
+
+impl IngestError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            IngestError::Adapter(e) => e.kind(),
+            IngestError::Persist(_) => "persist",
+            IngestError::Quarantine(_) => "quarantine",
+        }
+    }
+
+    /// HTTP status of the response the run failed on, if it failed on an unexpected one.
+    pub fn http_status(&self) -> Option<u16> {
+        match self {
+            IngestError::Adapter(AdapterError::Fetch(FetchError::Status(status))) => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+/// Errors of the `replay`, `quarantine` and `credentials` commands.
+#[derive(Debug, Error)]
+pub enum CommandError {
+    #[error("Invalid arguments: {0}")]
+    InvalidArguments(String),
+    #[error("Provider not found: {0}")]
+    ProviderNotFound(String),
+    #[error("Database error: {0}")]
+    Db(String),
+    #[error("Credentials error: {0}")]
+    Credentials(String),
+    #[error(transparent)]
+    Archive(#[from] ArchiveError),
+    #[error(transparent)]
+    Adapter(#[from] AdapterError),
+    #[error(transparent)]
+    Ingest(#[from] IngestError),
+}
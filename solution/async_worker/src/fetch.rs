@@ -0,0 +1,593 @@
+use crate::auth::Credentials;
+use crate::config::Config;
+use crate::error::FetchError;
+use crate::rate_limit::ProviderRateLimit;
+use crate::retry::{parse_retry_after, RetryPolicy};
+use encoding_rs::{Encoding, UTF_8};
+use futures::StreamExt;
+use log::{debug, warn};
+use reqwest::header::{
+    HeaderName, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, LINK, RETRY_AFTER,
+};
+use reqwest::{redirect, Client, ClientBuilder, Response, StatusCode};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// Failed attempt, along with the delay requested by the provider (if any).
+struct FailedAttempt {
+    error: FetchError,
+    retry_after: Option<Duration>,
+}
+
+impl From<FetchError> for FailedAttempt {
+    fn from(error: FetchError) -> Self {
+        FailedAttempt {
+            error,
+            retry_after: None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for FailedAttempt {
+    fn from(err: reqwest::Error) -> Self {
+        FailedAttempt::from(FetchError::from(err))
+    }
+}
+
+/// Maximum number of redirects followed by a request, as with the default reqwest policy.
+const MAX_REDIRECTS: usize = 10;
+
+/// Builds the HTTP client shared by every provider task, with connect/read/total timeouts.
+pub fn build_client(config: &Config) -> Result<Client, FetchError> {
+    client_builder(config)
+        .build()
+        .map_err(|e| FetchError::Request(e.to_string()))
+}
+
+/// Builds the HTTP client of the feeds that require credentials. It only follows redirects
+/// within the origin of the feed: reqwest strips `Authorization` on cross-host redirects, but
+/// would forward credentials sent in other headers, e.g. an API key.
+pub fn build_authenticated_client(config: &Config) -> Result<Client, FetchError> {
+    client_builder(config)
+        .redirect(same_origin_redirects())
+        .build()
+        .map_err(|e| FetchError::Request(e.to_string()))
+}
+
+fn client_builder(config: &Config) -> ClientBuilder {
+    Client::builder()
+        .connect_timeout(Duration::from_millis(config.fetch_connect_timeout_ms))
+        .read_timeout(Duration::from_millis(config.fetch_read_timeout_ms))
+        .timeout(Duration::from_millis(config.fetch_total_timeout_ms))
+}
+
+/// Follows redirects to the origin of the first request only. Other redirects are returned
+/// as is, and fail the fetch with their status.
+pub fn same_origin_redirects() -> redirect::Policy {
+    redirect::Policy::custom(|attempt| {
+        let same_origin = attempt
+            .previous()
+            .first()
+            .is_some_and(|first| first.origin() == attempt.url().origin());
+        if attempt.previous().len() > MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else if same_origin {
+            attempt.follow()
+        } else {
+            attempt.stop()
+        }
+    })
+}
+
+/// Validators of the last persisted payload, sent back as conditional request headers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Response body spooled to an anonymous temporary file, so it can be hashed before it is
+/// parsed without holding the whole feed in memory.
+#[derive(Debug)]
+pub struct Payload {
+    pub file: File,
+    pub size: u64,
+    /// Hex encoded SHA-256 of the body.
+    pub content_hash: String,
+    pub validators: Validators,
+    /// HTTP status of the response.
+    pub status: u16,
+    /// `rel="next"` URL of the `Link` header, as sent by paginated feeds.
+    pub next_link: Option<String>,
+}
+
+impl Payload {
+    /// Spools an in-memory body, e.g. one loaded back from the payload archive.
+    pub async fn from_bytes(body: &[u8], status: u16) -> Result<Self, FetchError> {
+        let io_error = |e: std::io::Error| FetchError::Body(e.to_string());
+        let mut file = File::from_std(tempfile::tempfile().map_err(io_error)?);
+        file.write_all(body).await.map_err(io_error)?;
+        file.flush().await.map_err(io_error)?;
+        file.rewind().await.map_err(io_error)?;
+        Ok(Payload {
+            file,
+            size: body.len() as u64,
+            content_hash: content_hash(body),
+            validators: Validators::default(),
+            status,
+            next_link: None,
+        })
+    }
+
+    /// Reads the whole body, leaving the file positioned at its start.
+    pub async fn read_bytes(&mut self) -> Result<Vec<u8>, FetchError> {
+        let io_error = |e: std::io::Error| FetchError::Body(e.to_string());
+        let mut body = Vec::with_capacity(self.size as usize);
+        self.file.read_to_end(&mut body).await.map_err(io_error)?;
+        self.file.rewind().await.map_err(io_error)?;
+        Ok(body)
+    }
+
+    /// Reads the whole body as text, decoded from the encoding of its byte order mark if it
+    /// has one, and from UTF-8 otherwise. Malformed text fails rather than being replaced.
+    pub async fn into_string(mut self) -> Result<String, FetchError> {
+        let mut body = Vec::with_capacity(self.size as usize);
+        self.file
+            .read_to_end(&mut body)
+            .await
+            .map_err(|e| FetchError::Body(e.to_string()))?;
+        let encoding = Encoding::for_bom(&body).map_or(UTF_8, |(encoding, _)| encoding);
+        let (text, malformed) = encoding.decode_with_bom_removal(&body);
+        if malformed {
+            return Err(FetchError::Body(format!(
+                "Payload is not valid {}",
+                encoding.name()
+            )));
+        }
+        Ok(text.into_owned())
+    }
+}
+
+/// Per-provider policies applied to every request sent to its feed.
+#[derive(Clone, Copy, Default)]
+pub struct FeedAccess<'a> {
+    pub credentials: Option<&'a Credentials>,
+    pub rate_limit: Option<&'a ProviderRateLimit>,
+}
+
+/// Result of a conditional fetch.
+#[derive(Debug)]
+pub enum FetchOutcome<T = Payload> {
+    /// The provider answered `304 Not Modified`.
+    NotModified,
+    Fetched(T),
+}
+
+/// Fetches `url` conditionally on `validators`, retrying transient failures according to
+/// `policy`. The body is decompressed and downloaded to a temporary file while its hash is
+/// computed, failing once it exceeds `max_body_bytes`. An attempt only succeeds once the body
+/// is fully downloaded. Requests carry the provider credentials and wait for its rate limit;
+/// a rejected OAuth2 token is renewed once.
+pub async fn fetch_payload(
+    client: &Client,
+    url: &str,
+    policy: &RetryPolicy,
+    validators: &Validators,
+    access: FeedAccess<'_>,
+    max_body_bytes: u64,
+) -> Result<FetchOutcome, FetchError> {
+    let fetch = || {
+        with_retry(url, policy, || {
+            fetch_once(client, url, validators, access, max_body_bytes)
+        })
+    };
+    match (fetch().await, access.credentials) {
+        (Err(FetchError::Status(401)), Some(credentials)) if credentials.unauthorized() => {
+            warn!("Access token rejected by {}. Requesting a new one", url);
+            fetch().await
+        }
+        (result, _) => result,
+    }
+}
+
+async fn with_retry<T, F, Fut>(url: &str, policy: &RetryPolicy, mut op: F) -> Result<T, FetchError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, FailedAttempt>>,
+{
+    let mut attempt = 1;
+    loop {
+        let failed = match op().await {
+            Ok(value) => return Ok(value),
+            Err(failed) => failed,
+        };
+        if attempt >= policy.max_attempts || !policy.is_retryable(&failed.error) {
+            return Err(failed.error);
+        }
+        let delay = policy.delay_for(attempt, failed.retry_after);
+        warn!(
+            "Attempt {}/{} to fetch {} failed: {}. Retrying in {} ms",
+            attempt,
+            policy.max_attempts,
+            url,
+            failed.error,
+            delay.as_millis()
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+async fn send_once(
+    client: &Client,
+    url: &str,
+    validators: &Validators,
+    access: FeedAccess<'_>,
+) -> Result<Response, FailedAttempt> {
+    let mut request = client.get(url);
+    if let Some(etag) = &validators.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+    if let Some(rate_limit) = access.rate_limit {
+        rate_limit.acquire().await;
+    }
+    // Authorized once the rate limit let the request through, so a token does not age waiting
+    if let Some(credentials) = access.credentials {
+        request = credentials.authorize(client, request).await?;
+    }
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() && status != StatusCode::NOT_MODIFIED {
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| parse_retry_after(v, chrono::Utc::now()));
+        if let (StatusCode::TOO_MANY_REQUESTS, Some(rate_limit)) = (status, access.rate_limit) {
+            rate_limit.throttled(retry_after).await;
+        }
+        return Err(FailedAttempt {
+            error: FetchError::Status(status.as_u16()),
+            retry_after,
+        });
+    }
+    Ok(response)
+}
+
+async fn fetch_once(
+    client: &Client,
+    url: &str,
+    validators: &Validators,
+    access: FeedAccess<'_>,
+    max_body_bytes: u64,
+) -> Result<FetchOutcome, FailedAttempt> {
+    let response = send_once(client, url, validators, access).await?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        debug!("{} not modified", url);
+        return Ok(FetchOutcome::NotModified);
+    }
+    let validators = Validators {
+        etag: header_value(&response, ETAG),
+        last_modified: header_value(&response, LAST_MODIFIED),
+    };
+    let next_link = header_value(&response, LINK).and_then(|link| parse_next_link(&link));
+    let status = response.status().as_u16();
+    let mut payload = spool(response, validators, status, max_body_bytes).await?;
+    payload.next_link = next_link;
+    debug!("Fetched {} bytes from {}", payload.size, url);
+    Ok(FetchOutcome::Fetched(payload))
+}
+
+async fn spool(
+    response: Response,
+    validators: Validators,
+    status: u16,
+    max_body_bytes: u64,
+) -> Result<Payload, FetchError> {
+    // Announced lengths are checked upfront. Decompressed bodies have none, and are only
+    // checked while they are streamed, which also stops compression bombs.
+    if response
+        .content_length()
+        .is_some_and(|length| length > max_body_bytes)
+    {
+        return Err(FetchError::BodyTooLarge(max_body_bytes));
+    }
+    let io_error = |e: std::io::Error| FetchError::Body(e.to_string());
+    let mut file = File::from_std(tempfile::tempfile().map_err(io_error)?);
+    let mut hasher = Sha256::new();
+    let mut size = 0;
+    let mut body = response.bytes_stream();
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk?;
+        size += chunk.len() as u64;
+        if size > max_body_bytes {
+            return Err(FetchError::BodyTooLarge(max_body_bytes));
+        }
+        hasher.update(&chunk);
+        file.write_all(&chunk).await.map_err(io_error)?;
+    }
+    file.flush().await.map_err(io_error)?;
+    file.rewind().await.map_err(io_error)?;
+    Ok(Payload {
+        file,
+        size,
+        content_hash: hex(&hasher.finalize()),
+        validators,
+        status,
+        next_link: None,
+    })
+}
+
+/// URL of the `rel="next"` entry of a `Link` header, e.g. `<https://..?page=2>; rel="next"`.
+fn parse_next_link(link: &str) -> Option<String> {
+    link.split(',').find_map(|entry| {
+        let mut parts = entry.split(';');
+        let url = parts.next()?.trim().strip_prefix('<')?.strip_suffix('>')?;
+        parts
+            .any(|param| {
+                param.trim().strip_prefix("rel=").is_some_and(|rel| {
+                    rel.trim_matches('"')
+                        .split_whitespace()
+                        .any(|rel| rel.eq_ignore_ascii_case("next"))
+                })
+            })
+            .then(|| url.to_string())
+    })
+}
+
+fn header_value(response: &Response, name: HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Hex encoded SHA-256 of `body`.
+pub fn content_hash(body: &[u8]) -> String {
+    hex(&Sha256::digest(body))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::prelude::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            jitter: false,
+            retryable_statuses: vec![429, 503],
+            honor_retry_after: true,
+        }
+    }
+
+    const MAX_BODY_BYTES: u64 = 1024;
+
+    async fn fetch(
+        server: &MockServer,
+        validators: &Validators,
+    ) -> Result<FetchOutcome, FetchError> {
+        fetch_payload(
+            &Client::new(),
+            &server.url("/api/events"),
+            &policy(),
+            validators,
+            FeedAccess::default(),
+            MAX_BODY_BYTES,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_fetch_returns_payload_on_success() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/events");
+                then.status(200)
+                    .header("ETag", "\"v1\"")
+                    .header("Last-Modified", "Wed, 21 Oct 2015 07:28:00 GMT")
+                    .body("<planList/>");
+            })
+            .await;
+
+        let Ok(FetchOutcome::Fetched(payload)) = fetch(&server, &Validators::default()).await
+        else {
+            panic!("Expected a payload");
+        };
+        assert_eq!(payload.size, 11);
+        assert_eq!(payload.status, 200);
+        assert_eq!(payload.validators.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(
+            payload.validators.last_modified.as_deref(),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT")
+        );
+        assert_eq!(payload.content_hash, content_hash(b"<planList/>"));
+        assert_eq!(payload.into_string().await.unwrap(), "<planList/>");
+        mock.assert_hits_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn test_fetch_sends_validators_and_handles_not_modified() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/events")
+                    .header("If-None-Match", "\"v1\"")
+                    .header("If-Modified-Since", "Wed, 21 Oct 2015 07:28:00 GMT");
+                then.status(304);
+            })
+            .await;
+
+        let validators = Validators {
+            etag: Some("\"v1\"".to_string()),
+            last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+        };
+        let result = fetch(&server, &validators).await;
+        assert!(matches!(result, Ok(FetchOutcome::NotModified)));
+        mock.assert_hits_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn test_fetch_retries_retryable_status_until_max_attempts() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/events");
+                then.status(503).header("Retry-After", "0");
+            })
+            .await;
+
+        let result = fetch(&server, &Validators::default()).await;
+        assert!(matches!(result, Err(FetchError::Status(503))));
+        mock.assert_hits_async(3).await;
+    }
+
+    #[test]
+    fn test_parse_next_link() {
+        assert_eq!(
+            parse_next_link(
+                r#"<https://api.example.com/events?page=1>; rel="prev", <https://api.example.com/events?page=3>; rel="next""#
+            )
+            .as_deref(),
+            Some("https://api.example.com/events?page=3")
+        );
+        assert_eq!(
+            parse_next_link("</events?cursor=abc>; rel=next").as_deref(),
+            Some("/events?cursor=abc")
+        );
+        assert_eq!(
+            parse_next_link(r#"<https://api.example.com/events>; rel="last""#),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_does_not_retry_other_statuses() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/events");
+                then.status(404);
+            })
+            .await;
+
+        let result = fetch(&server, &Validators::default()).await;
+        assert!(matches!(result, Err(FetchError::Status(404))));
+        mock.assert_hits_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn test_fetch_decompresses_gzip_bodies() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"<planList/>").unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/events");
+                then.status(200)
+                    .header("Content-Encoding", "gzip")
+                    .body(compressed);
+            })
+            .await;
+
+        let Ok(FetchOutcome::Fetched(payload)) = fetch(&server, &Validators::default()).await
+        else {
+            panic!("Expected a payload");
+        };
+        assert_eq!(payload.size, 11);
+        assert_eq!(payload.into_string().await.unwrap(), "<planList/>");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rejects_bodies_over_the_limit() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/api/events");
+                then.status(200)
+                    .body("x".repeat(MAX_BODY_BYTES as usize + 1));
+            })
+            .await;
+
+        let result = fetch(&server, &Validators::default()).await;
+        assert!(matches!(
+            result,
+            Err(FetchError::BodyTooLarge(MAX_BODY_BYTES))
+        ));
+        mock.assert_hits_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn test_into_string_decodes_byte_order_mark() {
+        let body: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain("{\"título\": 1}".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        let payload = Payload::from_bytes(&body, 200).await.unwrap();
+        assert_eq!(payload.into_string().await.unwrap(), "{\"título\": 1}");
+
+        let payload = Payload::from_bytes(b"{\"t\xEDtulo\": 1}", 200)
+            .await
+            .unwrap();
+        assert!(matches!(
+            payload.into_string().await,
+            Err(FetchError::Body(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_retries_truncated_bodies() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use tokio::net::TcpListener;
+
+        // Announces a longer body than it sends, then closes the connection
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/api/events", listener.local_addr().unwrap());
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let accepted = attempts.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                accepted.fetch_add(1, Ordering::SeqCst);
+                let mut request = [0; 1024];
+                let _ = socket.read(&mut request).await;
+                let _ = socket
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 100\r\n\r\n<planList>")
+                    .await;
+            }
+        });
+
+        let result = fetch_payload(
+            &Client::new(),
+            &url,
+            &policy(),
+            &Validators::default(),
+            FeedAccess::default(),
+            MAX_BODY_BYTES,
+        )
+        .await;
+        assert!(matches!(result, Err(FetchError::Body(_))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+}
@@ -1,13 +1,46 @@
+use futures::StreamExt;
 use log::{debug, error, info};
-use quick_xml::de::from_str;
-use reqwest::Client;
-use storage::models::base_plans::NewBasePlan;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use storage::models::ingestion_runs::IngestionRunStatus;
+use storage::models::provider_fetch_states::{
+    FetchOutcome as ProviderFetchOutcome, NewProviderFetchState, ProviderFetchState,
+};
+use storage::models::providers::Provider;
+use storage::provider_fetch_state::{
+    get_provider_fetch_state, record_provider_fetch_outcome, save_provider_fetch_state,
+};
 use uuid::Uuid;
 
-use common::persist::persist_base_plans;
-use common::xml_models::PlanList;
+use common::persist::SnapshotWriter;
+use common::pools::Pools;
+use common::xml_models::BasePlan;
 
-pub async fn process_provider_events(provider_id: Uuid, provider_name: String, url: String) {
+use crate::adapters::{
+    adapter_for, date_parser_for, zone_parser_for, ParsedRecord, ProviderAdapter, RecordStream,
+};
+use crate::context::WorkerContext;
+use crate::error::{AdapterError, FetchError, IngestError};
+use crate::fetch::{FeedAccess, FetchOutcome, Payload, Validators};
+use crate::ingestion_run::RunRecorder;
+use crate::pagination::{PageRead, Pages, Pagination};
+use crate::quarantine::quarantine_records;
+
+/// Runs one ingestion of a provider and records it in `ingestion_runs`.
+pub async fn process_provider_events(context: WorkerContext, provider: Provider) {
+    let mut run = RunRecorder::start(provider.providers_id);
+    let result = ingest(&context, &provider, &mut run).await;
+    run.save(&context.pools, result).await;
+}
+
+async fn ingest(
+    context: &WorkerContext,
+    provider: &Provider,
+    run: &mut RunRecorder,
+) -> Result<IngestionRunStatus, IngestError> {
+    let provider_id = provider.providers_id;
+    let provider_name = provider.name.clone();
+    let url = provider.url.clone();
     info!(
         "Fetching events for provider: {} - {}",
         provider_id, provider_name
@@ -18,95 +51,505 @@ pub async fn process_provider_events(provider_id: Uuid, provider_name: String, u
             "Provider URL is empty for provider: {} - {}",
             provider_id, provider_name
         );
-        return;
+        return Err(invalid_url("empty URL".to_string()));
     }
     if !url.starts_with("http://") && !url.starts_with("https://") {
         error!(
             "Invalid URL format for provider: {} - {}. URL: {}",
             provider_id, provider_name, url
         );
-        return;
+        return Err(invalid_url(url));
     }
-    // Send request to the provider's URL
-    let client = Client::new();
-    let response = match client.get(&url).send().await {
-        Ok(resp) => resp,
+    // Resolve the adapter that speaks the provider's format
+    let adapter = match adapter_for(provider) {
+        Ok(adapter) => adapter,
         Err(e) => {
-            error!("Failed to fetch events from {}: {}", url, e);
-            return;
+            error!(
+                "Cannot process provider: {} - {}: {}",
+                provider_id, provider_name, e
+            );
+            return Err(e.into());
         }
     };
-    // Check if the response is successful
-    if !response.status().is_success() {
-        error!(
-            "Failed to fetch events from {}: HTTP {}",
-            url,
-            response.status()
-        );
-        return;
-    }
-    // Fetch the XML body
-    let xml_body = match response.text().await {
-        Ok(body) => body,
+    let dates = match date_parser_for(provider) {
+        Ok(dates) => dates,
+        Err(e) => {
+            error!(
+                "Cannot process provider: {} - {}: {}",
+                provider_id, provider_name, e
+            );
+            return Err(e.into());
+        }
+    };
+    let zones = match zone_parser_for(provider) {
+        Ok(zones) => zones,
         Err(e) => {
-            error!("Failed to read response body from {}: {}", url, e);
-            return;
+            error!(
+                "Cannot process provider: {} - {}: {}",
+                provider_id, provider_name, e
+            );
+            return Err(e.into());
         }
     };
-    // Parse XML into PlanList
-    let plan_list: PlanList = match from_str(&xml_body) {
-        Ok(pl) => pl,
+    let pagination = match Pagination::from_provider(provider) {
+        Ok(pagination) => pagination,
         Err(e) => {
-            error!("Failed to parse XML from {}: {}", url, e);
-            return;
+            error!(
+                "Cannot process provider: {} - {}: {}",
+                provider_id, provider_name, e
+            );
+            return Err(e.into());
         }
     };
-    // Map PlanList into Vec<NewBasePlan>
-    let events: Vec<NewBasePlan> = plan_list
-        .output
-        .base_plan
-        .iter()
-        .flat_map(|bp| {
-            let base_plan_id = bp.base_plan_id.clone().unwrap_or_default();
-            bp.plans.iter().map(move |_plan| NewBasePlan {
-                base_plans_id: uuid::Uuid::new_v4(),
+    // Load the credentials the provider feed requires, if any
+    let credentials = match context.credentials.load(&context.pools, provider_id).await {
+        Ok(credentials) => credentials,
+        Err(e) => {
+            error!(
+                "Cannot authenticate provider: {} - {}: {}",
+                provider_id, provider_name, e
+            );
+            return Err(AdapterError::Fetch(e).into());
+        }
+    };
+    let rate_limit = context.rate_limiter.for_provider(provider);
+    let access = FeedAccess {
+        credentials: credentials.as_ref(),
+        rate_limit: rate_limit.as_ref(),
+    };
+    let (feed, new_state) = match &pagination {
+        // Paginated feeds are fetched page by page as they are persisted, into a single
+        // snapshot
+        Some(pagination) => {
+            let new_state = NewProviderFetchState {
                 providers_id: provider_id,
-                event_base_id: base_plan_id.clone(),
-                title: bp.title.clone(),
-                sell_mode: bp
-                    .sell_mode
-                    .as_ref()
-                    .map(|e| e.to_string())
-                    .unwrap_or_default(),
-            })
-        })
-        .collect();
-
-    // Log the number of base plans fetched
-    debug!("Fetched {} base_plans from {}", events.len(), url);
+                etag: None,
+                last_modified: None,
+                content_hash: None,
+                last_outcome: ProviderFetchOutcome::Changed.to_string(),
+            };
+            (Feed::Paginated(pagination), new_state)
+        }
+        None => {
+            // Fetch the payload conditionally on the last persisted one, retrying transient
+            // failures
+            let fetch_state = load_fetch_state(&context.pools, provider_id).await;
+            let validators = fetch_state
+                .as_ref()
+                .map(|state| Validators {
+                    etag: state.etag.clone(),
+                    last_modified: state.last_modified.clone(),
+                })
+                .unwrap_or_default();
+            let mut payload = match adapter.fetch(context, &url, &validators, access).await {
+                Ok(FetchOutcome::Fetched(payload)) => {
+                    context.circuit_breaker.record_success(provider_id).await;
+                    payload
+                }
+                Ok(FetchOutcome::NotModified) => {
+                    context.circuit_breaker.record_success(provider_id).await;
+                    info!(
+                        "Feed not modified for provider: {} - {}",
+                        provider_id, provider_name
+                    );
+                    run.not_modified();
+                    record_fetch_outcome(
+                        &context.pools,
+                        provider_id,
+                        ProviderFetchOutcome::NotModified,
+                    )
+                    .await;
+                    return Ok(IngestionRunStatus::NotModified);
+                }
+                Err(e) => {
+                    error!("Failed to fetch events from {}: {}", url, e);
+                    if let AdapterError::Fetch(_) = e {
+                        context.circuit_breaker.record_failure(provider_id).await;
+                    }
+                    return Err(e.into());
+                }
+            };
+            run.fetched(&payload);
+            let unchanged = fetch_state
+                .as_ref()
+                .and_then(|state| state.content_hash.as_deref())
+                .is_some_and(|hash| hash == payload.content_hash);
+            if unchanged {
+                info!(
+                    "Feed unchanged for provider: {} - {}",
+                    provider_id, provider_name
+                );
+                record_fetch_outcome(&context.pools, provider_id, ProviderFetchOutcome::Unchanged)
+                    .await;
+                return Ok(IngestionRunStatus::Unchanged);
+            }
+            let new_state = NewProviderFetchState {
+                providers_id: provider_id,
+                etag: payload.validators.etag.clone(),
+                last_modified: payload.validators.last_modified.clone(),
+                content_hash: Some(payload.content_hash.clone()),
+                last_outcome: ProviderFetchOutcome::Changed.to_string(),
+            };
+            let archive_id = archive_payload(context, provider, &mut payload).await;
+            (
+                Feed::Single {
+                    payload: Box::new(payload),
+                    archive_id,
+                },
+                new_state,
+            )
+        }
+    };
 
-    // Persist base plans to the database
-    log::debug!(
-        "Persisting base plans for provider: {} - {}",
-        provider_id,
-        provider_name
-    );
-    if let Err(e) = persist_base_plans(
-        plan_list.output.base_plan,
+    let mut writer = match SnapshotWriter::begin(
+        &context.pools,
         provider_id,
         provider_name.clone(),
+        dates,
+        zones,
+        context.transaction_scope,
     )
     .await
     {
-        error!(
-            "Failed to persist base plans for provider: {} - {}: {:?}",
-            provider_id, provider_name, e
+        Ok(writer) => writer,
+        Err(e) => {
+            error!(
+                "Failed to start snapshot for provider: {} - {}: {}",
+                provider_id, provider_name, e
+            );
+            return Err(e.into());
+        }
+    };
+    let result = match feed {
+        Feed::Single {
+            payload,
+            archive_id,
+        } => {
+            persist_payload(
+                context,
+                adapter.as_ref(),
+                *payload,
+                provider,
+                archive_id,
+                &mut writer,
+            )
+            .await
+        }
+        Feed::Paginated(pagination) => {
+            persist_pages(
+                context,
+                adapter.as_ref(),
+                pagination,
+                access,
+                provider,
+                &mut writer,
+                run,
+            )
+            .await
+        }
+    };
+    let stats = match result {
+        Ok(stats) => stats,
+        Err(e) => {
+            error!(
+                "Failed to process events for provider: {} - {}: {}",
+                provider_id, provider_name, e
+            );
+            run.persisted(writer.stats());
+            return Err(e);
+        }
+    };
+    run.parsed(stats.parse_duration);
+    run.quarantined(stats.quarantined);
+
+    // Log the number of base plans fetched
+    debug!(
+        "Fetched {} base_plans from {}, {} records quarantined",
+        stats.persisted, url, stats.quarantined
+    );
+    if stats.quarantined > 0 {
+        log::warn!(
+            "Quarantined {} invalid records for provider: {} - {}",
+            stats.quarantined,
+            provider_id,
+            provider_name
         );
-        return;
     }
+    // An empty feed is more likely a provider glitch than every plan being withdrawn
+    let mark_unseen = stats.persisted > 0;
+    if !mark_unseen {
+        log::warn!(
+            "No base plans found for provider: {} - {}, keeping plan availability",
+            provider_id,
+            provider_name
+        );
+    }
+    let committed = writer.stats();
+    match writer.finish(mark_unseen).await {
+        Ok(persisted) => {
+            if mark_unseen {
+                info!(
+                    "{} plans and {} zones dropped out of the feed of provider: {} - {}",
+                    persisted.unavailable_plans,
+                    persisted.unavailable_zones,
+                    provider_id,
+                    provider_name
+                );
+            }
+            run.persisted(persisted);
+        }
+        Err(e) => {
+            error!(
+                "Failed to complete snapshot for provider: {} - {}: {}",
+                provider_id, provider_name, e
+            );
+            run.persisted(committed);
+            return Err(e.into());
+        }
+    }
+    // Only remember the payload once it is fully persisted, so a failed run is retried
+    save_fetch_state(&context.pools, new_state).await;
     debug!(
         "Successfully processed events for provider: {} - {}",
-        provider_id,
-        provider_name.clone()
+        provider_id, provider_name
     );
+    Ok(IngestionRunStatus::Succeeded)
+}
+
+fn invalid_url(message: String) -> IngestError {
+    AdapterError::Fetch(FetchError::InvalidUrl(message)).into()
+}
+
+/// What a run persists: the payload of a feed returned in a single response, or the pages of
+/// a paginated one, which are fetched as they are persisted.
+enum Feed<'a> {
+    Single {
+        payload: Box<Payload>,
+        archive_id: Option<Uuid>,
+    },
+    Paginated(&'a Pagination),
+}
+
+/// Outcome of persisting a payload.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct IngestStats {
+    /// Records read from the payload, valid or rejected.
+    pub records: usize,
+    pub persisted: usize,
+    /// Distinct records quarantined.
+    pub quarantined: usize,
+    /// Time spent reading and parsing the records, persistence excluded.
+    pub parse_duration: Duration,
+}
+
+/// Archives a raw payload before it is parsed, so bad data can be inspected and replayed.
+async fn archive_payload(
+    context: &WorkerContext,
+    provider: &Provider,
+    payload: &mut Payload,
+) -> Option<Uuid> {
+    match context
+        .archive
+        .store(&context.pools, provider.providers_id, payload)
+        .await
+    {
+        Ok(entry) => entry.map(|entry| entry.payload_archives_id),
+        Err(e) => {
+            error!(
+                "Failed to archive payload for provider: {} - {}: {}",
+                provider.providers_id, provider.name, e
+            );
+            None
+        }
+    }
+}
+
+/// Fetches, archives and persists the pages of a paginated feed one after the other, into the
+/// same snapshot writer. Each page is parsed once, which tells where the next page is.
+async fn persist_pages(
+    context: &WorkerContext,
+    adapter: &dyn ProviderAdapter,
+    pagination: &Pagination,
+    access: FeedAccess<'_>,
+    provider: &Provider,
+    writer: &mut SnapshotWriter,
+    run: &mut RunRecorder,
+) -> Result<IngestStats, IngestError> {
+    let provider_id = provider.providers_id;
+    let mut pages = Pages::new(&provider.url, pagination, &context.pagination_limits)?;
+    // Conditional requests are only meaningful for a whole feed, not for a page of it
+    let validators = Validators::default();
+    let mut stats = IngestStats::default();
+    let mut quarantined = HashSet::new();
+    while let Some(page_url) = pages.next_url()? {
+        let fetch = adapter.fetch(context, &page_url, &validators, access);
+        let mut payload = match pages.fetch(&page_url, fetch).await {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!("Failed to fetch events from {}: {}", page_url, e);
+                if let AdapterError::Fetch(_) = e {
+                    context.circuit_breaker.record_failure(provider_id).await;
+                }
+                return Err(e.into());
+            }
+        };
+        run.fetched(&payload);
+        let next_link = payload.next_link.clone();
+        let archive_id = archive_payload(context, provider, &mut payload).await;
+        let parse_started = Instant::now();
+        let (records, cursor) = match pages.cursor_path() {
+            Some(cursor_path) => adapter.stream_page(payload, cursor_path).await?,
+            None => (adapter.stream(payload).await?, None),
+        };
+        let parsed = parse_started.elapsed();
+        let page = persist_records(
+            context,
+            records,
+            provider,
+            archive_id,
+            writer,
+            &mut quarantined,
+        )
+        .await?;
+        stats.records += page.records;
+        stats.persisted += page.persisted;
+        stats.quarantined += page.quarantined;
+        stats.parse_duration += parsed + page.parse_duration;
+        let page = PageRead {
+            records: page.records,
+            cursor,
+            next_link,
+        };
+        pages.advance(&page_url, page)?;
+    }
+    context.circuit_breaker.record_success(provider_id).await;
+    Ok(stats)
+}
+
+/// Parses a payload and persists its base plans as they are parsed, in bounded batches.
+/// Records that fail to parse are quarantined instead of failing the whole payload.
+pub async fn persist_payload(
+    context: &WorkerContext,
+    adapter: &dyn ProviderAdapter,
+    payload: Payload,
+    provider: &Provider,
+    archive_id: Option<Uuid>,
+    writer: &mut SnapshotWriter,
+) -> Result<IngestStats, IngestError> {
+    let parse_started = Instant::now();
+    let records = adapter.stream(payload).await?;
+    let parsed = parse_started.elapsed();
+    let mut quarantined = HashSet::new();
+    let mut stats = persist_records(
+        context,
+        records,
+        provider,
+        archive_id,
+        writer,
+        &mut quarantined,
+    )
+    .await?;
+    stats.parse_duration += parsed;
+    Ok(stats)
+}
+
+async fn persist_records(
+    context: &WorkerContext,
+    mut records: RecordStream,
+    provider: &Provider,
+    archive_id: Option<Uuid>,
+    writer: &mut SnapshotWriter,
+    quarantined: &mut HashSet<String>,
+) -> Result<IngestStats, IngestError> {
+    debug!(
+        "Persisting base plans for provider: {} - {}",
+        provider.providers_id, provider.name
+    );
+    let mut batch = Vec::with_capacity(context.persist_batch_size);
+    let mut rejected = Vec::new();
+    let mut stats = IngestStats::default();
+    loop {
+        let parse_started = Instant::now();
+        let Some(next) = records.next().await else {
+            break;
+        };
+        stats.parse_duration += parse_started.elapsed();
+        stats.records += 1;
+        match next? {
+            ParsedRecord::Valid(base_plan) => batch.push(base_plan),
+            ParsedRecord::Rejected(record) => rejected.push(record),
+        }
+        if batch.len() + rejected.len() >= context.persist_batch_size {
+            stats.persisted += persist_batch(writer, std::mem::take(&mut batch)).await?;
+            stats.quarantined += quarantine_records(
+                &context.pools,
+                provider.providers_id,
+                archive_id,
+                std::mem::take(&mut rejected),
+                quarantined,
+            )
+            .await?;
+        }
+    }
+    stats.persisted += persist_batch(writer, batch).await?;
+    stats.quarantined += quarantine_records(
+        &context.pools,
+        provider.providers_id,
+        archive_id,
+        rejected,
+        quarantined,
+    )
+    .await?;
+    Ok(stats)
+}
+
+async fn persist_batch(
+    writer: &mut SnapshotWriter,
+    batch: Vec<BasePlan>,
+) -> Result<usize, IngestError> {
+    if batch.is_empty() {
+        return Ok(0);
+    }
+    let persisted = batch.len();
+    writer.persist(batch).await?;
+    Ok(persisted)
+}
+
+async fn load_fetch_state(pools: &Pools, provider_id: Uuid) -> Option<ProviderFetchState> {
+    let mut conn = pools.db_connection().await?;
+    match get_provider_fetch_state(&mut conn, provider_id).await {
+        Ok(state) => state,
+        Err(e) => {
+            error!(
+                "Failed to load fetch state for provider {}: {}",
+                provider_id, e
+            );
+            None
+        }
+    }
+}
+
+async fn save_fetch_state(pools: &Pools, new_state: NewProviderFetchState) {
+    let provider_id = new_state.providers_id;
+    let Some(mut conn) = pools.db_connection().await else {
+        return;
+    };
+    if let Err(e) = save_provider_fetch_state(&mut conn, new_state).await {
+        error!(
+            "Failed to save fetch state for provider {}: {}",
+            provider_id, e
+        );
+    }
+}
+
+async fn record_fetch_outcome(pools: &Pools, provider_id: Uuid, outcome: ProviderFetchOutcome) {
+    let Some(mut conn) = pools.db_connection().await else {
+        return;
+    };
+    if let Err(e) = record_provider_fetch_outcome(&mut conn, provider_id, outcome).await {
+        error!(
+            "Failed to record fetch outcome for provider {}: {}",
+            provider_id, e
+        );
+    }
 }
@@ -0,0 +1,167 @@
+use chrono::{NaiveDateTime, Utc};
+use common::persist::PersistStats;
+use common::pools::Pools;
+use log::error;
+use std::time::Duration;
+use storage::ingestion_run::add_ingestion_run;
+use storage::models::ingestion_runs::{IngestionRunStatus, NewIngestionRun};
+use uuid::Uuid;
+
+use crate::error::IngestError;
+use crate::fetch::Payload;
+
+/// Collects what a worker run of a provider did, to store it in `ingestion_runs` once it is over.
+#[derive(Debug, Clone)]
+pub struct RunRecorder {
+    provider_id: Uuid,
+    started_at: NaiveDateTime,
+    http_status: Option<u16>,
+    bytes: Option<u64>,
+    parse_duration: Option<Duration>,
+    persisted: PersistStats,
+    quarantined: usize,
+}
+
+impl RunRecorder {
+    pub fn start(provider_id: Uuid) -> Self {
+        RunRecorder {
+            provider_id,
+            started_at: Utc::now().naive_utc(),
+            http_status: None,
+            bytes: None,
+            parse_duration: None,
+            persisted: PersistStats::default(),
+            quarantined: 0,
+        }
+    }
+
+    pub fn not_modified(&mut self) {
+        self.http_status = Some(304);
+    }
+
+    /// Records a fetched payload. Of a paginated feed, the status of the first page is kept and
+    /// the size of every page is added up.
+    pub fn fetched(&mut self, payload: &Payload) {
+        self.http_status.get_or_insert(payload.status);
+        self.bytes = Some(self.bytes.unwrap_or(0) + payload.size);
+    }
+
+    /// Time spent parsing the payload, persistence of its batches excluded.
+    pub fn parsed(&mut self, duration: Duration) {
+        self.parse_duration = Some(duration);
+    }
+
+    pub fn persisted(&mut self, stats: PersistStats) {
+        self.persisted = stats;
+    }
+
+    pub fn quarantined(&mut self, quarantined: usize) {
+        self.quarantined = quarantined;
+    }
+
+    /// The run as stored in `ingestion_runs`, given its result.
+    pub fn finish(&self, result: &Result<IngestionRunStatus, IngestError>) -> NewIngestionRun {
+        let (status, error_kind, error_message) = match result {
+            Ok(status) => (*status, None, None),
+            Err(e) => (
+                IngestionRunStatus::Failed,
+                Some(e.kind().to_string()),
+                Some(e.to_string()),
+            ),
+        };
+        // A run failing on an unexpected status records it, rather than that of an earlier page
+        let http_status = result
+            .as_ref()
+            .err()
+            .and_then(IngestError::http_status)
+            .or(self.http_status);
+        let persisted = &self.persisted;
+        NewIngestionRun {
+            ingestion_runs_id: Uuid::new_v4(),
+            providers_id: self.provider_id,
+            status: status.to_string(),
+            started_at: self.started_at,
+            finished_at: Utc::now().naive_utc(),
+            http_status: http_status.map(i32::from),
+            bytes: self.bytes.map(|bytes| bytes as i64),
+            parse_duration_ms: self.parse_duration.map(|d| d.as_millis() as i64),
+            base_plans_inserted: count(persisted.base_plans.inserted),
+            base_plans_updated: count(persisted.base_plans.updated),
+            base_plans_unchanged: count(persisted.base_plans.unchanged),
+            plans_inserted: count(persisted.plans.inserted),
+            plans_updated: count(persisted.plans.updated),
+            plans_unchanged: count(persisted.plans.unchanged),
+            zones_inserted: count(persisted.zones.inserted),
+            zones_updated: count(persisted.zones.updated),
+            zones_unchanged: count(persisted.zones.unchanged),
+            quarantined: count(self.quarantined),
+            error_kind,
+            error_message,
+        }
+    }
+
+    /// Stores the run. A failure to do so is logged and does not affect the run.
+    pub async fn save(&self, pools: &Pools, result: Result<IngestionRunStatus, IngestError>) {
+        let new_run = self.finish(&result);
+        let Some(mut conn) = pools.db_connection().await else {
+            return;
+        };
+        if let Err(e) = add_ingestion_run(&mut conn, new_run).await {
+            error!(
+                "Failed to record ingestion run for provider {}: {}",
+                self.provider_id, e
+            );
+        }
+    }
+}
+
+fn count(value: usize) -> i32 {
+    i32::try_from(value).unwrap_or(i32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{AdapterError, FetchError};
+
+    #[test]
+    fn test_finish_records_counts_and_error_kind() {
+        let provider_id = Uuid::new_v4();
+        let mut run = RunRecorder::start(provider_id);
+        run.parsed(Duration::from_millis(1_500));
+        let mut stats = PersistStats::default();
+        stats.plans.add(true, true);
+        stats.plans.add(false, true);
+        stats.plans.add(false, false);
+        run.persisted(stats);
+        run.quarantined(2);
+
+        let succeeded = run.finish(&Ok(IngestionRunStatus::Succeeded));
+        assert_eq!(succeeded.providers_id, provider_id);
+        assert_eq!(succeeded.status, "succeeded");
+        assert_eq!(succeeded.parse_duration_ms, Some(1_500));
+        assert_eq!(
+            (
+                succeeded.plans_inserted,
+                succeeded.plans_updated,
+                succeeded.plans_unchanged
+            ),
+            (1, 1, 1)
+        );
+        assert_eq!(succeeded.quarantined, 2);
+        assert_eq!(succeeded.error_kind, None);
+
+        let failed = run.finish(&Err(AdapterError::Fetch(FetchError::Timeout(
+            "deadline has elapsed".to_string(),
+        ))
+        .into()));
+        assert_eq!(failed.status, "failed");
+        assert_eq!(failed.error_kind.as_deref(), Some("timeout"));
+        assert_eq!(failed.http_status, None);
+        assert!(failed.started_at <= failed.finished_at);
+
+        let rejected = run.finish(&Err(AdapterError::Fetch(FetchError::Status(503)).into()));
+        assert_eq!(rejected.error_kind.as_deref(), Some("http_status"));
+        assert_eq!(rejected.http_status, Some(503));
+    }
+}
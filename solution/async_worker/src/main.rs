@@ -1,73 +1,125 @@
-// use storage::db::get_db_connection;
-use common::utils::get_db_connection;
+use common::pools::Pools;
 use std::time::Duration;
 use storage::provider::get_active_providers;
 
+mod adapters;
+mod archive;
+mod auth;
+mod circuit_breaker;
+mod command;
 mod config;
+mod context;
+mod control;
+mod error;
+mod fetch;
 mod handler;
+mod ingestion_run;
+mod pagination;
+mod quarantine;
+mod rate_limit;
+mod replay;
+mod retry;
+mod schedule;
+mod scheduler;
+mod status;
+#[cfg(test)]
+mod test_support;
 
-use handler::process_provider_events;
+use archive::PayloadArchiver;
+use auth::CredentialStore;
+use circuit_breaker::CircuitBreaker;
+use context::WorkerContext;
+use fetch::{build_authenticated_client, build_client};
+use pagination::PaginationLimits;
+use rate_limit::RateLimiter;
+use retry::RetryPolicy;
+use scheduler::Scheduler;
+use status::WorkerStatus;
 
 #[tokio::main]
 async fn main() {
     dotenv::dotenv().ok();
     env_logger::init();
     let config = config::build();
-    let interval_secs = config.async_worker_interval_sec;
+    let refresh = Duration::from_secs(config.scheduler_refresh_sec.max(1));
+    let pools = Pools::from_env(&config.pool_settings()).expect("Invalid connection pools");
+    let context = WorkerContext {
+        pools: pools.clone(),
+        client: build_client(&config).expect("Failed to build HTTP client"),
+        authenticated_client: build_authenticated_client(&config)
+            .expect("Failed to build HTTP client"),
+        retry_policy: RetryPolicy::from_config(&config),
+        circuit_breaker: CircuitBreaker::new(pools.cache.clone(), &config),
+        rate_limiter: RateLimiter::new(pools.cache.clone()),
+        pagination_limits: PaginationLimits::from_config(&config),
+        max_body_bytes: config.fetch_max_body_bytes,
+        persist_batch_size: config.persist_batch_size.max(1),
+        transaction_scope: config.persist_transaction_scope,
+        archive: PayloadArchiver::from_config(&config).expect("Invalid payload archive"),
+        status: WorkerStatus::default(),
+        credentials: CredentialStore::from_key(&config.credentials_key)
+            .expect("Invalid credentials key"),
+    };
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match args.first().map(String::as_str) {
+        Some("replay") => Some(replay::run(&context, &args[1..]).await),
+        Some("quarantine") => Some(quarantine::run(&pools, &args[1..]).await),
+        Some("credentials") => Some(auth::run(&context.credentials, &pools, &args[1..]).await),
+        _ => None,
+    };
+    if let Some(result) = command {
+        if let Err(e) = result {
+            log::error!("{} failed: {}", args[0], e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let archive = context.archive.clone();
+    let status = context.status.clone();
+    if !config.worker_control_server.is_empty() {
+        control::spawn(&config.worker_control_server, status.clone(), pools.clone())
+            .expect("Failed to start the control server");
+        log::info!(
+            "Control server listening on {}",
+            config.worker_control_server
+        );
+    }
+    let mut scheduler = Scheduler::new(
+        context,
+        Duration::from_secs(config.async_worker_interval_sec.max(1).into()),
+    );
 
     log::info!("Starting async_worker...");
 
-    // Main loop to fetch providers and process events
+    // Main loop to keep the provider schedules in sync with the database.
+    // Each provider is fetched by its own task, see `scheduler`.
     loop {
         log::info!("Fetching active providers...");
-        // Establish the connection asynchronously before entering the blocking task
-        let connection = get_db_connection().await;
-
         // If the connection is None, log an error and retry after a delay
-        if connection.is_none() {
+        let Some(mut pg_pool) = pools.db_connection().await else {
             log::error!("Failed to establish database connection.");
-            tokio::time::sleep(Duration::from_secs(interval_secs.into())).await;
+            tokio::time::sleep(refresh).await;
             continue;
-        }
-        let providers = tokio::task::spawn_blocking(move || {
-            let mut pg_pool = match connection {
-                Some(conn) => conn,
-                None => {
-                    log::error!("Database connection unexpectedly missing during blocking task.");
-                    return Err("Database connection missing".to_string());
-                }
-            };
-            get_active_providers(&mut pg_pool).map_err(|e| e.to_string())
-        })
-        .await
-        .expect("Failed to join blocking task");
+        };
 
-        match providers {
+        match get_active_providers(&mut pg_pool).await {
             Ok(providers) => {
-                let mut handles = vec![];
-                for provider in providers {
-                    let id = provider.providers_id;
-                    let name = provider.name.clone();
-                    let url = provider.url.clone();
-
-                    log::info!("Processing provider: {} - {}", id, name);
-
-                    // Spawn an async task for each provider
-                    let handle = tokio::spawn(async move {
-                        process_provider_events(id, name, url).await;
-                    });
-                    handles.push(handle);
-                }
-                for handle in handles {
-                    let _ = handle.await;
-                }
+                scheduler.sync(providers);
+                status.set_providers_loaded();
             }
             Err(e) => {
                 log::error!("Error fetching providers: {}", e);
             }
         }
 
-        log::info!("Sleeping for {} seconds before next run...", interval_secs);
-        tokio::time::sleep(Duration::from_secs(interval_secs.into())).await;
+        if archive.is_enabled() {
+            if let Err(e) = archive.prune(&pools).await {
+                log::error!("Failed to prune archived payloads: {}", e);
+            }
+        }
+
+        tokio::time::sleep(refresh).await;
     }
 }
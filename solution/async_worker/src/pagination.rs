@@ -0,0 +1,586 @@
+use log::{debug, info};
+use reqwest::Url;
+use serde::Deserialize;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use storage::models::providers::Provider;
+
+use crate::config::Config;
+use crate::error::AdapterError;
+use crate::fetch::{FetchOutcome, Payload};
+
+fn page_param() -> String {
+    "page".to_string()
+}
+
+fn first_page() -> u64 {
+    1
+}
+
+fn offset_param() -> String {
+    "offset".to_string()
+}
+
+fn limit_param() -> String {
+    "limit".to_string()
+}
+
+fn cursor_param() -> String {
+    "cursor".to_string()
+}
+
+/// How the pages of a provider feed are requested, from `providers.pagination`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PaginationStrategy {
+    /// `?page=N` from `first_page`, until a page has no records or fewer than `page_size`.
+    Page {
+        #[serde(default = "page_param")]
+        param: String,
+        #[serde(default = "first_page")]
+        first_page: u64,
+        /// Query parameter sending `page_size`, if the provider accepts one.
+        #[serde(default)]
+        size_param: Option<String>,
+        #[serde(default)]
+        page_size: Option<u64>,
+    },
+    /// `?offset=N&limit=L`, advancing by the records of each page until one is short.
+    Offset {
+        #[serde(default = "offset_param")]
+        param: String,
+        #[serde(default = "limit_param")]
+        limit_param: String,
+        limit: u64,
+    },
+    /// Cursor read from the JSON body at `cursor_path` and sent back as `param`,
+    /// until the body has none.
+    Cursor {
+        #[serde(default = "cursor_param")]
+        param: String,
+        cursor_path: String,
+    },
+    /// Follows the `rel="next"` URL of the `Link` header.
+    LinkHeader,
+}
+
+/// Pagination of a provider, with guards overriding the worker defaults.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Pagination {
+    #[serde(flatten)]
+    pub strategy: PaginationStrategy,
+    #[serde(default)]
+    pub max_pages: Option<usize>,
+    #[serde(default)]
+    pub max_duration_sec: Option<u64>,
+}
+
+impl Pagination {
+    /// The pagination of a provider, or `None` for feeds returned in a single response.
+    pub fn from_provider(provider: &Provider) -> Result<Option<Self>, AdapterError> {
+        provider
+            .pagination
+            .as_ref()
+            .map(|config| {
+                serde_json::from_value(config.clone())
+                    .map_err(|e| AdapterError::InvalidConfig(format!("pagination: {}", e)))
+            })
+            .transpose()
+    }
+}
+
+/// Default guards of paginated fetches, so a misbehaving provider cannot page forever.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaginationLimits {
+    pub max_pages: usize,
+    pub max_duration: Duration,
+}
+
+impl PaginationLimits {
+    pub fn from_config(config: &Config) -> Self {
+        PaginationLimits {
+            max_pages: config.pagination_max_pages.max(1),
+            max_duration: Duration::from_secs(config.pagination_max_duration_sec.max(1)),
+        }
+    }
+
+    fn for_pagination(&self, pagination: &Pagination) -> Self {
+        PaginationLimits {
+            max_pages: pagination.max_pages.unwrap_or(self.max_pages).max(1),
+            max_duration: pagination
+                .max_duration_sec
+                .map(Duration::from_secs)
+                .unwrap_or(self.max_duration),
+        }
+    }
+}
+
+/// What reading, i.e. persisting, a page found out about the next one.
+#[derive(Debug, Default)]
+pub struct PageRead {
+    /// Records of the page, valid or rejected.
+    pub records: usize,
+    /// Cursor read from the page document, for the `cursor` strategy.
+    pub cursor: Option<String>,
+    /// `rel="next"` URL of the `Link` header of the page.
+    pub next_link: Option<String>,
+}
+
+/// Walks the pages of a paginated feed. The next page is only known once the current one was
+/// read, so each page is persisted, in a single parse, before the next one is fetched.
+/// Exceeding a guard fails the run, so the plans missing from a partial snapshot are never
+/// marked unseen.
+pub struct Pages<'a> {
+    cursor: Cursor<'a>,
+    limits: PaginationLimits,
+    started: Instant,
+    read: usize,
+    next_url: Option<String>,
+}
+
+impl<'a> Pages<'a> {
+    pub fn new(
+        url: &str,
+        pagination: &'a Pagination,
+        limits: &PaginationLimits,
+    ) -> Result<Self, AdapterError> {
+        let cursor = Cursor::start(&pagination.strategy);
+        Ok(Pages {
+            next_url: Some(cursor.url(url)?),
+            cursor,
+            limits: limits.for_pagination(pagination),
+            started: Instant::now(),
+            read: 0,
+        })
+    }
+
+    /// URL of the next page, or `None` once the last one was read.
+    pub fn next_url(&self) -> Result<Option<String>, AdapterError> {
+        let Some(url) = &self.next_url else {
+            return Ok(None);
+        };
+        if self.read >= self.limits.max_pages {
+            return Err(AdapterError::Pagination(format!(
+                "more than {} pages",
+                self.limits.max_pages
+            )));
+        }
+        if self.started.elapsed() >= self.limits.max_duration {
+            return Err(self.timed_out());
+        }
+        Ok(Some(url.clone()))
+    }
+
+    /// Path of the cursor of the next page in a page document, for the `cursor` strategy.
+    pub fn cursor_path(&self) -> Option<&'a str> {
+        match self.cursor.strategy {
+            PaginationStrategy::Cursor { cursor_path, .. } => Some(cursor_path),
+            _ => None,
+        }
+    }
+
+    /// Waits for the fetch of a page, within the time left to read the feed.
+    pub async fn fetch<Fut>(&self, page_url: &str, fetch: Fut) -> Result<Payload, AdapterError>
+    where
+        Fut: Future<Output = Result<FetchOutcome, AdapterError>>,
+    {
+        let remaining = self
+            .limits
+            .max_duration
+            .saturating_sub(self.started.elapsed());
+        let outcome = tokio::time::timeout(remaining, fetch)
+            .await
+            .map_err(|_| self.timed_out())??;
+        match outcome {
+            FetchOutcome::Fetched(payload) => {
+                debug!("Fetched page {} from {}", self.read + 1, page_url);
+                Ok(payload)
+            }
+            FetchOutcome::NotModified => Err(AdapterError::Pagination(format!(
+                "unexpected 304 for {}",
+                page_url
+            ))),
+        }
+    }
+
+    /// Moves past the page read from `page_url`.
+    pub fn advance(&mut self, page_url: &str, page: PageRead) -> Result<(), AdapterError> {
+        self.read += 1;
+        self.next_url = match self.cursor.advance(page_url, page)? {
+            Some(next_url) if next_url == page_url => {
+                return Err(AdapterError::Pagination(format!(
+                    "next page is the current one: {}",
+                    next_url
+                )))
+            }
+            next_url => next_url,
+        };
+        if self.next_url.is_none() {
+            info!("Read {} pages from {}", self.read, page_url);
+        }
+        Ok(())
+    }
+
+    fn timed_out(&self) -> AdapterError {
+        AdapterError::Pagination(format!(
+            "pages not read within {} seconds",
+            self.limits.max_duration.as_secs()
+        ))
+    }
+}
+
+/// Position of a paginated fetch.
+struct Cursor<'a> {
+    strategy: &'a PaginationStrategy,
+    /// Page number, or offset, of the current page.
+    position: u64,
+    /// Cursor sent for the current page. The first page is requested without one.
+    token: Option<String>,
+}
+
+impl<'a> Cursor<'a> {
+    fn start(strategy: &'a PaginationStrategy) -> Self {
+        let position = match strategy {
+            PaginationStrategy::Page { first_page, .. } => *first_page,
+            _ => 0,
+        };
+        Cursor {
+            strategy,
+            position,
+            token: None,
+        }
+    }
+
+    /// URL of the current page.
+    fn url(&self, url: &str) -> Result<String, AdapterError> {
+        let position = self.position.to_string();
+        let mut params = Vec::new();
+        match self.strategy {
+            PaginationStrategy::Page {
+                param,
+                size_param,
+                page_size,
+                ..
+            } => {
+                params.push((param.as_str(), position));
+                if let (Some(size_param), Some(page_size)) = (size_param, page_size) {
+                    params.push((size_param.as_str(), page_size.to_string()));
+                }
+            }
+            PaginationStrategy::Offset {
+                param,
+                limit_param,
+                limit,
+            } => {
+                params.push((param.as_str(), position));
+                params.push((limit_param.as_str(), limit.to_string()));
+            }
+            PaginationStrategy::Cursor { param, .. } => {
+                if let Some(token) = &self.token {
+                    params.push((param.as_str(), token.clone()));
+                }
+            }
+            PaginationStrategy::LinkHeader => {}
+        }
+        with_query(url, &params)
+    }
+
+    /// Moves past a read page, returning the URL of the next one unless it was the last.
+    fn advance(&mut self, page_url: &str, page: PageRead) -> Result<Option<String>, AdapterError> {
+        let records = page.records as u64;
+        match self.strategy {
+            PaginationStrategy::Page { page_size, .. } => {
+                self.position += 1;
+                let last = records == 0 || page_size.is_some_and(|size| records < size);
+                self.next(last, page_url)
+            }
+            PaginationStrategy::Offset { limit, .. } => {
+                self.position += records;
+                self.next(records < *limit, page_url)
+            }
+            PaginationStrategy::Cursor { .. } => {
+                self.token = page.cursor.filter(|token| !token.is_empty());
+                self.next(self.token.is_none(), page_url)
+            }
+            PaginationStrategy::LinkHeader => page
+                .next_link
+                .map(|link| {
+                    Url::parse(page_url)
+                        .and_then(|base| base.join(&link))
+                        .map(String::from)
+                        .map_err(|e| AdapterError::Pagination(format!("next link: {}", e)))
+                })
+                .transpose(),
+        }
+    }
+
+    fn next(&self, last: bool, page_url: &str) -> Result<Option<String>, AdapterError> {
+        if last {
+            Ok(None)
+        } else {
+            self.url(page_url).map(Some)
+        }
+    }
+}
+
+/// `url` with the query parameters `params` set, replacing any previous value.
+fn with_query(url: &str, params: &[(&str, String)]) -> Result<String, AdapterError> {
+    let mut url = Url::parse(url).map_err(|e| AdapterError::Pagination(e.to_string()))?;
+    if params.is_empty() {
+        return Ok(url.to_string());
+    }
+    let kept: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| params.iter().all(|(param, _)| name != param))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(kept)
+        .extend_pairs(params.iter().map(|(name, value)| (*name, value.as_str())));
+    Ok(url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::json::JsonAdapter;
+    use crate::adapters::ProviderAdapter;
+    use crate::fetch::{fetch_payload, FeedAccess, Validators};
+    use crate::retry::RetryPolicy;
+    use common::dates::DateParser;
+    use common::zones::ZoneParser;
+    use futures::StreamExt;
+    use httpmock::prelude::*;
+    use reqwest::Client;
+
+    const LIMITS: PaginationLimits = PaginationLimits {
+        max_pages: 10,
+        max_duration: Duration::from_secs(60),
+    };
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 1,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+            jitter: false,
+            retryable_statuses: Vec::new(),
+            honor_retry_after: false,
+        }
+    }
+
+    fn page(ids: &[&str], next_cursor: Option<&str>) -> serde_json::Value {
+        let base_plans: Vec<_> = ids
+            .iter()
+            .map(|id| serde_json::json!({"base_plan_id": id, "title": id, "sell_mode": "online"}))
+            .collect();
+        serde_json::json!({"base_plans": base_plans, "next": next_cursor})
+    }
+
+    /// Walks the pages like a run does, returning the records read.
+    async fn read_pages(
+        server: &MockServer,
+        strategy: &PaginationStrategy,
+        limits: PaginationLimits,
+    ) -> Result<usize, AdapterError> {
+        let adapter =
+            JsonAdapter::json(None, DateParser::default(), ZoneParser::default()).unwrap();
+        let client = Client::new();
+        let policy = policy();
+        let pagination = Pagination {
+            strategy: strategy.clone(),
+            max_pages: None,
+            max_duration_sec: None,
+        };
+        let mut pages = Pages::new(&server.url("/events"), &pagination, &limits)?;
+        let mut read = 0;
+        while let Some(page_url) = pages.next_url()? {
+            let fetch = async {
+                Ok(fetch_payload(
+                    &client,
+                    &page_url,
+                    &policy,
+                    &Validators::default(),
+                    FeedAccess::default(),
+                    u64::MAX,
+                )
+                .await?)
+            };
+            let payload = pages.fetch(&page_url, fetch).await?;
+            let next_link = payload.next_link.clone();
+            let (records, cursor) = match pages.cursor_path() {
+                Some(cursor_path) => adapter.stream_page(payload, cursor_path).await?,
+                None => (adapter.stream(payload).await?, None),
+            };
+            let records = records.count().await;
+            read += records;
+            let page = PageRead {
+                records,
+                cursor,
+                next_link,
+            };
+            pages.advance(&page_url, page)?;
+        }
+        Ok(read)
+    }
+
+    #[test]
+    fn test_pagination_config() {
+        let pagination: Pagination = serde_json::from_value(
+            serde_json::json!({"type": "offset", "limit": 50, "max_pages": 5}),
+        )
+        .unwrap();
+        assert_eq!(
+            pagination.strategy,
+            PaginationStrategy::Offset {
+                param: "offset".to_string(),
+                limit_param: "limit".to_string(),
+                limit: 50
+            }
+        );
+        assert_eq!(LIMITS.for_pagination(&pagination).max_pages, 5);
+        assert!(
+            serde_json::from_value::<Pagination>(serde_json::json!({"type": "pages"})).is_err()
+        );
+    }
+
+    #[test]
+    fn test_with_query_replaces_parameters() {
+        assert_eq!(
+            with_query(
+                "https://api.example.com/events?page=1&lang=en",
+                &[("page", "2".to_string())]
+            )
+            .unwrap(),
+            "https://api.example.com/events?lang=en&page=2"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_page_strategy_stops_on_empty_page() {
+        let server = MockServer::start_async().await;
+        let mut mocks = Vec::new();
+        for (number, ids) in [("1", vec!["a", "b"]), ("2", vec!["c"]), ("3", vec![])] {
+            let body = page(&ids, None);
+            mocks.push(
+                server
+                    .mock_async(|when, then| {
+                        when.method(GET).path("/events").query_param("page", number);
+                        then.status(200).json_body(body);
+                    })
+                    .await,
+            );
+        }
+
+        let strategy = PaginationStrategy::Page {
+            param: "page".to_string(),
+            first_page: 1,
+            size_param: None,
+            page_size: None,
+        };
+        assert_eq!(read_pages(&server, &strategy, LIMITS).await.unwrap(), 3);
+        for mock in mocks {
+            mock.assert_hits_async(1).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_offset_strategy_stops_on_short_page() {
+        let server = MockServer::start_async().await;
+        let first = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/events")
+                    .query_param("offset", "0")
+                    .query_param("limit", "2");
+                then.status(200).json_body(page(&["a", "b"], None));
+            })
+            .await;
+        let second = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/events").query_param("offset", "2");
+                then.status(200).json_body(page(&["c"], None));
+            })
+            .await;
+
+        let strategy = PaginationStrategy::Offset {
+            param: "offset".to_string(),
+            limit_param: "limit".to_string(),
+            limit: 2,
+        };
+        assert_eq!(read_pages(&server, &strategy, LIMITS).await.unwrap(), 3);
+        first.assert_hits_async(1).await;
+        second.assert_hits_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn test_cursor_strategy_follows_body_cursor() {
+        let server = MockServer::start_async().await;
+        let second = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/events").query_param("cursor", "c2");
+                then.status(200).json_body(page(&["b"], None));
+            })
+            .await;
+        let first = server
+            .mock_async(|when, then| {
+                // Requests with a cursor are matched by the mock above
+                when.method(GET).path("/events");
+                then.status(200).json_body(page(&["a"], Some("c2")));
+            })
+            .await;
+
+        let strategy = PaginationStrategy::Cursor {
+            param: "cursor".to_string(),
+            cursor_path: "next".to_string(),
+        };
+        assert_eq!(read_pages(&server, &strategy, LIMITS).await.unwrap(), 2);
+        first.assert_hits_async(1).await;
+        second.assert_hits_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn test_link_header_strategy_and_max_pages() {
+        let server = MockServer::start_async().await;
+        let pages = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/events");
+                then.status(200)
+                    .header("Link", r#"</events?page=next>; rel="next""#)
+                    .json_body(page(&["a"], None));
+            })
+            .await;
+
+        let limits = PaginationLimits {
+            max_pages: 3,
+            ..LIMITS
+        };
+        let result = read_pages(&server, &PaginationStrategy::LinkHeader, limits).await;
+        // The next link never changes after the second page
+        assert!(matches!(result, Err(AdapterError::Pagination(_))));
+        pages.assert_hits_async(2).await;
+    }
+
+    #[tokio::test]
+    async fn test_max_duration_bounds_page_fetch() {
+        let server = MockServer::start_async().await;
+        let _slow = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/events");
+                then.status(200)
+                    .delay(Duration::from_secs(5))
+                    .json_body(page(&["a"], None));
+            })
+            .await;
+
+        let limits = PaginationLimits {
+            max_duration: Duration::from_millis(100),
+            ..LIMITS
+        };
+        let started = Instant::now();
+        let result = read_pages(&server, &PaginationStrategy::LinkHeader, limits).await;
+        assert!(matches!(result, Err(AdapterError::Pagination(_))));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
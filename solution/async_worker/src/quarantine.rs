@@ -0,0 +1,196 @@
+use common::persist::persist_base_plans;
+use common::pools::Pools;
+use log::{info, warn};
+use std::collections::HashSet;
+use storage::connections::db::PgPooledConnection;
+use storage::models::quarantined_records::{
+    NewQuarantinedRecord, QuarantineStatus, QuarantinedRecord,
+};
+use storage::provider::get_provider;
+use storage::quarantined_record::{
+    add_or_update_quarantined_records, get_quarantined_record,
+    list_pending_quarantined_records_after, list_quarantined_records, update_quarantined_record,
+};
+use uuid::Uuid;
+
+use crate::adapters::{adapter_for, date_parser_for, zone_parser_for, RejectedRecord};
+use crate::command::parse_id;
+use crate::error::{CommandError, IngestError};
+use crate::fetch::content_hash;
+
+const DEFAULT_LIST_LIMIT: i64 = 20;
+const REPROCESS_BATCH: i64 = 1_000;
+
+/// Stores rejected records in `quarantined_records`. `seen` holds the hashes of the records
+/// the run already quarantined, which are skipped so that a record repeated in a feed is
+/// counted once. Returns the number of distinct records quarantined.
+pub async fn quarantine_records(
+    pools: &Pools,
+    provider_id: Uuid,
+    archive_id: Option<Uuid>,
+    rejected: Vec<RejectedRecord>,
+    seen: &mut HashSet<String>,
+) -> Result<usize, IngestError> {
+    // A single upsert cannot touch the same row twice either
+    let new_records: Vec<NewQuarantinedRecord> = rejected
+        .into_iter()
+        .filter_map(|record| {
+            let record_hash = content_hash(record.raw.as_bytes());
+            if !seen.insert(record_hash.clone()) {
+                return None;
+            }
+            warn!(
+                "Quarantining record of provider {}: {}",
+                provider_id, record.error
+            );
+            Some(NewQuarantinedRecord {
+                quarantined_records_id: Uuid::new_v4(),
+                providers_id: provider_id,
+                payload_archives_id: archive_id,
+                record_hash,
+                raw: record.raw,
+                reason: serde_json::to_value(&record.error).unwrap_or_default(),
+                status: QuarantineStatus::Pending.to_string(),
+            })
+        })
+        .collect();
+    if new_records.is_empty() {
+        return Ok(0);
+    }
+    let mut conn = pools
+        .db_connection()
+        .await
+        .ok_or_else(|| IngestError::Quarantine("Failed to get DB connection".to_string()))?;
+    add_or_update_quarantined_records(&mut conn, &new_records)
+        .await
+        .map_err(|e| IngestError::Quarantine(e.to_string()))?;
+    Ok(new_records.len())
+}
+
+/// `async_worker quarantine --list [provider_id] [limit]` lists pending quarantined records.
+/// `async_worker quarantine --reprocess <record_id>` re-parses and persists a record, and
+/// `async_worker quarantine --reprocess-provider <provider_id>` does so for every pending
+/// record of a provider, e.g. after a parser or mapping fix. Records are re-processed in
+/// batches, each tried once: those still invalid stay pending for a later run.
+pub async fn run(pools: &Pools, args: &[String]) -> Result<(), CommandError> {
+    match args {
+        [flag, rest @ ..] if flag == "--list" && rest.len() <= 2 => {
+            let provider_id = rest.first().map(|id| parse_id(id)).transpose()?;
+            let limit = match rest.get(1) {
+                Some(limit) => limit
+                    .parse()
+                    .map_err(|_| CommandError::InvalidArguments(format!("limit: {}", limit)))?,
+                None => DEFAULT_LIST_LIMIT,
+            };
+            list(pools, provider_id, limit).await
+        }
+        [flag, record_id] if flag == "--reprocess" => {
+            let mut conn = connection(pools).await?;
+            let record = get_quarantined_record(&mut conn, parse_id(record_id)?)
+                .await
+                .map_err(|e| CommandError::Db(e.to_string()))?
+                .ok_or_else(|| CommandError::InvalidArguments(format!("record: {}", record_id)))?;
+            reprocess(pools, &mut conn, record).await.map(|_| ())
+        }
+        [flag, provider_id] if flag == "--reprocess-provider" => {
+            let provider_id = parse_id(provider_id)?;
+            let mut conn = connection(pools).await?;
+            let (mut total, mut resolved) = (0, 0);
+            // Paging by id moves past the records that stay pending instead of retrying them
+            let mut after = None;
+            loop {
+                let records = list_pending_quarantined_records_after(
+                    &mut conn,
+                    provider_id,
+                    after,
+                    REPROCESS_BATCH,
+                )
+                .await
+                .map_err(|e| CommandError::Db(e.to_string()))?;
+                let Some(last) = records.last() else {
+                    break;
+                };
+                after = Some(last.quarantined_records_id);
+                total += records.len();
+                for record in records {
+                    if reprocess(pools, &mut conn, record).await? {
+                        resolved += 1;
+                    }
+                }
+            }
+            info!("Resolved {} of {} quarantined records", resolved, total);
+            Ok(())
+        }
+        _ => Err(CommandError::InvalidArguments(
+            "usage: async_worker quarantine --list [provider_id] [limit] | --reprocess <record_id> | --reprocess-provider <provider_id>"
+                .to_string(),
+        )),
+    }
+}
+
+async fn list(pools: &Pools, provider_id: Option<Uuid>, limit: i64) -> Result<(), CommandError> {
+    let mut conn = connection(pools).await?;
+    let records =
+        list_quarantined_records(&mut conn, provider_id, QuarantineStatus::Pending, limit)
+            .await
+            .map_err(|e| CommandError::Db(e.to_string()))?;
+    for record in records {
+        println!(
+            "{}\t{}\t{}\t{}x\t{}",
+            record.quarantined_records_id,
+            record.providers_id,
+            record.updated_at,
+            record.occurrences,
+            record.reason
+        );
+    }
+    Ok(())
+}
+
+/// Re-parses a quarantined record with the current adapter of its provider. Returns whether
+/// it could be persisted; otherwise its reason is refreshed and it stays pending.
+async fn reprocess(
+    pools: &Pools,
+    conn: &mut PgPooledConnection,
+    record: QuarantinedRecord,
+) -> Result<bool, CommandError> {
+    let provider = get_provider(conn, record.providers_id)
+        .await
+        .map_err(|e| CommandError::Db(e.to_string()))?
+        .ok_or_else(|| CommandError::ProviderNotFound(record.providers_id.to_string()))?;
+    let adapter = adapter_for(&provider)?;
+    let dates = date_parser_for(&provider)?;
+    let zones = zone_parser_for(&provider)?;
+    let record_id = record.quarantined_records_id;
+    let (status, reason) = match adapter.parse_record(&record.raw) {
+        Ok(base_plan) => {
+            persist_base_plans(
+                pools,
+                vec![base_plan],
+                provider.providers_id,
+                provider.name.clone(),
+                dates,
+                zones,
+            )
+            .await
+            .map_err(IngestError::from)?;
+            info!("Quarantined record {} persisted", record_id);
+            (QuarantineStatus::Resolved, None)
+        }
+        Err(e) => {
+            warn!("Quarantined record {} is still invalid: {}", record_id, e);
+            (QuarantineStatus::Pending, serde_json::to_value(&e).ok())
+        }
+    };
+    update_quarantined_record(conn, record_id, status, reason)
+        .await
+        .map_err(|e| CommandError::Db(e.to_string()))?;
+    Ok(status == QuarantineStatus::Resolved)
+}
+
+async fn connection(pools: &Pools) -> Result<PgPooledConnection, CommandError> {
+    pools
+        .db_connection()
+        .await
+        .ok_or_else(|| CommandError::Db("Failed to get DB connection".to_string()))
+}
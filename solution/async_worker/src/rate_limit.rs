@@ -0,0 +1,131 @@
+use common::pools::SharedCache;
+use log::{error, info, warn};
+use std::time::Duration;
+use storage::connections::cache::RateLimitBucket;
+use storage::models::providers::Provider;
+use uuid::Uuid;
+
+/// Outbound rate limits of the providers. Their token buckets live in Redis so that every
+/// worker instance draws from the same quota.
+#[derive(Clone)]
+pub struct RateLimiter {
+    cache: SharedCache,
+}
+
+impl RateLimiter {
+    pub fn new(cache: SharedCache) -> Self {
+        RateLimiter { cache }
+    }
+
+    /// The rate limit of a provider, or `None` when it is not throttled.
+    pub fn for_provider(&self, provider: &Provider) -> Option<ProviderRateLimit> {
+        Some(ProviderRateLimit {
+            cache: self.cache.clone(),
+            provider_id: provider.providers_id,
+            bucket: bucket(provider)?,
+        })
+    }
+}
+
+/// Token bucket configured by `providers.rate_limit_per_minute` and `rate_limit_burst`.
+/// The burst defaults to a single request, i.e. requests are evenly spaced.
+fn bucket(provider: &Provider) -> Option<RateLimitBucket> {
+    let per_minute = u32::try_from(provider.rate_limit_per_minute?).ok()?;
+    let burst = provider
+        .rate_limit_burst
+        .and_then(|burst| u32::try_from(burst).ok())
+        .unwrap_or(1);
+    Some(RateLimitBucket {
+        per_minute: per_minute.max(1),
+        burst: burst.max(1),
+    })
+}
+
+/// Rate limit applied to every request sent to the feed of a provider.
+#[derive(Clone)]
+pub struct ProviderRateLimit {
+    cache: SharedCache,
+    provider_id: Uuid,
+    bucket: RateLimitBucket,
+}
+
+impl ProviderRateLimit {
+    /// Waits until the provider may be sent a request. Redis failures never block fetching.
+    pub async fn acquire(&self) {
+        let id = self.provider_id.to_string();
+        loop {
+            let Some(cache) = self.cache.get().await else {
+                return;
+            };
+            let wait = match cache.acquire_rate_limit_token(&id, self.bucket).await {
+                Ok(Some(wait)) => wait,
+                Ok(None) => return,
+                Err(e) => {
+                    error!(
+                        "Failed to acquire rate limit token for provider {}: {}",
+                        id, e
+                    );
+                    self.cache.on_error(&e);
+                    return;
+                }
+            };
+            info!(
+                "Rate limit of provider {} reached, waiting {} ms",
+                id,
+                wait.as_millis()
+            );
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Feeds a `429 Too Many Requests` back into the limiter: the bucket is emptied and, when
+    /// the provider sent a `Retry-After`, every worker instance holds off until it has elapsed.
+    pub async fn throttled(&self, retry_after: Option<Duration>) {
+        let id = self.provider_id.to_string();
+        warn!("Provider {} is throttling requests", id);
+        let Some(cache) = self.cache.get().await else {
+            return;
+        };
+        if let Err(e) = cache
+            .throttle_rate_limit(&id, self.bucket, retry_after.unwrap_or_default())
+            .await
+        {
+            error!("Failed to throttle rate limit of provider {}: {}", id, e);
+            self.cache.on_error(&e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn provider(per_minute: Option<i32>, burst: Option<i32>) -> Provider {
+        Provider {
+            rate_limit_per_minute: per_minute,
+            rate_limit_burst: burst,
+            ..test_support::provider("provider")
+        }
+    }
+
+    #[test]
+    fn test_bucket_from_provider() {
+        assert_eq!(bucket(&provider(None, Some(5))), None);
+        assert_eq!(
+            bucket(&provider(Some(120), None)),
+            Some(RateLimitBucket {
+                per_minute: 120,
+                burst: 1
+            })
+        );
+        assert_eq!(
+            bucket(&provider(Some(30), Some(10))),
+            Some(RateLimitBucket {
+                per_minute: 30,
+                burst: 10
+            })
+        );
+        assert_eq!(bucket(&provider(Some(-1), None)), None);
+    }
+}
@@ -0,0 +1,103 @@
+use common::persist::SnapshotWriter;
+use log::info;
+use storage::provider::get_provider;
+use uuid::Uuid;
+
+use crate::adapters::{adapter_for, date_parser_for, zone_parser_for};
+use crate::command::parse_id;
+use crate::context::WorkerContext;
+use crate::error::{CommandError, IngestError};
+use crate::handler::{persist_payload, IngestStats};
+
+const DEFAULT_LIST_LIMIT: i64 = 20;
+
+/// `async_worker replay <archive_id>` re-runs parsing and persistence from an archived payload.
+/// `async_worker replay --list <provider_id> [limit]` lists the latest archived payloads.
+pub async fn run(context: &WorkerContext, args: &[String]) -> Result<(), CommandError> {
+    match args {
+        [flag, provider_id, rest @ ..] if flag == "--list" && rest.len() <= 1 => {
+            let limit = match rest.first() {
+                Some(limit) => limit
+                    .parse()
+                    .map_err(|_| CommandError::InvalidArguments(format!("limit: {}", limit)))?,
+                None => DEFAULT_LIST_LIMIT,
+            };
+            list(context, parse_id(provider_id)?, limit).await
+        }
+        [archive_id] => replay(context, parse_id(archive_id)?).await.map(|_| ()),
+        _ => Err(CommandError::InvalidArguments(
+            "usage: async_worker replay <archive_id> | --list <provider_id> [limit]".to_string(),
+        )),
+    }
+}
+
+async fn list(context: &WorkerContext, provider_id: Uuid, limit: i64) -> Result<(), CommandError> {
+    for entry in context
+        .archive
+        .list(&context.pools, provider_id, limit)
+        .await?
+    {
+        println!(
+            "{}\t{}\t{}\t{} bytes\t{}",
+            entry.payload_archives_id,
+            entry.fetched_at,
+            entry.status,
+            entry.size,
+            entry.content_hash
+        );
+    }
+    Ok(())
+}
+
+/// Parses and persists an archived payload with the current adapter of its provider.
+/// Fetch state and plan availability are left untouched: the payload may be older than
+/// the latest snapshot.
+pub async fn replay(
+    context: &WorkerContext,
+    archive_id: Uuid,
+) -> Result<IngestStats, CommandError> {
+    let (archive, payload) = context.archive.load(&context.pools, archive_id).await?;
+    let mut conn = context
+        .pools
+        .db_connection()
+        .await
+        .ok_or_else(|| CommandError::Db("Failed to get DB connection".to_string()))?;
+    let provider = get_provider(&mut conn, archive.providers_id)
+        .await
+        .map_err(|e| CommandError::Db(e.to_string()))?
+        .ok_or_else(|| CommandError::ProviderNotFound(archive.providers_id.to_string()))?;
+    drop(conn);
+
+    info!(
+        "Replaying payload {} fetched at {} for provider: {} - {}",
+        archive_id, archive.fetched_at, provider.providers_id, provider.name
+    );
+    let adapter = adapter_for(&provider)?;
+    let dates = date_parser_for(&provider)?;
+    let zones = zone_parser_for(&provider)?;
+    let mut writer = SnapshotWriter::begin(
+        &context.pools,
+        provider.providers_id,
+        provider.name.clone(),
+        dates,
+        zones,
+        context.transaction_scope,
+    )
+    .await
+    .map_err(IngestError::from)?;
+    let stats = persist_payload(
+        context,
+        adapter.as_ref(),
+        payload,
+        &provider,
+        Some(archive_id),
+        &mut writer,
+    )
+    .await?;
+    writer.finish(false).await.map_err(IngestError::from)?;
+    info!(
+        "Replayed {} base plans from payload {}, {} records quarantined",
+        stats.persisted, archive_id, stats.quarantined
+    );
+    Ok(stats)
+}
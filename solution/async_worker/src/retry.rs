@@ -0,0 +1,173 @@
+use crate::config::Config;
+use crate::error::FetchError;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use std::time::Duration;
+
+/// Retry policy applied to every request sent to a provider.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: bool,
+    pub retryable_statuses: Vec<u16>,
+    pub honor_retry_after: bool,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        RetryPolicy {
+            max_attempts: config.fetch_max_attempts.max(1),
+            base_delay: Duration::from_millis(config.fetch_base_delay_ms),
+            max_delay: Duration::from_millis(config.fetch_max_delay_ms),
+            jitter: config.fetch_jitter,
+            retryable_statuses: parse_statuses(&config.fetch_retryable_statuses),
+            honor_retry_after: config.fetch_honor_retry_after,
+        }
+    }
+
+    /// Whether a failed attempt is worth retrying.
+    pub fn is_retryable(&self, error: &FetchError) -> bool {
+        match error {
+            FetchError::Status(status) => self.retryable_statuses.contains(status),
+            FetchError::Connect(_) | FetchError::Timeout(_) | FetchError::Body(_) => true,
+            FetchError::InvalidUrl(_)
+            | FetchError::Request(_)
+            | FetchError::Auth(_)
+            | FetchError::BodyTooLarge(_) => false,
+        }
+    }
+
+    /// Exponential backoff for the given (1-based) attempt, capped at `max_delay`.
+    /// With jitter enabled the delay is picked randomly in the upper half of the window.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let delay = self
+            .base_delay
+            .saturating_mul(1u32 << exponent)
+            .min(self.max_delay);
+        if !self.jitter || delay.is_zero() {
+            return delay;
+        }
+        let millis = delay.as_millis() as u64;
+        Duration::from_millis(rand::rng().random_range(millis / 2..=millis))
+    }
+
+    /// Delay to wait before the next attempt, honoring the provider's `Retry-After` when enabled.
+    pub fn delay_for(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        match retry_after {
+            Some(delay) if self.honor_retry_after => delay.min(self.max_delay),
+            _ => self.backoff(attempt),
+        }
+    }
+}
+
+/// Parses a `Retry-After` header value, given either in seconds or as an HTTP date.
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+fn parse_statuses(statuses: &str) -> Vec<u16> {
+    statuses
+        .split(',')
+        .filter_map(|s| {
+            let s = s.trim();
+            match s.parse::<u16>() {
+                Ok(status) => Some(status),
+                Err(_) if s.is_empty() => None,
+                Err(_) => {
+                    log::warn!("Ignoring invalid retryable status: {}", s);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(jitter: bool) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1_000),
+            jitter,
+            retryable_statuses: parse_statuses("429, 503"),
+            honor_retry_after: true,
+        }
+    }
+
+    #[test]
+    fn test_backoff_grows_exponentially_and_is_capped() {
+        let policy = policy(false);
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(5), Duration::from_millis(1_000));
+        assert_eq!(policy.backoff(40), Duration::from_millis(1_000));
+    }
+
+    #[test]
+    fn test_backoff_with_jitter_stays_in_window() {
+        let policy = policy(true);
+        for _ in 0..100 {
+            let delay = policy.backoff(3);
+            assert!(delay >= Duration::from_millis(200) && delay <= Duration::from_millis(400));
+        }
+    }
+
+    #[test]
+    fn test_retry_after_is_honored_and_capped() {
+        let policy = policy(false);
+        assert_eq!(
+            policy.delay_for(1, Some(Duration::from_millis(300))),
+            Duration::from_millis(300)
+        );
+        assert_eq!(
+            policy.delay_for(1, Some(Duration::from_secs(60))),
+            Duration::from_millis(1_000)
+        );
+        assert_eq!(policy.delay_for(2, None), Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_retryable_errors() {
+        let policy = policy(false);
+        assert!(policy.is_retryable(&FetchError::Status(503)));
+        assert!(!policy.is_retryable(&FetchError::Status(404)));
+        assert!(policy.is_retryable(&FetchError::Timeout("t".to_string())));
+        assert!(!policy.is_retryable(&FetchError::InvalidUrl("u".to_string())));
+    }
+}
@@ -0,0 +1,202 @@
+use chrono::{DateTime, Duration as ChronoDuration, NaiveTime, Utc};
+use std::str::FromStr;
+use std::time::Duration;
+use storage::models::providers::Provider;
+
+use crate::error::ScheduleError;
+
+/// Upper bound of cron occurrences inspected when looking for one inside the active hours.
+const MAX_CRON_LOOKAHEAD: usize = 10_000;
+
+/// When a provider is due to be fetched.
+#[derive(Debug, Clone)]
+pub enum Trigger {
+    Interval(Duration),
+    Cron(Box<cron::Schedule>),
+}
+
+/// Daily window, in UTC, during which a provider may be fetched.
+/// `until` before `from` means the window wraps midnight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActiveHours {
+    pub from: NaiveTime,
+    pub until: NaiveTime,
+}
+
+impl ActiveHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.from <= self.until {
+            self.from <= time && time <= self.until
+        } else {
+            time >= self.from || time <= self.until
+        }
+    }
+
+    /// First instant at or after `at` that falls inside the window.
+    fn next_start(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        if self.contains(at.time()) {
+            return at;
+        }
+        let start = at.date_naive().and_time(self.from).and_utc();
+        if start > at {
+            start
+        } else {
+            start + ChronoDuration::days(1)
+        }
+    }
+}
+
+/// Polling schedule of a provider, read from its `schedule_*` and `active_*` columns.
+#[derive(Debug, Clone)]
+pub struct ProviderSchedule {
+    pub trigger: Trigger,
+    pub active_hours: Option<ActiveHours>,
+}
+
+impl ProviderSchedule {
+    /// Providers without an interval or cron expression are fetched every `default_interval`.
+    pub fn from_provider(
+        provider: &Provider,
+        default_interval: Duration,
+    ) -> Result<Self, ScheduleError> {
+        let trigger = match (provider.schedule_interval_sec, &provider.schedule_cron) {
+            (_, Some(expression)) => Trigger::Cron(Box::new(
+                cron::Schedule::from_str(expression)
+                    .map_err(|e| ScheduleError::InvalidCron(expression.clone(), e.to_string()))?,
+            )),
+            (Some(seconds), None) if seconds > 0 => {
+                Trigger::Interval(Duration::from_secs(seconds as u64))
+            }
+            (Some(seconds), None) => return Err(ScheduleError::InvalidInterval(seconds)),
+            (None, None) => Trigger::Interval(default_interval),
+        };
+        let active_hours = match (provider.active_from, provider.active_until) {
+            (Some(from), Some(until)) => Some(ActiveHours { from, until }),
+            _ => None,
+        };
+        Ok(ProviderSchedule {
+            trigger,
+            active_hours,
+        })
+    }
+
+    /// First run after the worker (re)loads the schedule: interval schedules run straight away.
+    pub fn first_run(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match &self.trigger {
+            Trigger::Interval(_) => Some(self.within_active_hours(now)),
+            Trigger::Cron(_) => self.next_run(now),
+        }
+    }
+
+    /// Next run strictly after `after`, moved into the active hours when needed.
+    pub fn next_run(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match &self.trigger {
+            Trigger::Interval(interval) => {
+                let next = after + ChronoDuration::from_std(*interval).ok()?;
+                Some(self.within_active_hours(next))
+            }
+            Trigger::Cron(schedule) => {
+                schedule
+                    .after(&after)
+                    .take(MAX_CRON_LOOKAHEAD)
+                    .find(|next| {
+                        self.active_hours
+                            .is_none_or(|hours| hours.contains(next.time()))
+                    })
+            }
+        }
+    }
+
+    fn within_active_hours(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        match &self.active_hours {
+            Some(hours) => hours.next_start(at),
+            None => at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use chrono::TimeZone;
+
+    fn provider(interval: Option<i32>, cron: Option<&str>, hours: Option<(u32, u32)>) -> Provider {
+        let hour = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+        Provider {
+            schedule_interval_sec: interval,
+            schedule_cron: cron.map(str::to_string),
+            active_from: hours.map(|(from, _)| hour(from)),
+            active_until: hours.map(|(_, until)| hour(until)),
+            ..test_support::provider("provider")
+        }
+    }
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, 16, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_default_and_custom_intervals() {
+        let default = Duration::from_secs(300);
+        let schedule =
+            ProviderSchedule::from_provider(&provider(None, None, None), default).unwrap();
+        assert_eq!(schedule.first_run(at(10, 0)), Some(at(10, 0)));
+        assert_eq!(schedule.next_run(at(10, 0)), Some(at(10, 5)));
+
+        let schedule =
+            ProviderSchedule::from_provider(&provider(Some(10), None, None), default).unwrap();
+        assert_eq!(
+            schedule.next_run(at(10, 0)),
+            Some(at(10, 0) + ChronoDuration::seconds(10))
+        );
+    }
+
+    #[test]
+    fn test_cron_schedule() {
+        let schedule = ProviderSchedule::from_provider(
+            &provider(None, Some("0 0 * * * *"), None),
+            Duration::from_secs(300),
+        )
+        .unwrap();
+        assert_eq!(schedule.first_run(at(10, 30)), Some(at(11, 0)));
+        assert_eq!(schedule.next_run(at(11, 0)), Some(at(12, 0)));
+    }
+
+    #[test]
+    fn test_active_hours_delay_runs_until_the_window_opens() {
+        let default = Duration::from_secs(300);
+        let schedule =
+            ProviderSchedule::from_provider(&provider(None, None, Some((8, 20))), default).unwrap();
+        assert_eq!(schedule.next_run(at(12, 0)), Some(at(12, 5)));
+        assert_eq!(
+            schedule.next_run(at(20, 0)),
+            Some(at(8, 0) + ChronoDuration::days(1))
+        );
+        assert_eq!(schedule.first_run(at(6, 0)), Some(at(8, 0)));
+
+        let schedule = ProviderSchedule::from_provider(
+            &provider(None, Some("0 0 * * * *"), Some((22, 2))),
+            default,
+        )
+        .unwrap();
+        assert_eq!(schedule.next_run(at(12, 0)), Some(at(22, 0)));
+        assert_eq!(
+            schedule.next_run(at(23, 0)),
+            Some(at(0, 0) + ChronoDuration::days(1))
+        );
+    }
+
+    #[test]
+    fn test_invalid_schedules() {
+        let default = Duration::from_secs(300);
+        assert!(matches!(
+            ProviderSchedule::from_provider(&provider(None, Some("every minute"), None), default),
+            Err(ScheduleError::InvalidCron(_, _))
+        ));
+        assert!(matches!(
+            ProviderSchedule::from_provider(&provider(Some(0), None, None), default),
+            Err(ScheduleError::InvalidInterval(0))
+        ));
+    }
+}
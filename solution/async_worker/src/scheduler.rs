@@ -0,0 +1,189 @@
+use chrono::{DateTime, Utc};
+use log::{debug, error, info, warn};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use storage::models::providers::Provider;
+use tokio::sync::{watch, Notify};
+use uuid::Uuid;
+
+use crate::context::WorkerContext;
+use crate::handler::process_provider_events;
+use crate::schedule::ProviderSchedule;
+use crate::status::CycleState;
+
+/// Runs every active provider in its own task, on its own schedule, so a slow or
+/// rarely polled provider never delays the others.
+pub struct Scheduler {
+    context: WorkerContext,
+    default_interval: Duration,
+    providers: HashMap<Uuid, watch::Sender<Provider>>,
+}
+
+impl Scheduler {
+    pub fn new(context: WorkerContext, default_interval: Duration) -> Self {
+        Scheduler {
+            context,
+            default_interval,
+            providers: HashMap::new(),
+        }
+    }
+
+    /// Starts a task for each new provider, hands changed rows to the running tasks and
+    /// stops the tasks of providers that are no longer active.
+    pub fn sync(&mut self, providers: Vec<Provider>) {
+        let active: HashSet<Uuid> = providers.iter().map(|p| p.providers_id).collect();
+        // Dropping the sender stops the task once its current run, if any, is over
+        self.providers.retain(|id, _| active.contains(id));
+        self.context.status.retain(|id| active.contains(id));
+
+        for provider in providers {
+            let id = provider.providers_id;
+            if let Some(sender) = self.providers.get(&id).filter(|s| !s.is_closed()) {
+                sender.send_if_modified(|current| {
+                    let modified = *current != provider;
+                    if modified {
+                        info!("Reloading schedule of provider: {} - {}", id, provider.name);
+                        *current = provider;
+                    }
+                    modified
+                });
+                continue;
+            }
+            info!("Scheduling provider: {} - {}", id, provider.name);
+            let trigger = self.context.status.register(&provider);
+            let (sender, receiver) = watch::channel(provider);
+            tokio::spawn(run_provider(
+                self.context.clone(),
+                self.default_interval,
+                receiver,
+                trigger,
+            ));
+            self.providers.insert(id, sender);
+        }
+    }
+}
+
+async fn run_provider(
+    context: WorkerContext,
+    default_interval: Duration,
+    mut updates: watch::Receiver<Provider>,
+    trigger: Arc<Notify>,
+) {
+    let mut last_run: Option<DateTime<Utc>> = None;
+    loop {
+        let provider = updates.borrow_and_update().clone();
+        let schedule = match ProviderSchedule::from_provider(&provider, default_interval) {
+            Ok(schedule) => Some(schedule),
+            Err(e) => {
+                error!(
+                    "Invalid schedule for provider: {} - {}: {}",
+                    provider.providers_id, provider.name, e
+                );
+                None
+            }
+        };
+        let mut next = schedule.as_ref().and_then(|schedule| match last_run {
+            Some(last_run) => schedule.next_run(last_run),
+            None => schedule.first_run(Utc::now()),
+        });
+
+        loop {
+            context.status.update(provider.providers_id, |status| {
+                status.state = match next {
+                    Some(_) => CycleState::Waiting,
+                    None => CycleState::Unscheduled,
+                };
+                status.next_run_at = next;
+            });
+            let Some(at) = next else {
+                warn!(
+                    "No upcoming run for provider: {} - {}",
+                    provider.providers_id, provider.name
+                );
+                tokio::select! {
+                    changed = updates.changed() => {
+                        if changed.is_err() {
+                            return;
+                        }
+                        break;
+                    }
+                    _ = trigger.notified() => {
+                        run_requested(&context, &provider).await;
+                        continue;
+                    }
+                }
+            };
+            let delay = (at - Utc::now()).to_std().unwrap_or_default();
+            debug!(
+                "Next run of provider {} in {} seconds",
+                provider.providers_id,
+                delay.as_secs()
+            );
+            tokio::select! {
+                changed = updates.changed() => {
+                    if changed.is_err() {
+                        return;
+                    }
+                    break;
+                }
+                _ = tokio::time::sleep(delay) => {}
+                // A requested run leaves the schedule as it is
+                _ = trigger.notified() => {
+                    run_requested(&context, &provider).await;
+                    continue;
+                }
+            }
+            last_run = Some(at);
+            run_once(&context, &provider).await;
+            // Runs missed while this one was in progress are skipped rather than queued up
+            let now = Utc::now();
+            next = schedule.as_ref().and_then(|schedule| {
+                schedule
+                    .next_run(at)
+                    .filter(|next| *next > now)
+                    .or_else(|| schedule.next_run(now))
+            });
+        }
+    }
+}
+
+async fn run_requested(context: &WorkerContext, provider: &Provider) {
+    info!(
+        "Sync requested for provider: {} - {}",
+        provider.providers_id, provider.name
+    );
+    run_once(context, provider).await;
+}
+
+async fn run_once(context: &WorkerContext, provider: &Provider) {
+    let id = provider.providers_id;
+    // Skip providers whose circuit is open
+    let circuit_open = !context.circuit_breaker.allow_request(id).await;
+    context
+        .status
+        .update(id, |status| status.circuit_open = circuit_open);
+    if circuit_open {
+        debug!(
+            "Circuit open for provider: {} - {}, skipping",
+            id, provider.name
+        );
+        return;
+    }
+    info!("Processing provider: {} - {}", id, provider.name);
+    context.status.update(id, |status| {
+        status.state = CycleState::Running;
+        status.last_run_started_at = Some(Utc::now());
+    });
+    // Run in its own task so a panic only costs this run
+    let handle = tokio::spawn(process_provider_events(context.clone(), provider.clone()));
+    if let Err(e) = handle.await {
+        error!(
+            "Processing provider: {} - {} panicked: {}",
+            id, provider.name, e
+        );
+    }
+    context
+        .status
+        .update(id, |status| status.last_run_finished_at = Some(Utc::now()));
+}
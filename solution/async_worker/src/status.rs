@@ -0,0 +1,158 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use storage::models::providers::Provider;
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+/// Where a provider task is in its cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CycleState {
+    /// Sleeping until `next_run_at`.
+    Waiting,
+    /// Fetching and persisting the provider feed.
+    Running,
+    /// No upcoming run, e.g. an invalid schedule.
+    Unscheduled,
+}
+
+/// Live state of a scheduled provider, as reported by the control server.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProviderStatus {
+    pub providers_id: Uuid,
+    pub name: String,
+    pub state: CycleState,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub last_run_started_at: Option<DateTime<Utc>>,
+    pub last_run_finished_at: Option<DateTime<Utc>>,
+    /// Whether the last run was skipped because the provider circuit is open.
+    pub circuit_open: bool,
+}
+
+struct Entry {
+    status: ProviderStatus,
+    trigger: Arc<Notify>,
+}
+
+/// Registry of the scheduled providers, shared by the scheduler tasks and the control server.
+#[derive(Clone, Default)]
+pub struct WorkerStatus {
+    providers: Arc<Mutex<HashMap<Uuid, Entry>>>,
+    providers_loaded: Arc<AtomicBool>,
+}
+
+impl WorkerStatus {
+    /// Registers a newly scheduled provider. Returns the trigger its task listens to for
+    /// runs requested outside the schedule.
+    pub fn register(&self, provider: &Provider) -> Arc<Notify> {
+        let trigger = Arc::new(Notify::new());
+        self.lock().insert(
+            provider.providers_id,
+            Entry {
+                status: ProviderStatus {
+                    providers_id: provider.providers_id,
+                    name: provider.name.clone(),
+                    state: CycleState::Waiting,
+                    next_run_at: None,
+                    last_run_started_at: None,
+                    last_run_finished_at: None,
+                    circuit_open: false,
+                },
+                trigger: trigger.clone(),
+            },
+        );
+        trigger
+    }
+
+    /// Forgets the providers that are no longer scheduled.
+    pub fn retain(&self, scheduled: impl Fn(&Uuid) -> bool) {
+        self.lock().retain(|id, _| scheduled(id));
+    }
+
+    pub fn update(&self, provider_id: Uuid, f: impl FnOnce(&mut ProviderStatus)) {
+        if let Some(entry) = self.lock().get_mut(&provider_id) {
+            f(&mut entry.status);
+        }
+    }
+
+    /// Requests an immediate run of a scheduled provider. A request made while the provider
+    /// is running starts a new run right after it. Returns `false` for unknown providers.
+    pub fn trigger(&self, provider_id: Uuid) -> bool {
+        match self.lock().get(&provider_id) {
+            Some(entry) => {
+                entry.trigger.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, provider_id: Uuid) -> Option<ProviderStatus> {
+        self.lock()
+            .get(&provider_id)
+            .map(|entry| entry.status.clone())
+    }
+
+    /// Statuses of every scheduled provider, by name.
+    pub fn list(&self) -> Vec<ProviderStatus> {
+        let mut statuses: Vec<ProviderStatus> = self
+            .lock()
+            .values()
+            .map(|entry| entry.status.clone())
+            .collect();
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        statuses
+    }
+
+    /// Marks that the providers were loaded from the database at least once.
+    pub fn set_providers_loaded(&self) {
+        self.providers_loaded.store(true, Ordering::Relaxed);
+    }
+
+    pub fn providers_loaded(&self) -> bool {
+        self.providers_loaded.load(Ordering::Relaxed)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<Uuid, Entry>> {
+        self.providers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::provider;
+
+    #[tokio::test]
+    async fn test_registered_providers_can_be_triggered() {
+        let status = WorkerStatus::default();
+        let b = provider("B");
+        let a = provider("A");
+        let trigger = status.register(&b);
+        status.register(&a);
+
+        assert!(status.trigger(b.providers_id));
+        // The permit is kept until the task waits for it
+        tokio::time::timeout(std::time::Duration::from_secs(1), trigger.notified())
+            .await
+            .expect("Expected a trigger");
+        assert!(!status.trigger(Uuid::new_v4()));
+
+        status.update(a.providers_id, |s| s.state = CycleState::Running);
+        let names: Vec<String> = status.list().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["A", "B"]);
+        assert_eq!(
+            status.get(a.providers_id).map(|s| s.state),
+            Some(CycleState::Running)
+        );
+
+        status.retain(|id| *id == a.providers_id);
+        assert!(status.get(b.providers_id).is_none());
+        assert!(!status.trigger(b.providers_id));
+    }
+}
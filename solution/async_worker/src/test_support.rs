@@ -0,0 +1,29 @@
+//! Fixtures shared by the unit tests of the worker.
+
+use storage::models::providers::Provider;
+use uuid::Uuid;
+
+/// An active, unthrottled provider of the FeverUp XML feed scheduled with the defaults.
+pub fn provider(name: &str) -> Provider {
+    Provider {
+        providers_id: Uuid::new_v4(),
+        name: name.to_string(),
+        description: String::new(),
+        url: "http://localhost/events".to_string(),
+        is_active: true,
+        created_at: chrono::NaiveDateTime::default(),
+        updated_at: chrono::NaiveDateTime::default(),
+        adapter: "feverup_xml".to_string(),
+        adapter_config: None,
+        schedule_interval_sec: None,
+        schedule_cron: None,
+        active_from: None,
+        active_until: None,
+        rate_limit_per_minute: None,
+        rate_limit_burst: None,
+        pagination: None,
+        timezone: "UTC".to_string(),
+        date_formats: None,
+        currency: "EUR".to_string(),
+    }
+}
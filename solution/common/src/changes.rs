@@ -0,0 +1,198 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use storage::connections::cache::{ChangeEvent, ChangedPlan, FieldChange};
+use storage::models::plans::Plan;
+use storage::models::zones::Zone;
+
+/// Changes of a plan written by a snapshot, given its stored row before the snapshot, if
+/// any, and the row written.
+pub fn plan_changes(plan: &ChangedPlan, stored: Option<&Plan>, current: &Plan) -> Vec<ChangeEvent> {
+    let Some(stored) = stored else {
+        return vec![ChangeEvent::PlanCreated { plan: plan.clone() }];
+    };
+    let mut events = Vec::new();
+    let mut changes = Vec::new();
+    let mut diff = |field: &str, old: Option<String>, new: Option<String>| {
+        if old != new {
+            changes.push(FieldChange {
+                field: field.to_string(),
+                old,
+                new,
+            });
+        }
+    };
+    diff(
+        "plan_start_date",
+        Some(format_date(stored.plan_start_date)),
+        Some(format_date(current.plan_start_date)),
+    );
+    diff(
+        "plan_end_date",
+        Some(format_date(stored.plan_end_date)),
+        Some(format_date(current.plan_end_date)),
+    );
+    diff(
+        "sell_from",
+        stored.sell_from.map(format_date),
+        current.sell_from.map(format_date),
+    );
+    diff(
+        "sell_to",
+        stored.sell_to.map(format_date),
+        current.sell_to.map(format_date),
+    );
+    // A plan coming back to the feed
+    diff(
+        "availability",
+        Some(stored.availability.clone()),
+        Some(current.availability.clone()),
+    );
+    if !changes.is_empty() {
+        events.push(ChangeEvent::PlanUpdated {
+            plan: plan.clone(),
+            changes,
+        });
+    }
+    if stored.sold_out != current.sold_out {
+        events.push(ChangeEvent::SoldOutChanged {
+            plan: plan.clone(),
+            sold_out: current.sold_out,
+        });
+    }
+    events
+}
+
+/// Price change of a zone of a known plan, given its stored row before the snapshot, if any,
+/// and the row written. A new zone with a price counts as a change from no price.
+pub fn zone_change(
+    plan: &ChangedPlan,
+    stored: Option<&Zone>,
+    current: &Zone,
+) -> Option<ChangeEvent> {
+    let old_price = stored.and_then(|zone| zone.price.clone());
+    let same_currency = stored.is_none_or(|zone| zone.currency == current.currency);
+    if old_price == current.price && same_currency {
+        return None;
+    }
+    Some(ChangeEvent::ZonePriceChanged {
+        plan: plan.clone(),
+        event_zone_id: current.event_zone_id.clone(),
+        numbered: current.numbered,
+        old_price,
+        new_price: current.price.clone(),
+        currency: current.currency.clone(),
+    })
+}
+
+fn format_date(date: DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use storage::BigDecimal;
+
+    fn changed_plan() -> ChangedPlan {
+        ChangedPlan {
+            provider_id: "provider".to_string(),
+            plans_id: "plan".to_string(),
+            event_base_id: "291".to_string(),
+            event_plan_id: "291".to_string(),
+        }
+    }
+
+    fn plan() -> Plan {
+        let date = DateTime::parse_from_rfc3339("2021-06-30T19:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        Plan {
+            plans_id: uuid::Uuid::new_v4(),
+            base_plans_id: uuid::Uuid::new_v4(),
+            event_plan_id: "291".to_string(),
+            plan_start_date: date,
+            plan_end_date: date + chrono::Duration::minutes(30),
+            sell_from: None,
+            sell_to: None,
+            sold_out: false,
+            created_at: chrono::NaiveDateTime::default(),
+            updated_at: chrono::NaiveDateTime::default(),
+            first_seen_at: chrono::NaiveDateTime::default(),
+            last_seen_at: chrono::NaiveDateTime::default(),
+            availability: "available".to_string(),
+        }
+    }
+
+    fn zone(price: Option<i32>) -> Zone {
+        Zone {
+            zones_id: uuid::Uuid::new_v4(),
+            plans_id: uuid::Uuid::new_v4(),
+            event_zone_id: "40".to_string(),
+            name: "Platea".to_string(),
+            capacity: Some(243),
+            price: price.map(BigDecimal::from),
+            numbered: true,
+            created_at: chrono::NaiveDateTime::default(),
+            updated_at: chrono::NaiveDateTime::default(),
+            first_seen_at: chrono::NaiveDateTime::default(),
+            last_seen_at: chrono::NaiveDateTime::default(),
+            availability: "available".to_string(),
+            currency: "EUR".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_plan_changes() {
+        let stored = plan();
+        assert_eq!(
+            plan_changes(&changed_plan(), None, &stored),
+            vec![ChangeEvent::PlanCreated {
+                plan: changed_plan()
+            }]
+        );
+        assert!(plan_changes(&changed_plan(), Some(&stored), &stored).is_empty());
+
+        let mut current = stored.clone();
+        current.plan_end_date += chrono::Duration::hours(1);
+        current.sold_out = true;
+        assert_eq!(
+            plan_changes(&changed_plan(), Some(&stored), &current),
+            vec![
+                ChangeEvent::PlanUpdated {
+                    plan: changed_plan(),
+                    changes: vec![FieldChange {
+                        field: "plan_end_date".to_string(),
+                        old: Some("2021-06-30T19:30:00Z".to_string()),
+                        new: Some("2021-06-30T20:30:00Z".to_string()),
+                    }],
+                },
+                ChangeEvent::SoldOutChanged {
+                    plan: changed_plan(),
+                    sold_out: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_zone_change() {
+        let stored = zone(Some(20));
+        assert_eq!(zone_change(&changed_plan(), Some(&stored), &stored), None);
+        assert_eq!(zone_change(&changed_plan(), None, &zone(None)), None);
+
+        let current = zone(Some(25));
+        let Some(ChangeEvent::ZonePriceChanged {
+            old_price,
+            new_price,
+            ..
+        }) = zone_change(&changed_plan(), Some(&stored), &current)
+        else {
+            panic!("Expected a zone price change");
+        };
+        assert_eq!(old_price, Some(BigDecimal::from(20)));
+        assert_eq!(new_price, Some(BigDecimal::from(25)));
+
+        let mut current = stored.clone();
+        current.currency = "USD".to_string();
+        assert!(zone_change(&changed_plan(), Some(&stored), &current).is_some());
+    }
+}
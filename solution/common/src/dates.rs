@@ -0,0 +1,217 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+
+use crate::error::DateError;
+use crate::timezone::{to_utc, Tz};
+use crate::xml_models::{BasePlan, Plan};
+
+/// Format name of RFC 3339 dates, e.g. `2021-06-30T21:00:00.250+02:00`.
+pub const RFC3339: &str = "rfc3339";
+
+/// Formats tried when a provider does not configure any: local times with optional
+/// fractional seconds, then RFC 3339.
+pub const DEFAULT_DATE_FORMATS: &[&str] = &["%Y-%m-%dT%H:%M:%S%.f", RFC3339];
+
+/// Parses the dates of a provider feed with its list of formats, tried in order. Formats
+/// are `strftime` patterns or `rfc3339`: dates with an offset are absolute, dates without
+/// one are local times of the provider timezone, and date-only formats mean midnight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateParser {
+    formats: Vec<String>,
+    timezone: Tz,
+}
+
+impl Default for DateParser {
+    fn default() -> Self {
+        DateParser {
+            formats: default_formats(),
+            timezone: Tz::UTC,
+        }
+    }
+}
+
+/// Dates of a plan, in UTC.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlanDates {
+    pub plan_start_date: DateTime<Utc>,
+    pub plan_end_date: DateTime<Utc>,
+    pub sell_from: Option<DateTime<Utc>>,
+    pub sell_to: Option<DateTime<Utc>>,
+}
+
+impl DateParser {
+    /// Builds a parser from the formats configured for a provider, or the default ones.
+    pub fn new(formats: Option<Vec<String>>, timezone: Tz) -> Result<Self, String> {
+        let formats = match formats {
+            Some(formats) if formats.is_empty() => {
+                return Err("At least one date format is required".to_string())
+            }
+            Some(formats) => formats,
+            None => default_formats(),
+        };
+        if let Some(format) = formats.iter().find(|format| !is_valid_format(format)) {
+            return Err(format!("Invalid date format: {}", format));
+        }
+        Ok(DateParser { formats, timezone })
+    }
+
+    /// Parses a date with the first format that matches it.
+    pub fn parse(&self, value: &str) -> Option<DateTime<Utc>> {
+        let value = value.trim();
+        self.formats
+            .iter()
+            .find_map(|format| parse_with(value, format, self.timezone))
+    }
+
+    /// Parses the dates of a plan. Start and end dates are required; a sell window that is
+    /// missing or cannot be parsed is left open.
+    pub fn plan_dates(&self, plan: &Plan) -> Result<PlanDates, DateError> {
+        let optional = |field: &str, value: &Option<String>| {
+            let value = value.as_deref().filter(|value| !value.trim().is_empty())?;
+            let parsed = self.parse(value);
+            if parsed.is_none() {
+                log::warn!("Ignoring unparseable {} of plan: {}", field, value);
+            }
+            parsed
+        };
+        Ok(PlanDates {
+            plan_start_date: self.required("plan_start_date", &plan.plan_start_date)?,
+            plan_end_date: self.required("plan_end_date", &plan.plan_end_date)?,
+            sell_from: optional("sell_from", &plan.sell_from),
+            sell_to: optional("sell_to", &plan.sell_to),
+        })
+    }
+
+    /// Checks that the required dates of every plan of a base plan can be parsed.
+    pub fn check(&self, base_plan: &BasePlan) -> Result<(), DateError> {
+        for plan in &base_plan.plans {
+            self.required("plan_start_date", &plan.plan_start_date)?;
+            self.required("plan_end_date", &plan.plan_end_date)?;
+        }
+        Ok(())
+    }
+
+    fn required(&self, field: &str, value: &str) -> Result<DateTime<Utc>, DateError> {
+        self.parse(value).ok_or_else(|| DateError {
+            field: field.to_string(),
+            message: format!(
+                "`{}` matches none of the date formats {}",
+                value,
+                self.formats.join(", ")
+            ),
+        })
+    }
+}
+
+fn default_formats() -> Vec<String> {
+    DEFAULT_DATE_FORMATS
+        .iter()
+        .map(|format| format.to_string())
+        .collect()
+}
+
+fn is_valid_format(format: &str) -> bool {
+    format == RFC3339
+        || (!format.is_empty()
+            && StrftimeItems::new(format).all(|item| !matches!(item, Item::Error)))
+}
+
+fn parse_with(value: &str, format: &str, timezone: Tz) -> Option<DateTime<Utc>> {
+    if format == RFC3339 {
+        return DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|date| date.with_timezone(&Utc));
+    }
+    if let Ok(date) = DateTime::parse_from_str(value, format) {
+        return Some(date.with_timezone(&Utc));
+    }
+    if let Ok(local) = NaiveDateTime::parse_from_str(value, format) {
+        return Some(to_utc(local, timezone));
+    }
+    NaiveDate::parse_from_str(value, format)
+        .ok()
+        .map(|date| to_utc(date.and_time(NaiveTime::MIN), timezone))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().into()
+    }
+
+    fn plan(start: &str, sell_from: Option<&str>) -> Plan {
+        Plan {
+            plan_start_date: start.to_string(),
+            plan_end_date: "2021-06-30T21:30:00".to_string(),
+            plan_id: Some("1".to_string()),
+            sell_from: sell_from.map(str::to_string),
+            sell_to: None,
+            sold_out: None,
+            zones: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_default_formats() {
+        let parser = DateParser::new(None, "Europe/Madrid".parse().unwrap()).unwrap();
+        assert_eq!(
+            parser.parse("2021-06-30T21:00:00"),
+            Some(utc("2021-06-30T19:00:00Z"))
+        );
+        assert_eq!(
+            parser.parse("2021-06-30T21:00:00.250"),
+            Some(utc("2021-06-30T19:00:00.250Z"))
+        );
+        assert_eq!(
+            parser.parse("2021-06-30T21:00:00-03:00"),
+            Some(utc("2021-07-01T00:00:00Z"))
+        );
+        assert_eq!(parser.parse("30/06/2021"), None);
+        assert_eq!(parser.parse(""), None);
+    }
+
+    #[test]
+    fn test_parse_configured_formats() {
+        let parser = DateParser::new(
+            Some(vec![
+                "%d/%m/%Y %H:%M".to_string(),
+                "%Y-%m-%d %H:%M:%S %z".to_string(),
+                "%Y-%m-%d".to_string(),
+            ]),
+            Tz::UTC,
+        )
+        .unwrap();
+        assert_eq!(
+            parser.parse("30/06/2021 21:00"),
+            Some(utc("2021-06-30T21:00:00Z"))
+        );
+        assert_eq!(
+            parser.parse("2021-06-30 21:00:00 +0200"),
+            Some(utc("2021-06-30T19:00:00Z"))
+        );
+        assert_eq!(
+            parser.parse("2021-06-30"),
+            Some(utc("2021-06-30T00:00:00Z"))
+        );
+        assert_eq!(parser.parse("2021-06-30T21:00:00"), None);
+
+        assert!(DateParser::new(Some(Vec::new()), Tz::UTC).is_err());
+        assert!(DateParser::new(Some(vec!["%Y-%Q".to_string()]), Tz::UTC).is_err());
+    }
+
+    #[test]
+    fn test_plan_dates() {
+        let parser = DateParser::default();
+        let dates = parser
+            .plan_dates(&plan("2021-06-30T21:00:00", Some("not a date")))
+            .unwrap();
+        assert_eq!(dates.plan_start_date, utc("2021-06-30T21:00:00Z"));
+        assert_eq!(dates.sell_from, None);
+        assert_eq!(dates.sell_to, None);
+
+        let error = parser.plan_dates(&plan("tomorrow", None)).unwrap_err();
+        assert_eq!(error.field, "plan_start_date");
+    }
+}
@@ -19,3 +19,19 @@ pub enum PersistPlansError {
     #[error("Invalid Plans - Not found: {0}")]
     NotFound(String),
 }
+
+/// A required date of a record that matches none of the date formats of its provider.
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("Invalid {field}: {message}")]
+pub struct DateError {
+    pub field: String,
+    pub message: String,
+}
+
+/// A zone value of a record that cannot be converted to the type it is stored as.
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("Invalid {field}: {message}")]
+pub struct ZoneError {
+    pub field: String,
+    pub message: String,
+}
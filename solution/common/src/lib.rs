@@ -1,4 +1,8 @@
+pub mod changes;
+pub mod dates;
 pub mod error;
 pub mod persist;
-pub mod utils;
+pub mod pools;
+pub mod timezone;
 pub mod xml_models;
+pub mod zones;
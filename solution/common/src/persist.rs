@@ -1,15 +1,34 @@
-use crate::utils::{get_cache, get_db_connection};
+use crate::changes::{plan_changes, zone_change};
+use crate::dates::DateParser;
+use crate::pools::{Pools, SharedCache};
 use crate::xml_models;
 use crate::xml_models::{EventOutput, SellModeEnum};
+use crate::zones::ZoneParser;
 
-use storage::base_plan::add_or_update_base_plan;
-use storage::connections::cache::Cache;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use storage::availability::{
+    mark_unseen_plans_unavailable, mark_unseen_zones_unavailable, snapshot_started_at,
+};
+use storage::base_plan::{add_or_update_base_plans, get_base_plans_by_ids};
+use storage::bulk::UpsertCounts;
+use storage::change_event::{add_change_events, delete_change_events, lock_change_events};
+use storage::connections::cache::{Cache, ChangeEvent, ChangedPlan, CHANGE_STREAM_KEY};
 use storage::connections::db::PgPooledConnection;
+use storage::error::StorageError;
 use storage::models::base_plans::NewBasePlan;
-use storage::models::plans::NewPlan;
-use storage::models::zones::NewZone;
-use storage::plan::add_or_update_plan;
-use storage::zone::add_or_update_zone;
+use storage::models::change_events::NewChangeEvent;
+use storage::models::organizers::NewOrganizer;
+use storage::models::plans::{NewPlan, Plan};
+use storage::models::zones::{NewZone, Zone};
+use storage::organizer::add_or_update_organizers;
+use storage::plan::{add_or_update_plans, get_plans_of_base_plans};
+use storage::transaction::{
+    begin_transaction, commit_transaction, run_in_transaction, ScopedFutureExt,
+};
+use storage::zone::{add_or_update_zones, get_zones_of_plans};
+use storage::AsyncPgConnection;
 
 // Import or define PersistPlansError
 use crate::error::PersistPlansError;
@@ -17,33 +36,268 @@ use crate::error::PersistPlansError;
 // Import serde_json for serialization
 use serde_json;
 
-pub async fn persist_base_plans(
-    base_plans: Vec<xml_models::BasePlan>,
+/// Scope of the Postgres transactions that persist a provider snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionScope {
+    /// One transaction per batch of base plans with their plans and zones.
+    #[default]
+    #[serde(alias = "base_plan")]
+    Batch,
+    /// One transaction for the whole snapshot, availability diff included. The cache entries
+    /// of the snapshot are held in memory until it is committed, so memory grows with the
+    /// size of the feed.
+    Snapshot,
+}
+
+/// Cache entries of an online plan, written once the plan is committed.
+struct CachedPlan {
+    event_base_id: String,
+    event_plan_id: String,
+    plan_start_date: DateTime<Utc>,
+    plan_end_date: DateTime<Utc>,
+    key: String,
+    event: String,
+}
+
+/// What a batch of base plans wrote, to cache once it is committed.
+struct WrittenBatch {
+    cached: Vec<CachedPlan>,
+    stats: PersistStats,
+}
+
+/// Rows written by a snapshot, counted once they are committed.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PersistStats {
+    pub base_plans: UpsertCounts,
+    pub plans: UpsertCounts,
+    pub zones: UpsertCounts,
+    /// Plans and zones of the provider marked unavailable because they dropped out of the feed.
+    pub unavailable_plans: usize,
+    pub unavailable_zones: usize,
+}
+
+impl std::ops::AddAssign for PersistStats {
+    fn add_assign(&mut self, other: Self) {
+        self.base_plans += other.base_plans;
+        self.plans += other.plans;
+        self.zones += other.zones;
+        self.unavailable_plans += other.unavailable_plans;
+        self.unavailable_zones += other.unavailable_zones;
+    }
+}
+
+/// Writes a provider snapshot to Postgres, then to the cache once the rows are committed,
+/// so a failed ingestion never leaves half-written state behind. The change events of the
+/// rows are queued in the outbox in the same transaction, and relayed to the change stream
+/// once they are committed.
+///
+/// A writer dropped before `finish` discards its connection, which rolls back the open
+/// snapshot transaction.
+pub struct SnapshotWriter {
+    conn: PgPooledConnection,
+    cache: SharedCache,
+    scope: TransactionScope,
     provider_id: uuid::Uuid,
     provider_name: String,
-) -> Result<(), PersistPlansError> {
-    if base_plans.is_empty() {
-        log::warn!(
-            "No base plans found for provider: {} - {}",
+    /// Parser of the plan dates of the provider feed.
+    dates: DateParser,
+    /// Parser of the zone capacities, prices and currencies of the provider feed.
+    zones: ZoneParser,
+    started_at: chrono::NaiveDateTime,
+    /// Cache entries and stats of the open snapshot transaction, kept until it is committed.
+    /// Only the `Snapshot` scope fills them, with every row of the feed.
+    pending: Vec<CachedPlan>,
+    pending_stats: PersistStats,
+    stats: PersistStats,
+    in_transaction: bool,
+}
+
+impl SnapshotWriter {
+    pub async fn begin(
+        pools: &Pools,
+        provider_id: uuid::Uuid,
+        provider_name: String,
+        dates: DateParser,
+        zones: ZoneParser,
+        scope: TransactionScope,
+    ) -> Result<Self, PersistPlansError> {
+        let mut conn = pools
+            .db_connection()
+            .await
+            .ok_or_else(|| PersistPlansError::DbError("Failed to get DB connection".to_string()))?;
+        let in_transaction = scope == TransactionScope::Snapshot;
+        if in_transaction {
+            begin_transaction(&mut conn).await.map_err(db_error)?;
+        }
+        let mut writer = SnapshotWriter {
+            conn,
+            cache: pools.cache.clone(),
+            scope,
             provider_id,
-            provider_name
-        );
-        return Ok(());
+            provider_name,
+            dates,
+            zones,
+            started_at: chrono::NaiveDateTime::default(),
+            pending: Vec::new(),
+            pending_stats: PersistStats::default(),
+            stats: PersistStats::default(),
+            in_transaction,
+        };
+        writer.started_at = snapshot_started_at(&mut writer.conn)
+            .await
+            .map_err(db_error)?;
+        Ok(writer)
     }
-    // Get DB connection
-    let mut pg_pool = match get_db_connection().await {
-        Some(conn) => conn,
-        None => {
-            return Err(PersistPlansError::DbError(
-                "Failed to get DB connection".to_string(),
-            ));
+
+    /// Persists base plans with their plans and zones: one multi-row upsert per table and chunk
+    /// of rows, so the number of statements grows with the number of batches, not of rows.
+    pub async fn persist(
+        &mut self,
+        base_plans: Vec<xml_models::BasePlan>,
+    ) -> Result<(), PersistPlansError> {
+        if base_plans.is_empty() {
+            log::warn!(
+                "No base plans found for provider: {} - {}",
+                self.provider_id,
+                self.provider_name
+            );
+            return Ok(());
         }
-    };
-    // Get Cache instance
-    let redis_conn = get_cache().await;
+        if let Some(bp) = base_plans.iter().find(|bp| bp.plans.is_empty()) {
+            let event_base_id = bp.base_plan_id.clone().unwrap_or_default();
+            log::warn!("No plans found for base_plan: {}", event_base_id);
+            return Err(PersistPlansError::NotFound(format!(
+                "No plans found for base_plan: {}",
+                event_base_id
+            )));
+        }
+        let provider_id = self.provider_id;
+        let dates = &self.dates;
+        let zones = &self.zones;
+        let since = self.started_at;
+        match self.scope {
+            TransactionScope::Batch => {
+                let written = run_in_transaction(&mut self.conn, |conn| {
+                    write_base_plans(conn, &base_plans, provider_id, dates, zones, since)
+                        .scope_boxed()
+                })
+                .await
+                .map_err(db_error)?;
+                self.stats += written.stats;
+                cache_online_plans(&self.cache, written.cached).await;
+                relay_changes(&mut self.conn, &self.cache).await;
+            }
+            TransactionScope::Snapshot => {
+                let written = write_base_plans(
+                    &mut self.conn,
+                    &base_plans,
+                    provider_id,
+                    dates,
+                    zones,
+                    since,
+                )
+                .await
+                .map_err(db_error)?;
+                self.pending.extend(written.cached);
+                self.pending_stats += written.stats;
+            }
+        }
+        Ok(())
+    }
 
-    for bp in base_plans {
-        let new_base_plan = NewBasePlan {
+    /// Rows committed so far. Rows of an open snapshot transaction are only counted by `finish`.
+    pub fn stats(&self) -> PersistStats {
+        self.stats
+    }
+
+    /// Completes the snapshot and returns the rows it wrote. With `mark_unseen`, the plans and
+    /// zones of the provider that were not part of it are marked unavailable, and published
+    /// as removed.
+    pub async fn finish(mut self, mark_unseen: bool) -> Result<PersistStats, PersistPlansError> {
+        let provider_id = self.provider_id;
+        let since = self.started_at;
+        let (unavailable_plans, unavailable_zones) = if !mark_unseen {
+            (0, 0)
+        } else if self.in_transaction {
+            mark_unseen_unavailable(&mut self.conn, provider_id, since)
+                .await
+                .map_err(db_error)?
+        } else {
+            run_in_transaction(&mut self.conn, |conn| {
+                mark_unseen_unavailable(conn, provider_id, since).scope_boxed()
+            })
+            .await
+            .map_err(db_error)?
+        };
+        if self.in_transaction {
+            self.in_transaction = false;
+            commit_transaction(&mut self.conn).await.map_err(db_error)?;
+        }
+        let pending_stats = std::mem::take(&mut self.pending_stats);
+        self.stats += pending_stats;
+        self.stats.unavailable_plans += unavailable_plans;
+        self.stats.unavailable_zones += unavailable_zones;
+        cache_online_plans(&self.cache, std::mem::take(&mut self.pending)).await;
+        relay_changes(&mut self.conn, &self.cache).await;
+        Ok(self.stats)
+    }
+}
+
+impl Drop for SnapshotWriter {
+    fn drop(&mut self) {
+        if self.in_transaction {
+            log::warn!(
+                "Rolling back snapshot of provider: {} - {}",
+                self.provider_id,
+                self.provider_name
+            );
+        }
+    }
+}
+
+/// Persists base plans outside of a snapshot: the availability of other plans is untouched.
+pub async fn persist_base_plans(
+    pools: &Pools,
+    base_plans: Vec<xml_models::BasePlan>,
+    provider_id: uuid::Uuid,
+    provider_name: String,
+    dates: DateParser,
+    zones: ZoneParser,
+) -> Result<PersistStats, PersistPlansError> {
+    let mut writer = SnapshotWriter::begin(
+        pools,
+        provider_id,
+        provider_name,
+        dates,
+        zones,
+        TransactionScope::Batch,
+    )
+    .await?;
+    writer.persist(base_plans).await?;
+    writer.finish(false).await
+}
+
+fn db_error(e: StorageError) -> PersistPlansError {
+    PersistPlansError::DbError(e.to_string())
+}
+
+/// Upserts base plans, plans and zones, and queues the changes made to the stored plans in
+/// the outbox. Returns the cache entries of their online plans and the rows written; rows
+/// whose `updated_at` is older than `since` were left unchanged.
+/// Plan dates are parsed with `dates` and stored in UTC, zone values are parsed with `zones`.
+async fn write_base_plans(
+    pg_pool: &mut AsyncPgConnection,
+    base_plans: &[xml_models::BasePlan],
+    provider_id: uuid::Uuid,
+    dates: &DateParser,
+    zones: &ZoneParser,
+    since: chrono::NaiveDateTime,
+) -> Result<WrittenBatch, StorageError> {
+    let organizer_ids = persist_organizers(base_plans, provider_id, pg_pool).await?;
+    let new_base_plans: Vec<NewBasePlan> = base_plans
+        .iter()
+        .map(|bp| NewBasePlan {
             base_plans_id: uuid::Uuid::new_v4(),
             providers_id: provider_id,
             event_base_id: bp.base_plan_id.clone().unwrap_or_default(),
@@ -53,228 +307,581 @@ pub async fn persist_base_plans(
                 .as_ref()
                 .map(|e| e.to_string())
                 .unwrap_or_default(),
-        };
+            organizers_id: organizer_id(&organizer_ids, bp),
+        })
+        .collect();
+    let inserted_base_plans = add_or_update_base_plans(pg_pool, &new_base_plans)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to add base_plans: {}", e);
+            e
+        })?;
+    log::debug!(
+        "Added {} base_plans for provider: {}",
+        inserted_base_plans.len(),
+        provider_id
+    );
+    // Child rows reference the ids of the stored rows, which differ from the generated ones on update
+    let base_plan_ids: HashMap<&str, uuid::Uuid> = inserted_base_plans
+        .iter()
+        .map(|bp| (bp.event_base_id.as_str(), bp.base_plans_id))
+        .collect();
+    let base_plan_counts = count_upserts(
+        new_base_plans.iter().map(|bp| bp.base_plans_id),
+        inserted_base_plans
+            .iter()
+            .map(|bp| (bp.base_plans_id, bp.updated_at)),
+        since,
+    );
 
-        // Persist the base_plan to the database and cache
-        match add_or_update_base_plan(&mut pg_pool, new_base_plan) {
-            Ok(inserted) => {
-                log::debug!(
-                    "Added base_plan: {} : {}",
-                    inserted.event_base_id,
-                    inserted.title
-                );
-
-                // Persist plans associated with this base plan and cache
-                match persist_plans(
-                    &bp.plans,
-                    bp.sell_mode.as_ref().cloned(),
-                    inserted.base_plans_id,
-                    &inserted.event_base_id,
-                    inserted.providers_id,
-                    &inserted.title,
-                    &mut pg_pool,
-                    &redis_conn,
-                )
-                .await
-                {
-                    Ok(_) => {
-                        log::debug!(
-                            "Successfully persisted plans for base plan ID: {}",
-                            inserted.base_plans_id
-                        );
-                    }
-                    Err(e) => {
-                        log::error!(
-                            "Failed to persist plans for base plan ID {}: {}",
-                            inserted.base_plans_id,
-                            e
-                        );
-                        return Err(e);
-                    }
-                }
-            }
+    // Stored state the batch is compared against, to publish what it changes
+    let stored = StoredPlans::load(pg_pool, &base_plan_ids).await?;
+    let (plans, plan_counts) =
+        persist_plans(base_plans, &base_plan_ids, dates, since, pg_pool).await?;
+    let (inserted_zones, zone_counts) =
+        persist_zones(base_plans, &base_plan_ids, &plans, zones, since, pg_pool).await?;
+    let changes = change_events(
+        &base_plan_ids,
+        &stored,
+        &plans,
+        &inserted_zones,
+        provider_id,
+    );
+    queue_changes(pg_pool, provider_id, &changes).await?;
+    let cached = online_plans(
+        base_plans,
+        &base_plan_ids,
+        &organizer_ids,
+        &plans,
+        zones,
+        provider_id,
+    );
+    let stats = PersistStats {
+        base_plans: base_plan_counts,
+        plans: plan_counts,
+        zones: zone_counts,
+        ..PersistStats::default()
+    };
+    Ok(WrittenBatch { cached, stats })
+}
+
+/// Key of a zone within the plans of a provider.
+type ZoneKey = (uuid::Uuid, String, bool);
+
+/// Plans and zones of the base plans of a batch, as stored before the batch is written.
+struct StoredPlans {
+    plans: PlanIndex,
+    zones: HashMap<ZoneKey, Zone>,
+}
+
+impl StoredPlans {
+    async fn load(
+        pg_pool: &mut AsyncPgConnection,
+        base_plan_ids: &HashMap<&str, uuid::Uuid>,
+    ) -> Result<Self, StorageError> {
+        let base_plans_ids: Vec<uuid::Uuid> = base_plan_ids.values().copied().collect();
+        let plans = get_plans_of_base_plans(pg_pool, &base_plans_ids).await?;
+        let plans_ids: Vec<uuid::Uuid> = plans.iter().map(|plan| plan.plans_id).collect();
+        let zones = get_zones_of_plans(pg_pool, &plans_ids).await?;
+        Ok(StoredPlans {
+            plans: plans
+                .into_iter()
+                .map(|plan| ((plan.base_plans_id, plan.event_plan_id.clone()), plan))
+                .collect(),
+            zones: zones
+                .into_iter()
+                .map(|zone| {
+                    (
+                        (zone.plans_id, zone.event_zone_id.clone(), zone.numbered),
+                        zone,
+                    )
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Change events of the plans written by a batch, compared to their stored state. Zones of
+/// new plans are part of their `plan_created` event.
+fn change_events(
+    base_plan_ids: &HashMap<&str, uuid::Uuid>,
+    stored: &StoredPlans,
+    plans: &PlanIndex,
+    zones: &[Zone],
+    provider_id: uuid::Uuid,
+) -> Vec<ChangeEvent> {
+    let event_base_ids: HashMap<uuid::Uuid, &str> = base_plan_ids
+        .iter()
+        .map(|(event_base_id, id)| (*id, *event_base_id))
+        .collect();
+    let mut zones_by_plan: HashMap<uuid::Uuid, Vec<&Zone>> = HashMap::new();
+    for zone in zones {
+        zones_by_plan.entry(zone.plans_id).or_default().push(zone);
+    }
+    let mut written: Vec<(&(uuid::Uuid, String), &Plan)> = plans.iter().collect();
+    written.sort_by_key(|(key, _)| *key);
+    let mut events = Vec::new();
+    for (key, plan) in written {
+        let changed = changed_plan(
+            provider_id,
+            event_base_ids
+                .get(&plan.base_plans_id)
+                .copied()
+                .unwrap_or_default(),
+            plan,
+        );
+        let stored_plan = stored.plans.get(key);
+        events.extend(plan_changes(&changed, stored_plan, plan));
+        if stored_plan.is_none() {
+            continue;
+        }
+        for zone in zones_by_plan.get(&plan.plans_id).into_iter().flatten() {
+            let zone_key = (zone.plans_id, zone.event_zone_id.clone(), zone.numbered);
+            events.extend(zone_change(&changed, stored.zones.get(&zone_key), zone));
+        }
+    }
+    events
+}
+
+/// Marks the plans and zones of a provider not seen since `since` unavailable, and queues
+/// the plans as removed. Returns how many plans and zones were marked.
+async fn mark_unseen_unavailable(
+    pg_pool: &mut AsyncPgConnection,
+    provider_id: uuid::Uuid,
+    since: chrono::NaiveDateTime,
+) -> Result<(usize, usize), StorageError> {
+    let plans = mark_unseen_plans_unavailable(pg_pool, provider_id, since).await?;
+    let zones = mark_unseen_zones_unavailable(pg_pool, provider_id, since).await?;
+    let removed = removed_plans(pg_pool, &plans, provider_id).await?;
+    queue_changes(pg_pool, provider_id, &removed).await?;
+    Ok((plans.len(), zones))
+}
+
+/// `plan_removed` events of the plans of a provider marked unavailable.
+async fn removed_plans(
+    pg_pool: &mut AsyncPgConnection,
+    plans: &[Plan],
+    provider_id: uuid::Uuid,
+) -> Result<Vec<ChangeEvent>, StorageError> {
+    if plans.is_empty() {
+        return Ok(Vec::new());
+    }
+    let base_plans_ids: Vec<uuid::Uuid> = plans.iter().map(|plan| plan.base_plans_id).collect();
+    let event_base_ids: HashMap<uuid::Uuid, String> =
+        get_base_plans_by_ids(pg_pool, &base_plans_ids)
+            .await?
+            .into_iter()
+            .map(|bp| (bp.base_plans_id, bp.event_base_id))
+            .collect();
+    Ok(plans
+        .iter()
+        .map(|plan| ChangeEvent::PlanRemoved {
+            plan: changed_plan(
+                provider_id,
+                event_base_ids
+                    .get(&plan.base_plans_id)
+                    .map(String::as_str)
+                    .unwrap_or_default(),
+                plan,
+            ),
+        })
+        .collect())
+}
+
+fn changed_plan(provider_id: uuid::Uuid, event_base_id: &str, plan: &Plan) -> ChangedPlan {
+    ChangedPlan {
+        provider_id: provider_id.to_string(),
+        plans_id: plan.plans_id.to_string(),
+        event_base_id: event_base_id.to_string(),
+        event_plan_id: plan.event_plan_id.clone(),
+    }
+}
+
+/// Number of queued change events published per relay transaction.
+const RELAY_BATCH_SIZE: i64 = 1_000;
+
+/// Queues change events in the outbox, to be relayed once their transaction is committed.
+async fn queue_changes(
+    pg_pool: &mut AsyncPgConnection,
+    provider_id: uuid::Uuid,
+    changes: &[ChangeEvent],
+) -> Result<(), StorageError> {
+    let new_events = changes
+        .iter()
+        .map(|event| {
+            Ok(NewChangeEvent {
+                providers_id: provider_id,
+                event: serde_json::to_value(event)
+                    .map_err(|e| StorageError::Other(e.to_string()))?,
+            })
+        })
+        .collect::<Result<Vec<_>, StorageError>>()?;
+    let queued = add_change_events(pg_pool, &new_events).await?;
+    log::debug!("Queued {} change events", queued);
+    Ok(())
+}
+
+/// Relays the change events queued in the outbox to the change stream, oldest first, and
+/// removes them once published. Publishing is best effort: while Redis is unreachable the
+/// events stay queued for the next relay and the ingestion goes on.
+async fn relay_changes(pg_pool: &mut AsyncPgConnection, cache: &SharedCache) {
+    let Some(redis_conn) = cache.get().await else {
+        log::warn!("Redis is unreachable, change events left in the outbox");
+        return;
+    };
+    let redis_conn = &redis_conn;
+    loop {
+        let relayed = run_in_transaction(pg_pool, |conn| {
+            relay_change_batch(conn, redis_conn).scope_boxed()
+        })
+        .await;
+        match relayed {
+            Ok(0) => return,
+            Ok(relayed) => log::debug!("Relayed {} change events", relayed),
             Err(e) => {
-                log::error!("Failed to add base_plan: {}", e);
-                return Err(PersistPlansError::DbError(e.to_string()));
+                log::error!("Failed to relay change events, left in the outbox: {}", e);
+                return;
             }
         }
     }
-    Ok(())
 }
 
-#[allow(clippy::too_many_arguments)]
+/// Publishes the oldest queued change events and removes them from the outbox. Returns how
+/// many were taken from it.
+async fn relay_change_batch(
+    pg_pool: &mut AsyncPgConnection,
+    cache: &Cache,
+) -> Result<usize, StorageError> {
+    let queued = lock_change_events(pg_pool, RELAY_BATCH_SIZE).await?;
+    let mut events = Vec::with_capacity(queued.len());
+    for row in &queued {
+        match serde_json::from_value::<ChangeEvent>(row.event.clone()) {
+            Ok(event) => events.push(event),
+            // Kept in the outbox, an event that cannot be read would block the ones after it
+            Err(e) => log::error!(
+                "Dropping unreadable change event {}: {}",
+                row.change_events_id,
+                e
+            ),
+        }
+    }
+    cache
+        .publish_change_events(CHANGE_STREAM_KEY, &events)
+        .await?;
+    let ids: Vec<i64> = queued.iter().map(|row| row.change_events_id).collect();
+    delete_change_events(pg_pool, &ids).await?;
+    Ok(queued.len())
+}
+
+/// Stored organizers of a batch, by provider organizer id.
+type OrganizerIndex = HashMap<String, uuid::Uuid>;
+
+/// Upserts the organizers referenced by the base plans of a batch.
+async fn persist_organizers(
+    base_plans: &[xml_models::BasePlan],
+    provider_id: uuid::Uuid,
+    pg_pool: &mut AsyncPgConnection,
+) -> Result<OrganizerIndex, StorageError> {
+    let new_organizers: Vec<NewOrganizer> = base_plans
+        .iter()
+        .filter_map(|bp| external_organizer_id(bp))
+        .map(|event_organizer_id| NewOrganizer {
+            organizers_id: uuid::Uuid::new_v4(),
+            providers_id: provider_id,
+            event_organizer_id: event_organizer_id.to_string(),
+        })
+        .collect();
+    if new_organizers.is_empty() {
+        return Ok(OrganizerIndex::new());
+    }
+    let inserted = add_or_update_organizers(pg_pool, &new_organizers)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to add organizers: {}", e);
+            e
+        })?;
+    Ok(inserted
+        .into_iter()
+        .map(|organizer| (organizer.event_organizer_id, organizer.organizers_id))
+        .collect())
+}
+
+/// Organizer id of a base plan as sent by the provider; blank ids mean no organizer.
+fn external_organizer_id(bp: &xml_models::BasePlan) -> Option<&str> {
+    bp.organizer_company_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+}
+
+fn organizer_id(organizer_ids: &OrganizerIndex, bp: &xml_models::BasePlan) -> Option<uuid::Uuid> {
+    organizer_ids.get(external_organizer_id(bp)?).copied()
+}
+
+/// Counts upserted rows, given the ids generated for them and the `(id, updated_at)` of the
+/// stored rows: conflicting rows keep their previous id.
+fn count_upserts(
+    generated: impl Iterator<Item = uuid::Uuid>,
+    stored: impl Iterator<Item = (uuid::Uuid, chrono::NaiveDateTime)>,
+    since: chrono::NaiveDateTime,
+) -> UpsertCounts {
+    let generated: HashSet<uuid::Uuid> = generated.collect();
+    let mut counts = UpsertCounts::default();
+    for (id, updated_at) in stored {
+        counts.add(generated.contains(&id), updated_at >= since);
+    }
+    counts
+}
+
+/// Stored plans of a batch, by base plan id and provider plan id.
+type PlanIndex = HashMap<(uuid::Uuid, String), Plan>;
+
 async fn persist_plans(
-    bp_plans: &Vec<xml_models::Plan>,
-    sell_mode: Option<SellModeEnum>,
+    base_plans: &[xml_models::BasePlan],
+    base_plan_ids: &HashMap<&str, uuid::Uuid>,
+    dates: &DateParser,
+    since: chrono::NaiveDateTime,
+    pg_pool: &mut AsyncPgConnection,
+) -> Result<(PlanIndex, UpsertCounts), StorageError> {
+    let new_plans: Vec<NewPlan> = base_plans
+        .iter()
+        .flat_map(|bp| {
+            let base_plans_id = base_plan_id(base_plan_ids, bp);
+            bp.plans
+                .iter()
+                .map(move |plan| new_plan(plan, base_plans_id, dates))
+        })
+        .collect::<Result<_, _>>()?;
+    log::debug!("Persisting {} plans", new_plans.len());
+    let inserted = add_or_update_plans(pg_pool, &new_plans)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to add plans: {}", e);
+            e
+        })?;
+    let counts = count_upserts(
+        new_plans.iter().map(|plan| plan.plans_id),
+        inserted.iter().map(|plan| (plan.plans_id, plan.updated_at)),
+        since,
+    );
+    let plans = inserted
+        .into_iter()
+        .map(|plan| ((plan.base_plans_id, plan.event_plan_id.clone()), plan))
+        .collect();
+    Ok((plans, counts))
+}
+
+/// Builds the row of a plan. Records with unparseable required dates are rejected by the
+/// adapters, so failing here aborts the batch rather than storing a made-up date.
+fn new_plan(
+    plan: &xml_models::Plan,
     base_plans_id: uuid::Uuid,
-    event_base_id: &str,
+    dates: &DateParser,
+) -> Result<NewPlan, StorageError> {
+    let plan_dates = dates
+        .plan_dates(plan)
+        .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+    Ok(NewPlan {
+        plans_id: uuid::Uuid::new_v4(),
+        base_plans_id,
+        event_plan_id: plan.plan_id.clone().unwrap_or_default(),
+        plan_start_date: plan_dates.plan_start_date,
+        plan_end_date: plan_dates.plan_end_date,
+        sell_from: plan_dates.sell_from,
+        sell_to: plan_dates.sell_to,
+        sold_out: plan.sold_out.unwrap_or(false),
+    })
+}
+
+/// Formats the values of a zone as stored, e.g. prices with two decimal places. Zones are
+/// validated before being persisted, so their values always convert.
+fn cached_zone(zone: &xml_models::Zone, zones: &ZoneParser) -> xml_models::Zone {
+    let Ok(values) = zones.zone_values(zone) else {
+        return zone.clone();
+    };
+    xml_models::Zone {
+        capacity: values.capacity.map(|capacity| capacity.to_string()),
+        price: values.price.map(|price| price.to_string()),
+        currency: Some(values.currency),
+        ..zone.clone()
+    }
+}
+
+/// Formats a stored date as served from the cache: RFC 3339 in UTC.
+fn cached_date(date: DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Cache entries of ONLY plans that are associated to a base_plan with sell mode = 'online'
+fn online_plans(
+    base_plans: &[xml_models::BasePlan],
+    base_plan_ids: &HashMap<&str, uuid::Uuid>,
+    organizer_ids: &OrganizerIndex,
+    plans: &PlanIndex,
+    zones: &ZoneParser,
     provider_id: uuid::Uuid,
-    title: &str,
-    pg_pool: &mut PgPooledConnection,
-    redis_conn: &Cache,
-) -> Result<(), PersistPlansError> {
-    if bp_plans.is_empty() {
-        log::warn!("No plans found for base_plan ID: {}", base_plans_id);
-        return Err(PersistPlansError::NotFound(format!(
-            "No plans found for base_plan ID: {}",
-            base_plans_id
-        )));
+) -> Vec<CachedPlan> {
+    let mut cached = Vec::new();
+    for bp in base_plans {
+        if bp.sell_mode.as_ref() != Some(&SellModeEnum::Online) {
+            continue;
+        }
+        let base_plans_id = base_plan_id(base_plan_ids, bp);
+        let event_base_id = bp.base_plan_id.clone().unwrap_or_default();
+        let organizer = external_organizer_id(bp).and_then(|external_id| {
+            Some(xml_models::EventOrganizer {
+                id: *organizer_ids.get(external_id)?,
+                external_id: external_id.to_string(),
+            })
+        });
+        for plan in &bp.plans {
+            let event_plan_id = plan.plan_id.clone().unwrap_or_default();
+            let Some(inserted_plan) = plans.get(&(base_plans_id, event_plan_id)) else {
+                continue;
+            };
+            // Cached dates are absolute, so the search API can render them in any timezone
+            let new_event = EventOutput {
+                base_plan_id: Some(event_base_id.clone()),
+                title: Some(bp.title.clone()),
+                sell_mode: Some(SellModeEnum::Online),
+                plan: xml_models::Plan {
+                    plan_start_date: cached_date(inserted_plan.plan_start_date),
+                    plan_end_date: cached_date(inserted_plan.plan_end_date),
+                    sell_from: inserted_plan.sell_from.map(cached_date),
+                    sell_to: inserted_plan.sell_to.map(cached_date),
+                    zones: plan
+                        .zones
+                        .iter()
+                        .map(|zone| cached_zone(zone, zones))
+                        .collect(),
+                    ..plan.clone()
+                },
+                organizer: organizer.clone(),
+            };
+            cached.push(CachedPlan {
+                event_base_id: event_base_id.clone(),
+                event_plan_id: inserted_plan.event_plan_id.clone(),
+                plan_start_date: inserted_plan.plan_start_date,
+                plan_end_date: inserted_plan.plan_end_date,
+                key: format!(
+                    "plan:{}:{}:{}",
+                    provider_id, event_base_id, inserted_plan.event_plan_id
+                ),
+                event: serde_json::to_string(&new_event).unwrap_or_default(),
+            });
+        }
     }
-    log::debug!(
-        "Persisting {} plans for base_plan ID: {}",
-        bp_plans.len(),
-        base_plans_id
-    );
-    // Clone sell_mode so it can be used multiple times
-    let sell_mode_clone = sell_mode.clone();
-
-    for plan in bp_plans {
-        let new_plan = NewPlan {
-            plans_id: uuid::Uuid::new_v4(),
-            base_plans_id,
-            event_plan_id: plan.plan_id.clone().unwrap_or_default(),
-            plan_start_date: chrono::NaiveDateTime::parse_from_str(
-                &plan.plan_start_date,
-                "%Y-%m-%dT%H:%M:%S",
-            )
-            .unwrap_or_else(|_| chrono::Utc::now().naive_utc()),
-            plan_end_date: chrono::NaiveDateTime::parse_from_str(
-                &plan.plan_end_date,
-                "%Y-%m-%dT%H:%M:%S",
-            )
-            .unwrap_or_else(|_| chrono::Utc::now().naive_utc()),
-            sell_from: plan
-                .sell_from
-                .as_ref()
-                .and_then(|s| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").ok())
-                .unwrap_or_else(|| chrono::Utc::now().naive_utc()),
-            sell_to: plan
-                .sell_to
-                .as_ref()
-                .and_then(|s| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").ok())
-                .unwrap_or_else(|| chrono::Utc::now().naive_utc()),
-            sold_out: plan.sold_out.unwrap_or(false),
-        };
+    cached
+}
 
-        match add_or_update_plan(pg_pool, new_plan) {
-            Ok(inserted_plan) => {
-                log::debug!(
-                    "Added plan: {} : {}",
-                    inserted_plan.plans_id,
-                    inserted_plan.event_plan_id
-                );
-                // Cache ONLY plan dates that are associated to a base_plan with sell mode = 'online'
-                if sell_mode_clone.as_ref() == Some(&SellModeEnum::Online) {
-                    if let Err(e) = redis_conn
-                        .cache_plan_dates(
-                            event_base_id.to_string(),
-                            inserted_plan.event_plan_id.to_string(),
-                            inserted_plan.plan_start_date,
-                            inserted_plan.plan_end_date,
-                        )
-                        .await
-                    {
-                        log::error!(
-                            "Failed to cache start/end date for online event {}: {}",
-                            inserted_plan.event_plan_id.to_string(),
-                            e
-                        );
-                        return Err(PersistPlansError::RedisError(e.to_string()));
-                    }
-
-                    let new_event = EventOutput {
-                        base_plan_id: Some(event_base_id.to_string()),
-                        title: Some(title.to_owned()),
-                        sell_mode: Some(sell_mode_clone.clone().unwrap_or(SellModeEnum::Online)),
-                        plan: plan.clone(),
-                    };
-                    // Cache the online plan
-                    if let Err(e) = redis_conn
-                        .set(
-                            format!(
-                                "plan:{}:{}:{}",
-                                provider_id, event_base_id, inserted_plan.event_plan_id
-                            ),
-                            serde_json::to_string(&new_event).unwrap_or_default(),
-                        )
-                        .await
-                    {
-                        log::error!(
-                            "Failed to cache online base_plan {}:{}:{}: {}",
-                            provider_id,
-                            event_base_id,
-                            inserted_plan.event_plan_id,
-                            e
-                        );
-                    }
-                }
-                // Convert xml_models::Zone to NewZone before persisting
-                let new_zones: Vec<NewZone> = plan
-                    .zones
-                    .iter()
-                    .map(|zone| NewZone {
-                        zones_id: uuid::Uuid::new_v4(),
-                        plans_id: inserted_plan.plans_id,
-                        name: zone.name.clone().unwrap_or_default(),
-                        capacity: zone.capacity.clone().unwrap_or_default(),
-                        event_zone_id: zone.zone_id.clone().unwrap_or_default(),
-                        price: zone.price.clone().unwrap_or_default(),
-                        numbered: zone.numbered.unwrap_or_default(),
-                        // Add other fields as required by NewZone struct
-                    })
-                    .collect();
-
-                // Persist zones for the plan
-                log::debug!(
-                    "Persisting {} zones for plan ID: {}",
-                    new_zones.len(),
-                    inserted_plan.plans_id
-                );
-                if let Err(e) = persist_zones(&new_zones, pg_pool).await {
-                    log::error!(
-                        "Failed to persist zones for plan {}: {}",
-                        inserted_plan.plans_id,
-                        e
-                    );
-                    return Err(e);
-                }
-            }
-            Err(e) => {
-                log::error!("Failed to add plan: {}", e);
-                return Err(PersistPlansError::DbError(e.to_string()));
-            }
+/// Caches the online plans of committed rows. Caching is best effort: while Redis is
+/// unreachable the plans are left uncached and the ingestion goes on.
+async fn cache_online_plans(cache: &SharedCache, cached: Vec<CachedPlan>) {
+    if cached.is_empty() {
+        return;
+    }
+    // Get Cache instance
+    let Some(redis_conn) = cache.get().await else {
+        log::warn!("Redis is unreachable, {} plans left uncached", cached.len());
+        return;
+    };
+    for plan in cached {
+        if let Err(e) = redis_conn
+            .cache_plan_dates(
+                plan.event_base_id,
+                plan.event_plan_id.clone(),
+                plan.plan_start_date,
+                plan.plan_end_date,
+            )
+            .await
+        {
+            log::error!(
+                "Failed to cache start/end date for online event {}: {}",
+                plan.event_plan_id,
+                e
+            );
+            cache.on_error(&e);
+            return;
+        }
+        // Cache the online plan
+        if let Err(e) = redis_conn.set(plan.key.clone(), plan.event).await {
+            log::error!("Failed to cache online base_plan {}: {}", plan.key, e);
+            cache.on_error(&e);
         }
     }
-    Ok(())
 }
 
 async fn persist_zones(
-    zones: &Vec<NewZone>,
-    pg_pool: &mut PgPooledConnection,
-) -> Result<(), PersistPlansError> {
-    if zones.is_empty() {
-        log::warn!("No zones to persist for this plan.");
-        return Ok(());
-    }
-    for zone in zones {
-        match add_or_update_zone(pg_pool, zone.clone()) {
-            Ok(inserted_zone) => {
-                log::debug!(
-                    "Added/updated zone: {} for plan: {}",
-                    inserted_zone.zones_id,
-                    inserted_zone.plans_id
-                );
-            }
-            Err(e) => {
-                log::error!(
-                    "Failed to add/update zone {} for plan {}: {}",
-                    zone.zones_id,
-                    zone.plans_id,
-                    e
-                );
-                return Err(PersistPlansError::DbError(e.to_string()));
+    base_plans: &[xml_models::BasePlan],
+    base_plan_ids: &HashMap<&str, uuid::Uuid>,
+    plans: &PlanIndex,
+    zones: &ZoneParser,
+    since: chrono::NaiveDateTime,
+    pg_pool: &mut AsyncPgConnection,
+) -> Result<(Vec<Zone>, UpsertCounts), StorageError> {
+    let mut new_zones = Vec::new();
+    for bp in base_plans {
+        let base_plans_id = base_plan_id(base_plan_ids, bp);
+        for plan in &bp.plans {
+            let event_plan_id = plan.plan_id.clone().unwrap_or_default();
+            let Some(inserted_plan) = plans.get(&(base_plans_id, event_plan_id)) else {
+                continue;
+            };
+            // Convert xml_models::Zone to NewZone before persisting
+            for zone in &plan.zones {
+                new_zones.push(new_zone(zone, inserted_plan.plans_id, zones)?);
             }
         }
     }
-    Ok(())
+    if new_zones.is_empty() {
+        log::warn!("No zones to persist for this batch.");
+        return Ok((Vec::new(), UpsertCounts::default()));
+    }
+    log::debug!("Persisting {} zones", new_zones.len());
+    match add_or_update_zones(pg_pool, &new_zones).await {
+        Ok(inserted) => {
+            log::debug!("Added/updated {} zones", inserted.len());
+            let counts = count_upserts(
+                new_zones.iter().map(|zone| zone.zones_id),
+                inserted.iter().map(|zone| (zone.zones_id, zone.updated_at)),
+                since,
+            );
+            Ok((inserted, counts))
+        }
+        Err(e) => {
+            log::error!("Failed to add/update zones: {}", e);
+            Err(e)
+        }
+    }
+}
+
+/// Builds the row of a zone. Records with unconvertible zone values are rejected by the
+/// adapters, so failing here aborts the batch rather than storing a lossy value.
+fn new_zone(
+    zone: &xml_models::Zone,
+    plans_id: uuid::Uuid,
+    zones: &ZoneParser,
+) -> Result<NewZone, StorageError> {
+    let values = zones
+        .zone_values(zone)
+        .map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+    Ok(NewZone {
+        zones_id: uuid::Uuid::new_v4(),
+        plans_id,
+        name: zone.name.clone().unwrap_or_default(),
+        capacity: values.capacity,
+        event_zone_id: zone.zone_id.clone().unwrap_or_default(),
+        price: values.price,
+        numbered: zone.numbered.unwrap_or_default(),
+        currency: values.currency,
+    })
+}
+
+fn base_plan_id(
+    base_plan_ids: &HashMap<&str, uuid::Uuid>,
+    bp: &xml_models::BasePlan,
+) -> uuid::Uuid {
+    let event_base_id = bp.base_plan_id.as_deref().unwrap_or_default();
+    // Every base plan of the batch was upserted, so its id is always known
+    base_plan_ids[event_base_id]
 }
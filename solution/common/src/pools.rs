@@ -0,0 +1,88 @@
+use std::env;
+use std::sync::{Arc, Mutex};
+use storage::connections::cache::Cache;
+use storage::connections::db::{build_pool, PgPool, PgPooledConnection, PoolSettings};
+use storage::error::{CacheError, StorageError};
+
+/// Connection pools shared by the whole process. They are built once at startup and
+/// cloned into every task; clones share the same connections.
+#[derive(Clone)]
+pub struct Pools {
+    pub db: PgPool,
+    pub cache: SharedCache,
+}
+
+impl Pools {
+    /// Builds the pools for `DATABASE_URL` and `REDIS_URI`. No connection is opened yet,
+    /// so neither Postgres nor Redis has to be up.
+    pub fn from_env(settings: &PoolSettings) -> Result<Self, StorageError> {
+        let database_url = env_var("DATABASE_URL")?;
+        let redis_url = env_var("REDIS_URI")?;
+        Ok(Pools {
+            db: build_pool(&database_url, settings),
+            cache: SharedCache::new(redis_url),
+        })
+    }
+
+    /// Get a PostgreSQL pooled connection.
+    pub async fn db_connection(&self) -> Option<PgPooledConnection> {
+        match self.db.get_owned().await {
+            Ok(conn) => Some(conn),
+            Err(e) => {
+                log::error!("Failed to get DB connection: {}", e);
+                None
+            }
+        }
+    }
+}
+
+fn env_var(name: &str) -> Result<String, StorageError> {
+    env::var(name).map_err(|_| StorageError::InvalidInput(format!("{} must be set", name)))
+}
+
+/// Redis connection shared by the process. It connects on first use and reconnects after
+/// the connection is lost, so Redis being down only disables caching for a while.
+#[derive(Clone)]
+pub struct SharedCache {
+    redis_url: String,
+    cache: Arc<Mutex<Option<Cache>>>,
+}
+
+impl SharedCache {
+    pub fn new(redis_url: String) -> Self {
+        SharedCache {
+            redis_url,
+            cache: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// The shared connection, or `None` while Redis is unreachable.
+    pub async fn get(&self) -> Option<Cache> {
+        if let Some(cache) = self.lock().clone() {
+            return Some(cache);
+        }
+        match Cache::with_url(self.redis_url.clone()).await {
+            Ok(cache) => {
+                *self.lock() = Some(cache.clone());
+                Some(cache)
+            }
+            Err(e) => {
+                log::error!("Failed to connect to Redis: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Drops the shared connection when `error` means it was lost, so the next `get` reconnects.
+    pub fn on_error(&self, error: &CacheError) {
+        if *error == CacheError::NotConnected {
+            self.lock().take();
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Cache>> {
+        self.cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+pub use chrono_tz::Tz;
+
+/// Parses the IANA name of a provider timezone, e.g. `Europe/Madrid`.
+pub fn parse_timezone(name: &str) -> Result<Tz, String> {
+    name.trim()
+        .parse::<Tz>()
+        .map_err(|_| format!("Unknown timezone: {}", name))
+}
+
+/// Converts a local time of `timezone` to UTC. A time repeated when clocks go back resolves
+/// to its first occurrence; a time skipped when clocks go forward is moved past the gap.
+pub fn to_utc(local: NaiveDateTime, timezone: Tz) -> DateTime<Utc> {
+    timezone
+        .from_local_datetime(&local)
+        .earliest()
+        .or_else(|| {
+            timezone
+                .from_local_datetime(&(local + Duration::hours(1)))
+                .earliest()
+        })
+        .map_or_else(|| local.and_utc(), |local| local.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S").unwrap()
+    }
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().into()
+    }
+
+    #[test]
+    fn test_to_utc() {
+        let madrid = parse_timezone("Europe/Madrid").unwrap();
+        assert_eq!(
+            to_utc(local("2021-06-30T21:00:00"), madrid),
+            utc("2021-06-30T19:00:00Z")
+        );
+        assert_eq!(
+            to_utc(local("2021-02-10T20:00:00"), madrid),
+            utc("2021-02-10T19:00:00Z")
+        );
+        // Clocks went back from 03:00 to 02:00, and forward from 02:00 to 03:00
+        assert_eq!(
+            to_utc(local("2021-10-31T02:30:00"), madrid),
+            utc("2021-10-31T00:30:00Z")
+        );
+        assert_eq!(
+            to_utc(local("2021-03-28T02:30:00"), madrid),
+            utc("2021-03-28T01:30:00Z")
+        );
+        assert_eq!(
+            to_utc(local("2021-06-30T21:00:00"), Tz::UTC),
+            utc("2021-06-30T21:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(
+            parse_timezone(" America/New_York "),
+            Ok(Tz::America__New_York)
+        );
+        assert!(parse_timezone("Mars/Olympus_Mons").is_err());
+    }
+}
@@ -56,6 +56,9 @@ pub struct Zone {
     pub name: Option<String>,
     #[serde(rename = "@numbered")]
     pub numbered: Option<bool>,
+    /// ISO 4217 currency of the price, when it differs from the one of the provider.
+    #[serde(rename = "@currency", default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -74,6 +77,15 @@ pub struct EventOutput {
     pub sell_mode: Option<SellModeEnum>,
     #[serde(rename = "plan")]
     pub plan: Plan,
+    #[serde(rename = "organizer", default, skip_serializing_if = "Option::is_none")]
+    pub organizer: Option<EventOrganizer>,
+}
+
+/// Organizer of a cached event: its stored id and the id used by the provider.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct EventOrganizer {
+    pub id: uuid::Uuid,
+    pub external_id: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -0,0 +1,211 @@
+use std::str::FromStr;
+
+use storage::BigDecimal;
+
+use crate::error::ZoneError;
+use crate::xml_models::{BasePlan, Zone};
+
+/// Currency of the prices of providers that do not configure one.
+pub const DEFAULT_CURRENCY: &str = "EUR";
+
+/// Decimal places of a stored price, as in `zones.price NUMERIC(12, 2)`.
+pub const PRICE_SCALE: i64 = 2;
+
+/// Integer digits of a stored price.
+const PRICE_INTEGER_DIGITS: usize = 10;
+
+/// Converts the capacity, price and currency of the zones of a provider feed to the typed
+/// values stored for them. Zones without a currency of their own take the provider one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneParser {
+    currency: String,
+}
+
+impl Default for ZoneParser {
+    fn default() -> Self {
+        ZoneParser {
+            currency: DEFAULT_CURRENCY.to_string(),
+        }
+    }
+}
+
+/// Typed values of a zone. A capacity or price missing from the feed is `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneValues {
+    pub capacity: Option<i32>,
+    pub price: Option<BigDecimal>,
+    pub currency: String,
+}
+
+impl ZoneParser {
+    /// Builds a parser for the currency configured for a provider.
+    pub fn new(currency: &str) -> Result<Self, String> {
+        Ok(ZoneParser {
+            currency: parse_currency(currency)?,
+        })
+    }
+
+    /// Converts the values of a zone, rejecting the ones that cannot be stored.
+    pub fn zone_values(&self, zone: &Zone) -> Result<ZoneValues, ZoneError> {
+        let capacity = present(&zone.capacity)
+            .map(|value| parse_capacity(value).map_err(|e| zone_error("capacity", e)))
+            .transpose()?;
+        let price = present(&zone.price)
+            .map(|value| parse_price(value).map_err(|e| zone_error("price", e)))
+            .transpose()?;
+        let currency = match present(&zone.currency) {
+            Some(value) => parse_currency(value).map_err(|e| zone_error("currency", e))?,
+            None => self.currency.clone(),
+        };
+        Ok(ZoneValues {
+            capacity,
+            price,
+            currency,
+        })
+    }
+
+    /// Checks that the values of every zone of a base plan can be stored.
+    pub fn check(&self, base_plan: &BasePlan) -> Result<(), ZoneError> {
+        for zone in base_plan.plans.iter().flat_map(|plan| &plan.zones) {
+            self.zone_values(zone)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a non-negative decimal price with at most `PRICE_SCALE` decimal places, e.g.
+/// `20`, `20.5` or `20.50`. Signs, exponents and separators other than `.` are rejected.
+pub fn parse_price(value: &str) -> Result<BigDecimal, String> {
+    let value = value.trim();
+    let (integer, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let is_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+    if integer.is_empty()
+        || !is_digits(integer)
+        || !is_digits(fraction)
+        || (value.contains('.') && fraction.is_empty())
+    {
+        return Err(format!("`{}` is not a decimal number", value));
+    }
+    if fraction.trim_end_matches('0').len() > PRICE_SCALE as usize {
+        return Err(format!(
+            "`{}` has more than {} decimal places",
+            value, PRICE_SCALE
+        ));
+    }
+    if integer.trim_start_matches('0').len() > PRICE_INTEGER_DIGITS {
+        return Err(format!("`{}` is too large", value));
+    }
+    BigDecimal::from_str(value)
+        .map(|price| price.with_scale(PRICE_SCALE))
+        .map_err(|e| format!("`{}` is not a decimal number: {}", value, e))
+}
+
+/// Parses a non-negative integer capacity.
+pub fn parse_capacity(value: &str) -> Result<i32, String> {
+    let value = value.trim();
+    value
+        .parse::<u32>()
+        .ok()
+        .and_then(|capacity| i32::try_from(capacity).ok())
+        .ok_or_else(|| format!("`{}` is not a non-negative integer", value))
+}
+
+/// Parses an ISO 4217 currency code, e.g. `EUR`. Codes are stored in upper case.
+pub fn parse_currency(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value.len() != 3 || !value.bytes().all(|byte| byte.is_ascii_alphabetic()) {
+        return Err(format!("`{}` is not an ISO 4217 currency code", value));
+    }
+    Ok(value.to_ascii_uppercase())
+}
+
+fn present(value: &Option<String>) -> Option<&str> {
+    value.as_deref().filter(|value| !value.trim().is_empty())
+}
+
+fn zone_error(field: &str, message: String) -> ZoneError {
+    ZoneError {
+        field: field.to_string(),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone(capacity: Option<&str>, price: Option<&str>, currency: Option<&str>) -> Zone {
+        Zone {
+            zone_id: Some("1".to_string()),
+            capacity: capacity.map(str::to_string),
+            price: price.map(str::to_string),
+            name: Some("Platea".to_string()),
+            numbered: Some(true),
+            currency: currency.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_parse_price() {
+        assert_eq!(parse_price("20").unwrap().to_string(), "20.00");
+        assert_eq!(parse_price(" 20.5 ").unwrap().to_string(), "20.50");
+        assert_eq!(parse_price("0.990").unwrap().to_string(), "0.99");
+        assert_eq!(
+            parse_price("9999999999.99").unwrap().to_string(),
+            "9999999999.99"
+        );
+        for invalid in [
+            "",
+            "abc",
+            "-5",
+            "+5",
+            "1e3",
+            "20,50",
+            ".5",
+            "5.",
+            "20.005",
+            "10000000000",
+        ] {
+            assert!(parse_price(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_capacity_and_currency() {
+        assert_eq!(parse_capacity("243"), Ok(243));
+        assert!(parse_capacity("-1").is_err());
+        assert!(parse_capacity("12.5").is_err());
+        assert!(parse_capacity("99999999999").is_err());
+
+        assert_eq!(parse_currency("usd"), Ok("USD".to_string()));
+        assert!(parse_currency("EURO").is_err());
+        assert!(parse_currency("€").is_err());
+    }
+
+    #[test]
+    fn test_zone_values() {
+        let parser = ZoneParser::new("GBP").unwrap();
+        assert_eq!(
+            parser
+                .zone_values(&zone(Some("100"), Some("15.00"), None))
+                .unwrap(),
+            ZoneValues {
+                capacity: Some(100),
+                price: Some(parse_price("15").unwrap()),
+                currency: "GBP".to_string(),
+            }
+        );
+        let values = parser
+            .zone_values(&zone(None, Some(" "), Some("eur")))
+            .unwrap();
+        assert_eq!(values.capacity, None);
+        assert_eq!(values.price, None);
+        assert_eq!(values.currency, "EUR");
+
+        let error = parser
+            .zone_values(&zone(Some("10"), Some("cheap"), None))
+            .unwrap_err();
+        assert_eq!(error.field, "price");
+        assert!(ZoneParser::new("").is_err());
+    }
+}
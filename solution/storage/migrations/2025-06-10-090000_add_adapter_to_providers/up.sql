@@ -0,0 +1,2 @@
+ALTER TABLE providers
+    ADD COLUMN adapter TEXT NOT NULL DEFAULT 'feverup_xml';
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE providers DROP COLUMN adapter_config;
@@ -0,0 +1,2 @@
+ALTER TABLE providers
+    ADD COLUMN adapter_config JSONB;
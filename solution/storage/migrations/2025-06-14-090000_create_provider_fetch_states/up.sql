@@ -0,0 +1,13 @@
+CREATE TABLE provider_fetch_states (
+    providers_id uuid PRIMARY KEY,
+    etag TEXT,
+    last_modified TEXT,
+    content_hash TEXT,
+    last_outcome TEXT NOT NULL,
+    checked_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    changed_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+
+    FOREIGN KEY (providers_id) references providers(providers_id)
+);
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE providers
+    DROP CONSTRAINT providers_active_hours_pair,
+    DROP CONSTRAINT providers_schedule_single_trigger,
+    DROP CONSTRAINT providers_schedule_interval_positive,
+    DROP COLUMN active_until,
+    DROP COLUMN active_from,
+    DROP COLUMN schedule_cron,
+    DROP COLUMN schedule_interval_sec;
@@ -0,0 +1,11 @@
+-- Per-provider polling schedule. Providers without an interval or cron expression
+-- use the worker default interval. Active hours are inclusive bounds of the day
+-- (in UTC) during which the provider may be polled; a window may wrap midnight.
+ALTER TABLE providers
+    ADD COLUMN schedule_interval_sec INTEGER,
+    ADD COLUMN schedule_cron TEXT,
+    ADD COLUMN active_from TIME,
+    ADD COLUMN active_until TIME,
+    ADD CONSTRAINT providers_schedule_interval_positive CHECK (schedule_interval_sec > 0),
+    ADD CONSTRAINT providers_schedule_single_trigger CHECK (schedule_interval_sec IS NULL OR schedule_cron IS NULL),
+    ADD CONSTRAINT providers_active_hours_pair CHECK ((active_from IS NULL) = (active_until IS NULL));
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX zones_availability_idx;
+DROP INDEX plans_availability_idx;
+ALTER TABLE zones DROP COLUMN availability, DROP COLUMN last_seen_at, DROP COLUMN first_seen_at;
+ALTER TABLE plans DROP COLUMN availability, DROP COLUMN last_seen_at, DROP COLUMN first_seen_at;
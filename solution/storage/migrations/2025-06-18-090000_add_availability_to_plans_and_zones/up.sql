@@ -0,0 +1,17 @@
+-- When each plan and zone was first and last present in a provider snapshot, and
+-- whether it is still offered ('available') or has dropped out of the feed ('unavailable').
+ALTER TABLE plans
+    ADD COLUMN first_seen_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    ADD COLUMN last_seen_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    ADD COLUMN availability TEXT NOT NULL DEFAULT 'available';
+
+ALTER TABLE zones
+    ADD COLUMN first_seen_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    ADD COLUMN last_seen_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    ADD COLUMN availability TEXT NOT NULL DEFAULT 'available';
+
+UPDATE plans SET first_seen_at = created_at, last_seen_at = updated_at;
+UPDATE zones SET first_seen_at = created_at, last_seen_at = updated_at;
+
+CREATE INDEX plans_availability_idx ON plans (availability, last_seen_at);
+CREATE INDEX zones_availability_idx ON zones (availability, last_seen_at);
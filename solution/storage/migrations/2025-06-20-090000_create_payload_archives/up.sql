@@ -0,0 +1,17 @@
+-- Raw provider payloads, kept for debugging and replay. The body is stored inline
+-- (`body`) or as a file of the worker archive directory (`path`).
+CREATE TABLE payload_archives (
+    payload_archives_id uuid PRIMARY KEY,
+    providers_id uuid NOT NULL REFERENCES providers(providers_id),
+    fetched_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    status INTEGER NOT NULL,
+    content_hash TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    body BYTEA,
+    path TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CONSTRAINT payload_archives_body_or_path CHECK ((body IS NULL) <> (path IS NULL))
+);
+
+CREATE INDEX payload_archives_provider_idx ON payload_archives (providers_id, fetched_at DESC);
+CREATE INDEX payload_archives_fetched_at_idx ON payload_archives (fetched_at);
@@ -0,0 +1,17 @@
+-- Provider records that could not be parsed, kept with their raw fragment and a structured
+-- reason until they are re-processed. A record seen again bumps `occurrences`.
+CREATE TABLE quarantined_records (
+    quarantined_records_id uuid PRIMARY KEY,
+    providers_id uuid NOT NULL REFERENCES providers(providers_id),
+    payload_archives_id uuid REFERENCES payload_archives(payload_archives_id) ON DELETE SET NULL,
+    record_hash TEXT NOT NULL,
+    raw TEXT NOT NULL,
+    reason JSONB NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    occurrences INTEGER NOT NULL DEFAULT 1,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (providers_id, record_hash)
+);
+
+CREATE INDEX quarantined_records_status_idx ON quarantined_records (status, providers_id);
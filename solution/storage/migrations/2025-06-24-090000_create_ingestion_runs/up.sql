@@ -0,0 +1,27 @@
+-- One row per worker run of a provider: what was fetched, how long parsing took, what was
+-- persisted and, for failed runs, the kind of error.
+CREATE TABLE ingestion_runs (
+    ingestion_runs_id uuid PRIMARY KEY,
+    providers_id uuid NOT NULL REFERENCES providers(providers_id),
+    status TEXT NOT NULL,
+    started_at TIMESTAMP NOT NULL,
+    finished_at TIMESTAMP NOT NULL,
+    http_status INTEGER,
+    bytes BIGINT,
+    parse_duration_ms BIGINT,
+    base_plans_inserted INTEGER NOT NULL DEFAULT 0,
+    base_plans_updated INTEGER NOT NULL DEFAULT 0,
+    base_plans_unchanged INTEGER NOT NULL DEFAULT 0,
+    plans_inserted INTEGER NOT NULL DEFAULT 0,
+    plans_updated INTEGER NOT NULL DEFAULT 0,
+    plans_unchanged INTEGER NOT NULL DEFAULT 0,
+    zones_inserted INTEGER NOT NULL DEFAULT 0,
+    zones_updated INTEGER NOT NULL DEFAULT 0,
+    zones_unchanged INTEGER NOT NULL DEFAULT 0,
+    quarantined INTEGER NOT NULL DEFAULT 0,
+    error_kind TEXT,
+    error_message TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX ingestion_runs_provider_idx ON ingestion_runs (providers_id, started_at DESC);
@@ -0,0 +1,10 @@
+-- Authentication of the provider feeds. `secret` holds the whole auth configuration,
+-- encrypted with AES-256-GCM (12 byte nonce followed by the ciphertext); `auth_type` is
+-- kept in clear for inspection only.
+CREATE TABLE provider_credentials (
+    providers_id uuid PRIMARY KEY REFERENCES providers(providers_id) ON DELETE CASCADE,
+    auth_type TEXT NOT NULL,
+    secret BYTEA NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE providers
+    DROP CONSTRAINT providers_rate_limit_burst_positive,
+    DROP CONSTRAINT providers_rate_limit_positive,
+    DROP COLUMN rate_limit_burst,
+    DROP COLUMN rate_limit_per_minute;
@@ -0,0 +1,8 @@
+-- Per-provider outbound rate limit, enforced by a token bucket shared by every worker
+-- instance: `rate_limit_per_minute` tokens are refilled per minute, up to `rate_limit_burst`.
+-- Providers without a rate limit are not throttled.
+ALTER TABLE providers
+    ADD COLUMN rate_limit_per_minute INTEGER,
+    ADD COLUMN rate_limit_burst INTEGER,
+    ADD CONSTRAINT providers_rate_limit_positive CHECK (rate_limit_per_minute > 0),
+    ADD CONSTRAINT providers_rate_limit_burst_positive CHECK (rate_limit_burst > 0);
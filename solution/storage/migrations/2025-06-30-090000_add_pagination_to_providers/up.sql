@@ -0,0 +1,4 @@
+-- Pagination strategy of the provider feed (page, offset, cursor or link_header) and its
+-- guards. Providers without one return their whole catalogue in a single response.
+ALTER TABLE providers
+    ADD COLUMN pagination JSONB;
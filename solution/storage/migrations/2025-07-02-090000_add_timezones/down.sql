@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE plans
+    ALTER COLUMN plan_start_date TYPE TIMESTAMP USING plan_start_date AT TIME ZONE 'UTC',
+    ALTER COLUMN plan_end_date TYPE TIMESTAMP USING plan_end_date AT TIME ZONE 'UTC',
+    ALTER COLUMN sell_from TYPE TIMESTAMP USING sell_from AT TIME ZONE 'UTC',
+    ALTER COLUMN sell_to TYPE TIMESTAMP USING sell_to AT TIME ZONE 'UTC';
+
+ALTER TABLE providers
+    DROP COLUMN timezone;
@@ -0,0 +1,10 @@
+-- IANA timezone of the local times sent by the provider feed.
+ALTER TABLE providers
+    ADD COLUMN timezone TEXT NOT NULL DEFAULT 'UTC';
+
+-- Plan dates were stored as UTC wall-clock times.
+ALTER TABLE plans
+    ALTER COLUMN plan_start_date TYPE TIMESTAMPTZ USING plan_start_date AT TIME ZONE 'UTC',
+    ALTER COLUMN plan_end_date TYPE TIMESTAMPTZ USING plan_end_date AT TIME ZONE 'UTC',
+    ALTER COLUMN sell_from TYPE TIMESTAMPTZ USING sell_from AT TIME ZONE 'UTC',
+    ALTER COLUMN sell_to TYPE TIMESTAMPTZ USING sell_to AT TIME ZONE 'UTC';
@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+UPDATE plans SET sell_from = created_at AT TIME ZONE 'UTC' WHERE sell_from IS NULL;
+UPDATE plans SET sell_to = created_at AT TIME ZONE 'UTC' WHERE sell_to IS NULL;
+
+ALTER TABLE plans
+    ALTER COLUMN sell_from SET NOT NULL,
+    ALTER COLUMN sell_to SET NOT NULL;
+
+ALTER TABLE providers
+    DROP COLUMN date_formats;
@@ -0,0 +1,9 @@
+-- Formats of the dates sent by the provider feed, tried in order: a JSON array of strftime
+-- patterns or "rfc3339". Providers without one use the default formats of the worker.
+ALTER TABLE providers
+    ADD COLUMN date_formats JSONB;
+
+-- Sell windows missing from the feed, or not parseable, are left open.
+ALTER TABLE plans
+    ALTER COLUMN sell_from DROP NOT NULL,
+    ALTER COLUMN sell_to DROP NOT NULL;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE base_plans
+    DROP COLUMN organizers_id;
+
+DROP TABLE organizers;
@@ -0,0 +1,15 @@
+-- Organizers of the events, as identified by each provider (`organizer_company_id`).
+CREATE TABLE organizers (
+    organizers_id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    providers_id uuid NOT NULL REFERENCES providers(providers_id) ON DELETE CASCADE,
+    event_organizer_id TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+
+    UNIQUE (providers_id, event_organizer_id)
+);
+
+ALTER TABLE base_plans
+    ADD COLUMN organizers_id uuid REFERENCES organizers(organizers_id) ON DELETE SET NULL;
+
+CREATE INDEX base_plans_organizers_id_idx ON base_plans (organizers_id);
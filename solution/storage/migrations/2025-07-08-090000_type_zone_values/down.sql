@@ -0,0 +1,24 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE zones
+    DROP COLUMN currency,
+    DROP CONSTRAINT zones_price_check,
+    DROP CONSTRAINT zones_capacity_check,
+    ALTER COLUMN price TYPE TEXT USING COALESCE(price::text, ''),
+    ALTER COLUMN price SET NOT NULL,
+    ALTER COLUMN capacity TYPE TEXT USING COALESCE(capacity::text, ''),
+    ALTER COLUMN capacity SET NOT NULL;
+
+UPDATE zones z
+SET price = f.raw_value
+FROM zone_conversion_failures f
+WHERE f.zones_id = z.zones_id AND f.column_name = 'price';
+
+UPDATE zones z
+SET capacity = f.raw_value
+FROM zone_conversion_failures f
+WHERE f.zones_id = z.zones_id AND f.column_name = 'capacity';
+
+DROP TABLE zone_conversion_failures;
+
+ALTER TABLE providers
+    DROP COLUMN currency;
@@ -0,0 +1,78 @@
+-- ISO 4217 currency of the prices of a provider feed, unless a zone sends its own.
+ALTER TABLE providers
+    ADD COLUMN currency TEXT NOT NULL DEFAULT 'EUR' CHECK (currency ~ '^[A-Z]{3}$');
+
+-- Zone values that could not be converted to their typed columns. They are stored as NULL
+-- and kept here with their original text for review.
+CREATE TABLE zone_conversion_failures (
+    zones_id uuid NOT NULL REFERENCES zones(zones_id) ON DELETE CASCADE,
+    column_name TEXT NOT NULL,
+    raw_value TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (zones_id, column_name)
+);
+
+-- Prices are non-negative decimals with at most 2 decimal places and 10 integer digits.
+INSERT INTO zone_conversion_failures (zones_id, column_name, raw_value)
+SELECT zones_id, 'price', price
+FROM zones
+WHERE btrim(price) <> ''
+  AND CASE
+        WHEN btrim(price) ~ '^[0-9]+(\.[0-9]+)?$'
+            THEN btrim(price)::numeric >= 1e10
+                 OR scale(trim_scale(btrim(price)::numeric)) > 2
+        ELSE TRUE
+      END;
+
+-- Capacities are non-negative integers.
+INSERT INTO zone_conversion_failures (zones_id, column_name, raw_value)
+SELECT zones_id, 'capacity', capacity
+FROM zones
+WHERE btrim(capacity) <> ''
+  AND CASE
+        WHEN btrim(capacity) ~ '^[0-9]{1,10}$' THEN btrim(capacity)::bigint > 2147483647
+        ELSE TRUE
+      END;
+
+UPDATE zones
+SET price = ''
+WHERE zones_id IN (
+    SELECT zones_id FROM zone_conversion_failures WHERE column_name = 'price'
+);
+
+UPDATE zones
+SET capacity = ''
+WHERE zones_id IN (
+    SELECT zones_id FROM zone_conversion_failures WHERE column_name = 'capacity'
+);
+
+ALTER TABLE zones
+    ALTER COLUMN price DROP NOT NULL,
+    ALTER COLUMN price TYPE NUMERIC(12, 2) USING NULLIF(btrim(price), '')::numeric,
+    ALTER COLUMN capacity DROP NOT NULL,
+    ALTER COLUMN capacity TYPE INTEGER USING NULLIF(btrim(capacity), '')::integer,
+    ADD CONSTRAINT zones_price_check CHECK (price >= 0),
+    ADD CONSTRAINT zones_capacity_check CHECK (capacity >= 0),
+    ADD COLUMN currency TEXT NOT NULL DEFAULT 'EUR' CHECK (currency ~ '^[A-Z]{3}$');
+
+-- Existing zones take the currency of their provider; new ones are always written with one.
+UPDATE zones z
+SET currency = pr.currency
+FROM plans p
+JOIN base_plans bp ON bp.base_plans_id = p.base_plans_id
+JOIN providers pr ON pr.providers_id = bp.providers_id
+WHERE p.plans_id = z.plans_id;
+
+ALTER TABLE zones
+    ALTER COLUMN currency DROP DEFAULT;
+
+DO $$
+DECLARE
+    failures INTEGER;
+BEGIN
+    SELECT count(*) INTO failures FROM zone_conversion_failures;
+    IF failures > 0 THEN
+        RAISE WARNING '% zone values could not be converted and were set to NULL, see zone_conversion_failures', failures;
+    END IF;
+END $$;
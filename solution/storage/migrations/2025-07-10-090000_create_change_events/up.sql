@@ -0,0 +1,9 @@
+-- Outbox of the change events of plans, written in the transaction of the rows they
+-- describe and deleted once relayed to the change stream. Ids follow the order in which the
+-- events were written, which is the order they are published in.
+CREATE TABLE change_events (
+    change_events_id BIGSERIAL PRIMARY KEY,
+    providers_id uuid NOT NULL REFERENCES providers(providers_id) ON DELETE CASCADE,
+    event JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,181 @@
+use crate::error::StorageError;
+use crate::models::availability::Availability;
+use crate::models::plans::Plan;
+use crate::schema::{base_plans, plans, zones};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+/// Database clock at the start of a provider snapshot. Rows upserted by the snapshot get a
+/// `last_seen_at` at or after it.
+pub async fn snapshot_started_at(
+    connection: &mut AsyncPgConnection,
+) -> Result<chrono::NaiveDateTime, StorageError> {
+    diesel::select(diesel::dsl::now)
+        .get_result(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+/// Marks the available plans of a provider that were not seen since `seen_since` as unavailable.
+/// Returns the plans it marked.
+pub async fn mark_unseen_plans_unavailable(
+    connection: &mut AsyncPgConnection,
+    provider_id: Uuid,
+    seen_since: chrono::NaiveDateTime,
+) -> Result<Vec<Plan>, StorageError> {
+    let provider_base_plans = base_plans::table
+        .filter(base_plans::providers_id.eq(provider_id))
+        .select(base_plans::base_plans_id);
+    diesel::update(
+        plans::table
+            .filter(plans::base_plans_id.eq_any(provider_base_plans))
+            .filter(plans::availability.eq(Availability::Available.to_string()))
+            .filter(plans::last_seen_at.lt(seen_since)),
+    )
+    .set((
+        plans::availability.eq(Availability::Unavailable.to_string()),
+        plans::updated_at.eq(diesel::dsl::now),
+    ))
+    .get_results(connection)
+    .await
+    .map_err(StorageError::from)
+}
+
+/// Marks the available zones of a provider that were not seen since `seen_since` as unavailable.
+pub async fn mark_unseen_zones_unavailable(
+    connection: &mut AsyncPgConnection,
+    provider_id: Uuid,
+    seen_since: chrono::NaiveDateTime,
+) -> Result<usize, StorageError> {
+    let provider_plans = plans::table
+        .inner_join(base_plans::table)
+        .filter(base_plans::providers_id.eq(provider_id))
+        .select(plans::plans_id);
+    diesel::update(
+        zones::table
+            .filter(zones::plans_id.eq_any(provider_plans))
+            .filter(zones::availability.eq(Availability::Available.to_string()))
+            .filter(zones::last_seen_at.lt(seen_since)),
+    )
+    .set((
+        zones::availability.eq(Availability::Unavailable.to_string()),
+        zones::updated_at.eq(diesel::dsl::now),
+    ))
+    .execute(connection)
+    .await
+    .map_err(StorageError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base_plan::add_or_update_base_plan;
+    use crate::connections::db::establish_connection;
+    use crate::models::base_plans::NewBasePlan;
+    use crate::models::plans::NewPlan;
+    use crate::models::zones::NewZone;
+    use crate::plan::add_or_update_plan;
+    use crate::provider::add_or_update_provider;
+    use crate::test_support::new_provider;
+    use crate::zone::add_or_update_zone;
+    use bigdecimal::BigDecimal;
+
+    fn new_plan(base_plans_id: Uuid, event_plan_id: &str) -> NewPlan {
+        let now = chrono::Utc::now();
+        NewPlan {
+            plans_id: Uuid::new_v4(),
+            base_plans_id,
+            event_plan_id: event_plan_id.to_string(),
+            plan_start_date: now,
+            plan_end_date: now,
+            sell_from: Some(now),
+            sell_to: Some(now),
+            sold_out: false,
+        }
+    }
+
+    fn new_zone(plans_id: Uuid, event_zone_id: &str) -> NewZone {
+        NewZone {
+            zones_id: Uuid::new_v4(),
+            plans_id,
+            event_zone_id: event_zone_id.to_string(),
+            name: "Platea".to_string(),
+            capacity: Some(10),
+            price: Some(BigDecimal::from(20)),
+            numbered: true,
+            currency: "EUR".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unseen_plans_and_zones_become_unavailable() {
+        let connection = establish_connection().await;
+        let mut pg_pool = connection
+            .get_owned()
+            .await
+            .expect("Failed to get connection from pool");
+
+        let provider = add_or_update_provider(&mut pg_pool, new_provider("Availability test"))
+            .await
+            .expect("Expected Ok result");
+        let base_plan = add_or_update_base_plan(
+            &mut pg_pool,
+            NewBasePlan {
+                base_plans_id: Uuid::new_v4(),
+                providers_id: provider.providers_id,
+                event_base_id: "1".to_string(),
+                title: "Availability test".to_string(),
+                sell_mode: "online".to_string(),
+                organizers_id: None,
+            },
+        )
+        .await
+        .expect("Expected Ok result");
+        let kept = add_or_update_plan(&mut pg_pool, new_plan(base_plan.base_plans_id, "1"))
+            .await
+            .unwrap();
+        let dropped = add_or_update_plan(&mut pg_pool, new_plan(base_plan.base_plans_id, "2"))
+            .await
+            .unwrap();
+        add_or_update_zone(&mut pg_pool, new_zone(kept.plans_id, "1"))
+            .await
+            .unwrap();
+        add_or_update_zone(&mut pg_pool, new_zone(kept.plans_id, "2"))
+            .await
+            .unwrap();
+
+        // Second snapshot: plan 2 and zone 2 are gone
+        let started_at = snapshot_started_at(&mut pg_pool)
+            .await
+            .expect("Expected Ok result");
+        let kept = add_or_update_plan(&mut pg_pool, new_plan(base_plan.base_plans_id, "1"))
+            .await
+            .unwrap();
+        let zone = add_or_update_zone(&mut pg_pool, new_zone(kept.plans_id, "1"))
+            .await
+            .unwrap();
+
+        let plans = mark_unseen_plans_unavailable(&mut pg_pool, provider.providers_id, started_at)
+            .await
+            .expect("Expected Ok result");
+        let zones = mark_unseen_zones_unavailable(&mut pg_pool, provider.providers_id, started_at)
+            .await
+            .expect("Expected Ok result");
+        assert_eq!(plans.len(), 1);
+        assert_eq!(plans[0].plans_id, dropped.plans_id);
+        assert_eq!(plans[0].availability, "unavailable");
+        assert_eq!(zones, 1);
+
+        let dropped: String = plans::table
+            .find(dropped.plans_id)
+            .select(plans::availability)
+            .first(&mut pg_pool)
+            .await
+            .unwrap();
+        assert_eq!(dropped, "unavailable");
+        assert_eq!(kept.availability, "available");
+        assert!(kept.first_seen_at < kept.last_seen_at);
+        assert_eq!(zone.availability, "available");
+    }
+}
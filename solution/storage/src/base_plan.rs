@@ -1,22 +1,41 @@
-use crate::connections::db::PgPooledConnection;
+use crate::bulk::{last_by_key, rows_per_chunk, updated_at_if_changed};
 use crate::error::StorageError;
 use crate::models::base_plans::*;
-use crate::schema::base_plans;
-use crate::schema::base_plans::sell_mode;
-use crate::schema::base_plans::title;
-use crate::schema::base_plans::updated_at;
+use crate::schema::base_plans::{self, organizers_id, sell_mode, title, updated_at};
 use diesel::insert_into;
+use diesel::pg::upsert::excluded;
 use diesel::prelude::*;
-use diesel::RunQueryDsl;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 
-pub fn get_base_plans(connection: &mut PgPooledConnection) -> Result<Vec<BasePlan>, StorageError> {
+/// Bind parameters of one `NewBasePlan` row.
+pub(crate) const BASE_PLAN_PARAMS: usize = 6;
+
+/// Columns whose change bumps `updated_at` on upsert.
+pub(crate) const BASE_PLAN_CHANGE_COLUMNS: &[&str] = &["title", "sell_mode", "organizers_id"];
+
+pub async fn get_base_plans(
+    connection: &mut AsyncPgConnection,
+) -> Result<Vec<BasePlan>, StorageError> {
     base_plans::table
         .load::<BasePlan>(connection)
+        .await
         .map_err(StorageError::from)
 }
 
-pub fn add_or_update_base_plan(
-    connection: &mut PgPooledConnection,
+/// Loads the base plans with the given ids.
+pub async fn get_base_plans_by_ids(
+    connection: &mut AsyncPgConnection,
+    base_plans_ids: &[uuid::Uuid],
+) -> Result<Vec<BasePlan>, StorageError> {
+    base_plans::table
+        .filter(base_plans::base_plans_id.eq_any(base_plans_ids))
+        .load::<BasePlan>(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+pub async fn add_or_update_base_plan(
+    connection: &mut AsyncPgConnection,
     new_base_plan: NewBasePlan,
 ) -> Result<BasePlan, StorageError> {
     insert_into(base_plans::table)
@@ -26,8 +45,41 @@ pub fn add_or_update_base_plan(
         .set((
             title.eq(&new_base_plan.title),
             sell_mode.eq(&new_base_plan.sell_mode),
-            updated_at.eq(diesel::dsl::now), // Use current time for updated_at
-        )) // Handle conflict if the event already exists
+            organizers_id.eq(&new_base_plan.organizers_id),
+            updated_at.eq(diesel::dsl::now),
+        ))
         .get_result(connection)
+        .await
         .map_err(StorageError::from)
 }
+
+/// Multi-row variant of `add_or_update_base_plan`, one statement per chunk of rows.
+/// When a base plan appears more than once, its last occurrence wins.
+pub async fn add_or_update_base_plans(
+    connection: &mut AsyncPgConnection,
+    new_base_plans: &[NewBasePlan],
+) -> Result<Vec<BasePlan>, StorageError> {
+    let rows = last_by_key(new_base_plans, |bp| {
+        (bp.providers_id, bp.event_base_id.clone())
+    });
+    let mut inserted = Vec::with_capacity(rows.len());
+    for chunk in rows.chunks(rows_per_chunk(BASE_PLAN_PARAMS)) {
+        let chunk_rows: Vec<BasePlan> = insert_into(base_plans::table)
+            .values(chunk.to_vec())
+            .on_conflict((base_plans::providers_id, base_plans::event_base_id))
+            .do_update()
+            .set((
+                title.eq(excluded(title)),
+                sell_mode.eq(excluded(sell_mode)),
+                organizers_id.eq(excluded(organizers_id)),
+                updated_at.eq(updated_at_if_changed(
+                    "base_plans",
+                    BASE_PLAN_CHANGE_COLUMNS,
+                )),
+            ))
+            .get_results(connection)
+            .await?;
+        inserted.extend(chunk_rows);
+    }
+    Ok(inserted)
+}
@@ -0,0 +1,97 @@
+use diesel::dsl::sql;
+use diesel::expression::SqlLiteral;
+use diesel::sql_types::Timestamp;
+use std::collections::HashSet;
+use std::hash::Hash;
+
+/// Maximum number of bind parameters of a single Postgres statement.
+pub const MAX_BIND_PARAMS: usize = 65_535;
+
+/// Number of rows of `params_per_row` bind parameters that fit in one statement.
+pub fn rows_per_chunk(params_per_row: usize) -> usize {
+    (MAX_BIND_PARAMS / params_per_row.max(1)).max(1)
+}
+
+/// Keeps the last row of each conflict key, in order. An `INSERT ... ON CONFLICT DO UPDATE`
+/// fails when it would update the same row twice.
+pub(crate) fn last_by_key<T, K, F>(rows: &[T], key: F) -> Vec<&T>
+where
+    K: Eq + Hash,
+    F: Fn(&T) -> K,
+{
+    let mut seen = HashSet::new();
+    let mut unique: Vec<&T> = rows
+        .iter()
+        .rev()
+        .filter(|row| seen.insert(key(row)))
+        .collect();
+    unique.reverse();
+    unique
+}
+
+/// `updated_at` of an upserted row: `NOW()` when one of `columns` differs from the proposed
+/// row, its previous value otherwise, so that unchanged rows can be told apart from updated ones.
+pub(crate) fn updated_at_if_changed(table: &str, columns: &[&str]) -> SqlLiteral<Timestamp> {
+    let stored: Vec<String> = columns
+        .iter()
+        .map(|column| format!("{}.{}", table, column))
+        .collect();
+    let proposed: Vec<String> = columns
+        .iter()
+        .map(|column| format!("excluded.{}", column))
+        .collect();
+    sql(&format!(
+        "CASE WHEN ({}) IS DISTINCT FROM ({}) THEN NOW() ELSE {}.updated_at END",
+        stored.join(", "),
+        proposed.join(", "),
+        table
+    ))
+}
+
+/// Number of rows of a table inserted, updated and left unchanged by upserts.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct UpsertCounts {
+    pub inserted: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+}
+
+impl UpsertCounts {
+    /// Counts an upserted row. A row is `inserted` when it kept the id generated for it, and
+    /// `changed` when its `updated_at` was bumped by the upsert.
+    pub fn add(&mut self, inserted: bool, changed: bool) {
+        if inserted {
+            self.inserted += 1;
+        } else if changed {
+            self.updated += 1;
+        } else {
+            self.unchanged += 1;
+        }
+    }
+}
+
+impl std::ops::AddAssign for UpsertCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.inserted += other.inserted;
+        self.updated += other.updated;
+        self.unchanged += other.unchanged;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_per_chunk_fits_the_parameter_limit() {
+        assert_eq!(rows_per_chunk(7), 9_362);
+        assert!(rows_per_chunk(7) * 7 <= MAX_BIND_PARAMS);
+        assert_eq!(rows_per_chunk(0), MAX_BIND_PARAMS);
+    }
+
+    #[test]
+    fn test_last_by_key_keeps_last_occurrence() {
+        let rows = [("a", 1), ("b", 2), ("a", 3)];
+        assert_eq!(last_by_key(&rows, |row| row.0), vec![&("b", 2), &("a", 3)]);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::bulk::rows_per_chunk;
+use crate::error::StorageError;
+use crate::models::change_events::*;
+use crate::schema::change_events;
+use diesel::insert_into;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+/// Bind parameters of one `NewChangeEvent` row.
+pub(crate) const CHANGE_EVENT_PARAMS: usize = 2;
+
+/// Queues change events in the outbox, one statement per chunk of rows. Run it in the
+/// transaction of the rows the events describe, so that both are committed together.
+pub async fn add_change_events(
+    connection: &mut AsyncPgConnection,
+    new_events: &[NewChangeEvent],
+) -> Result<usize, StorageError> {
+    let mut added = 0;
+    for chunk in new_events.chunks(rows_per_chunk(CHANGE_EVENT_PARAMS)) {
+        added += insert_into(change_events::table)
+            .values(chunk)
+            .execute(connection)
+            .await?;
+    }
+    Ok(added)
+}
+
+/// Oldest queued change events, locked until the end of the transaction. Events locked by
+/// another transaction are skipped, so concurrent relays never pick the same event.
+pub async fn lock_change_events(
+    connection: &mut AsyncPgConnection,
+    limit: i64,
+) -> Result<Vec<StoredChangeEvent>, StorageError> {
+    change_events::table
+        .order(change_events::change_events_id.asc())
+        .limit(limit)
+        .for_update()
+        .skip_locked()
+        .load::<StoredChangeEvent>(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+/// Removes relayed change events from the outbox.
+pub async fn delete_change_events(
+    connection: &mut AsyncPgConnection,
+    ids: &[i64],
+) -> Result<usize, StorageError> {
+    diesel::delete(change_events::table.filter(change_events::change_events_id.eq_any(ids)))
+        .execute(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::db::establish_connection;
+    use crate::provider::add_or_update_provider;
+    use crate::test_support::new_provider;
+    use crate::transaction::{begin_transaction, rollback_transaction};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_queue_lock_and_delete_change_events() {
+        let pool = establish_connection().await;
+        let mut conn = pool
+            .get_owned()
+            .await
+            .expect("Failed to get connection from pool");
+        let mut other = pool
+            .get_owned()
+            .await
+            .expect("Failed to get connection from pool");
+        let provider = add_or_update_provider(&mut conn, new_provider("Change events test"))
+            .await
+            .expect("Expected Ok result");
+        let new_event = |kind: &str| NewChangeEvent {
+            providers_id: provider.providers_id,
+            event: json!({ "type": kind }),
+        };
+
+        // Rows are queued at the end of the outbox, so the test runs in a transaction to lock
+        // every row queued before it
+        begin_transaction(&mut conn).await.unwrap();
+        let added = add_change_events(
+            &mut conn,
+            &[new_event("plan_created"), new_event("plan_removed")],
+        )
+        .await
+        .expect("Expected Ok result");
+        assert_eq!(added, 2);
+        let locked = lock_change_events(&mut conn, i64::MAX)
+            .await
+            .expect("Expected Ok result");
+        let queued: Vec<&StoredChangeEvent> = locked
+            .iter()
+            .filter(|event| event.providers_id == provider.providers_id)
+            .collect();
+        assert_eq!(queued.len(), 2);
+        assert_eq!(queued[0].event, json!({ "type": "plan_created" }));
+        assert!(queued[0].change_events_id < queued[1].change_events_id);
+
+        // Uncommitted and locked rows are invisible to other relays
+        let ids: Vec<i64> = queued.iter().map(|event| event.change_events_id).collect();
+        let others = lock_change_events(&mut other, i64::MAX).await.unwrap();
+        assert!(others
+            .iter()
+            .all(|event| !ids.contains(&event.change_events_id)));
+
+        assert_eq!(delete_change_events(&mut conn, &ids).await.unwrap(), 2);
+        let remaining = lock_change_events(&mut conn, i64::MAX).await.unwrap();
+        assert!(remaining
+            .iter()
+            .all(|event| event.providers_id != provider.providers_id));
+        rollback_transaction(&mut conn).await.unwrap();
+    }
+}
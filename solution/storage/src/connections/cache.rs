@@ -1,15 +1,26 @@
 use crate::error::{CacheError, CacheResult};
-use chrono::NaiveDateTime;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
 use dotenv::dotenv;
 use log::error;
+use redis::AsyncConnectionConfig;
 use redis::Client;
 use redis::Pipeline;
 use redis::{aio::MultiplexedConnection, AsyncCommands};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::env;
+use std::str::FromStr;
+use std::time::Duration;
 
+/// Time allowed to connect to Redis, handshake included.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(2);
+/// Time allowed for Redis to answer a command, after which the connection counts as lost.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone)]
 pub struct Cache {
+    client: Client,
     pub(super) conn: MultiplexedConnection,
 }
 
@@ -19,6 +30,15 @@ pub struct ProviderABaseEvent {
     pub title: String,
     pub sell_mode: String,
     pub plan: Plan,
+    /// Absent from events cached before organizers were stored.
+    #[serde(default)]
+    pub organizer: Option<EventOrganizer>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EventOrganizer {
+    pub id: String,
+    pub external_id: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -30,9 +50,11 @@ pub struct Plan {
     #[serde(rename = "plan_id")]
     pub plan_id: String,
     #[serde(rename = "sell_from")]
-    pub sell_from: String,
+    #[serde(default)]
+    pub sell_from: Option<String>,
     #[serde(rename = "sell_to")]
-    pub sell_to: String,
+    #[serde(default)]
+    pub sell_to: Option<String>,
     #[serde(rename = "sold_out")]
     pub sold_out: bool,
     #[serde(rename = "zone")]
@@ -44,36 +66,222 @@ pub struct Zone {
     #[serde(rename = "zone_id")]
     pub zone_id: String,
     #[serde(rename = "capacity")]
-    pub capacity: String,
+    #[serde(default, deserialize_with = "lenient")]
+    pub capacity: Option<i32>,
     #[serde(rename = "price")]
-    pub price: String,
+    #[serde(default, deserialize_with = "lenient")]
+    pub price: Option<BigDecimal>,
     #[serde(rename = "name")]
     pub name: String,
     #[serde(rename = "numbered")]
     pub numbered: bool,
+    /// ISO 4217 currency of `price`.
+    #[serde(rename = "currency")]
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+/// Reads a zone value cached as a string or a number. Values cached before zones were
+/// validated may not convert; they are read as missing rather than failing the search.
+fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+{
+    Ok(
+        match Option::<serde_json::Value>::deserialize(deserializer)? {
+            Some(serde_json::Value::String(value)) => value.trim().parse().ok(),
+            Some(serde_json::Value::Number(value)) => value.to_string().parse().ok(),
+            _ => None,
+        },
+    )
 }
 
 pub struct FilterQuery {
-    pub starts_at: NaiveDateTime,
-    pub ends_at: NaiveDateTime,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+/// State of a provider circuit breaker.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, strum_macros::Display, strum_macros::EnumString,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum CircuitState {
+    #[default]
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+/// Circuit breaker record shared by every worker instance.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CircuitBreakerRecord {
+    pub state: CircuitState,
+    pub failures: u32,
+    /// Unix timestamp (in seconds) of the last transition to `Open`.
+    pub opened_at: Option<i64>,
+}
+
+/// Token bucket of a provider rate limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitBucket {
+    /// Tokens refilled per minute.
+    pub per_minute: u32,
+    /// Maximum number of tokens, i.e. of requests sent back to back.
+    pub burst: u32,
+}
+
+/// Redis Stream the change events of plans are published to.
+pub const CHANGE_STREAM_KEY: &str = "plan_changes";
+
+/// Approximate number of entries kept in a change stream; older entries are trimmed.
+pub const CHANGE_STREAM_MAX_LEN: usize = 100_000;
+
+/// Plan a change event is about.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ChangedPlan {
+    pub provider_id: String,
+    pub plans_id: String,
+    pub event_base_id: String,
+    pub event_plan_id: String,
+}
+
+/// Value of a plan field before and after a change. Dates are RFC 3339 in UTC.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// Semantic change of a plan, published to a change stream. Serialized with its `type`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChangeEvent {
+    /// A plan appeared in the feed of its provider for the first time.
+    PlanCreated {
+        #[serde(flatten)]
+        plan: ChangedPlan,
+    },
+    /// Dates or availability of a known plan changed.
+    PlanUpdated {
+        #[serde(flatten)]
+        plan: ChangedPlan,
+        changes: Vec<FieldChange>,
+    },
+    /// Price or currency of a zone of a known plan changed, or the zone is new.
+    ZonePriceChanged {
+        #[serde(flatten)]
+        plan: ChangedPlan,
+        event_zone_id: String,
+        numbered: bool,
+        old_price: Option<BigDecimal>,
+        new_price: Option<BigDecimal>,
+        currency: String,
+    },
+    /// A known plan sold out or went back on sale.
+    SoldOutChanged {
+        #[serde(flatten)]
+        plan: ChangedPlan,
+        sold_out: bool,
+    },
+    /// A plan dropped out of the feed of its provider.
+    PlanRemoved {
+        #[serde(flatten)]
+        plan: ChangedPlan,
+    },
+}
+
+impl ChangeEvent {
+    /// Type of the event, as serialized.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ChangeEvent::PlanCreated { .. } => "plan_created",
+            ChangeEvent::PlanUpdated { .. } => "plan_updated",
+            ChangeEvent::ZonePriceChanged { .. } => "zone_price_changed",
+            ChangeEvent::SoldOutChanged { .. } => "sold_out_changed",
+            ChangeEvent::PlanRemoved { .. } => "plan_removed",
+        }
+    }
+}
+
+/// Change event delivered to a consumer group, acknowledged by its stream `id`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChangeStreamEntry {
+    pub id: String,
+    pub event: ChangeEvent,
 }
 
 const ROOT_KEY: &str = "plan";
+const CIRCUIT_BREAKER_KEY: &str = "circuit_breaker";
+const RATE_LIMIT_KEY: &str = "rate_limit";
+
+/// Takes a token from the bucket `KEYS[1]` (capacity `ARGV[1]`, `ARGV[2]` tokens per
+/// millisecond). Returns 0 when a token was taken, otherwise the milliseconds to wait.
+/// Uses the Redis clock so that every worker instance agrees on the refill.
+const RATE_LIMIT_ACQUIRE_SCRIPT: &str = r"
+redis.replicate_commands()
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local capacity = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at', 'blocked_until')
+local blocked_until = tonumber(bucket[3]) or 0
+if blocked_until > now then
+    return blocked_until - now
+end
+local tokens = tonumber(bucket[1]) or capacity
+local updated_at = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - updated_at) * rate)
+local wait = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+else
+    wait = math.ceil((1 - tokens) / rate)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', now)
+redis.call('PEXPIRE', KEYS[1], math.ceil(capacity / rate) + 60000)
+return wait
+";
+
+/// Empties the bucket `KEYS[1]` after the provider throttled us, and blocks it for
+/// `ARGV[3]` milliseconds (e.g. its `Retry-After`).
+const RATE_LIMIT_THROTTLE_SCRIPT: &str = r"
+redis.replicate_commands()
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local capacity = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local blocked_until = now + tonumber(ARGV[3])
+local previous = tonumber(redis.call('HGET', KEYS[1], 'blocked_until')) or 0
+redis.call('HSET', KEYS[1], 'tokens', '0', 'updated_at', now,
+    'blocked_until', math.max(previous, blocked_until))
+redis.call('PEXPIRE', KEYS[1], math.ceil(capacity / rate) + tonumber(ARGV[3]) + 60000)
+return 1
+";
 
 /// Async Cache implementation for redis
 impl Cache {
     pub async fn new() -> Result<Self, CacheError> {
         dotenv().ok();
 
-        let redis_url = env::var("REDIS_URI").expect("REDIS_URI must be set");
-        Self::with_url(redis_url.to_string()).await
+        let redis_url = env::var("REDIS_URI").map_err(|_| CacheError::CannotParseUrl)?;
+        Self::with_url(redis_url).await
     }
 
+    /// Connects to `redis_url`. Connecting and every command are bounded by a timeout, so a
+    /// Redis that accepts connections but does not answer fails like one that is down.
     pub async fn with_url(redis_url: String) -> CacheResult<Self> {
         let client = Client::open(redis_url.as_str())?;
-        let conn = client.get_multiplexed_async_connection().await?;
+        let config = AsyncConnectionConfig::new()
+            .set_connection_timeout(CONNECTION_TIMEOUT)
+            .set_response_timeout(RESPONSE_TIMEOUT);
+        let conn = client
+            .get_multiplexed_async_connection_with_config(&config)
+            .await?;
 
-        Ok(Self { conn })
+        Ok(Self { client, conn })
     }
 
     // Returns a Redis async pipeline and a cloned connection for use in async contexts.
@@ -85,13 +293,13 @@ impl Cache {
         &self,
         event_base_id: String,
         event_plan_id: String,
-        start_date: chrono::NaiveDateTime,
-        end_date: chrono::NaiveDateTime,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
     ) -> Result<(), CacheError> {
         let (mut pipe, mut conn) = self.pipeline().await;
         pipe.cmd("ZADD")
             .arg("start_date")
-            .arg(start_date.and_utc().timestamp())
+            .arg(start_date.timestamp())
             .arg(format!(
                 "{}:{}",
                 event_base_id.clone(),
@@ -100,14 +308,14 @@ impl Cache {
 
         pipe.cmd("ZADD")
             .arg("end_date")
-            .arg(end_date.and_utc().timestamp())
+            .arg(end_date.timestamp())
             .arg(format!(
                 "{}:{}",
                 event_base_id.clone(),
                 event_plan_id.clone()
             ));
 
-        pipe.query_async::<_, ()>(&mut conn)
+        pipe.query_async::<()>(&mut conn)
             .await
             .map_err(|e| CacheError::Error(format!("Failed to cache plan dates: {}", e)))
     }
@@ -115,19 +323,19 @@ impl Cache {
     /// Get from sorted Set plans that start after start_timestamp and plans that end before end_timestamp.
     pub async fn get_matched_plans(
         &self,
-        start_timestamp: NaiveDateTime,
-        end_timestamp: NaiveDateTime,
+        start_timestamp: DateTime<Utc>,
+        end_timestamp: DateTime<Utc>,
     ) -> Result<Vec<ProviderABaseEvent>, CacheError> {
         let (mut pipe, mut conn) = self.pipeline().await;
 
         pipe.cmd("ZRANGEBYSCORE")
             .arg("start_date")
-            .arg(start_timestamp.and_utc().timestamp())
+            .arg(start_timestamp.timestamp())
             .arg("+inf");
         pipe.cmd("ZRANGEBYSCORE")
             .arg("end_date")
             .arg("-inf")
-            .arg(end_timestamp.and_utc().timestamp());
+            .arg(end_timestamp.timestamp());
 
         let (start_event_ids, end_event_ids): (Vec<String>, Vec<String>) =
             pipe.query_async(&mut conn).await.map_err(|e| {
@@ -210,7 +418,8 @@ impl Cache {
                     .replace("\"@capacity\"", "\"capacity\"")
                     .replace("\"@price\"", "\"price\"")
                     .replace("\"@name\"", "\"name\"")
-                    .replace("\"@numbered\"", "\"numbered\"");
+                    .replace("\"@numbered\"", "\"numbered\"")
+                    .replace("\"@currency\"", "\"currency\"");
 
                 let plan: ProviderABaseEvent = serde_json::from_str(&plan_json).map_err(|e| {
                     error!("Error deserializing plan: {} | raw value: {}", e, plan_json);
@@ -242,6 +451,7 @@ impl Cache {
 
         let mut seen = HashSet::new();
         while let Some(key) = iter.next_item().await {
+            let key = key.map_err(|_| CacheError::CannotScan(pattern.to_string()))?;
             if seen.insert(key.clone()) {
                 keys.push(key);
             }
@@ -264,13 +474,433 @@ impl Cache {
             .await
             .map_err(|_| CacheError::CannotSet(key))
     }
+
+    /// Set a key/value pair in redis only if the key does not exist, with an expiry in seconds.
+    /// Returns whether the key was set.
+    pub async fn set_nx_ex(&self, key: String, value: String, ttl_secs: u64) -> CacheResult<bool> {
+        let mut conn = self.conn.clone();
+        let result: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(value)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_secs.max(1))
+            .query_async(&mut conn)
+            .await
+            .map_err(|_| CacheError::CannotSetNx(key))?;
+        Ok(result.is_some())
+    }
+
+    /// Get the circuit breaker record of a provider. Missing records are reported as closed.
+    pub async fn get_circuit_breaker(
+        &self,
+        provider_id: &str,
+    ) -> CacheResult<CircuitBreakerRecord> {
+        let mut conn = self.conn.clone();
+        let key = circuit_breaker_key(provider_id);
+        let fields: HashMap<String, String> = conn
+            .hgetall(&key)
+            .await
+            .map_err(|_| CacheError::NotFound(key.clone()))?;
+
+        Ok(CircuitBreakerRecord {
+            state: fields
+                .get("state")
+                .and_then(|s| CircuitState::from_str(s).ok())
+                .unwrap_or_default(),
+            failures: fields
+                .get("failures")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_default(),
+            opened_at: fields.get("opened_at").and_then(|s| s.parse().ok()),
+        })
+    }
+
+    /// Atomically increment the consecutive failures of a provider circuit breaker.
+    /// Returns the updated record.
+    pub async fn incr_circuit_breaker_failures(
+        &self,
+        provider_id: &str,
+    ) -> CacheResult<CircuitBreakerRecord> {
+        let (mut pipe, mut conn) = self.pipeline().await;
+        let key = circuit_breaker_key(provider_id);
+        pipe.atomic()
+            .hincr(&key, "failures", 1)
+            .hget(&key, "state")
+            .hget(&key, "opened_at");
+
+        let (failures, state, opened_at): (u32, Option<String>, Option<i64>) = pipe
+            .query_async(&mut conn)
+            .await
+            .map_err(|_| CacheError::CannotIncrement(key, "failures".to_string()))?;
+
+        Ok(CircuitBreakerRecord {
+            state: state
+                .and_then(|s| CircuitState::from_str(&s).ok())
+                .unwrap_or_default(),
+            failures,
+            opened_at,
+        })
+    }
+
+    /// Move a provider circuit breaker to the given state.
+    /// `opened_at` is only updated when provided.
+    pub async fn set_circuit_breaker_state(
+        &self,
+        provider_id: &str,
+        state: CircuitState,
+        opened_at: Option<i64>,
+    ) -> CacheResult<()> {
+        let mut conn = self.conn.clone();
+        let key = circuit_breaker_key(provider_id);
+        let mut fields = vec![("state", state.to_string())];
+        if let Some(opened_at) = opened_at {
+            fields.push(("opened_at", opened_at.to_string()));
+        }
+        conn.hset_multiple(&key, &fields)
+            .await
+            .map_err(|_| CacheError::CannotSet(key))
+    }
+
+    /// Try to become the single half-open probe of a provider circuit breaker for `ttl_secs`.
+    pub async fn try_acquire_circuit_breaker_probe(
+        &self,
+        provider_id: &str,
+        ttl_secs: u64,
+    ) -> CacheResult<bool> {
+        let key = format!("{}:probe", circuit_breaker_key(provider_id));
+        self.set_nx_ex(key, "1".to_string(), ttl_secs).await
+    }
+
+    /// Take a token from the rate limit bucket of a provider, shared by every worker instance.
+    /// Returns how long to wait before trying again, or `None` when a token was taken.
+    pub async fn acquire_rate_limit_token(
+        &self,
+        provider_id: &str,
+        bucket: RateLimitBucket,
+    ) -> CacheResult<Option<Duration>> {
+        let mut conn = self.conn.clone();
+        let wait_ms: u64 = redis::Script::new(RATE_LIMIT_ACQUIRE_SCRIPT)
+            .key(rate_limit_key(provider_id))
+            .arg(bucket.capacity())
+            .arg(bucket.tokens_per_ms())
+            .invoke_async(&mut conn)
+            .await?;
+        Ok((wait_ms > 0).then(|| Duration::from_millis(wait_ms)))
+    }
+
+    /// Empty the rate limit bucket of a provider that answered `429 Too Many Requests`,
+    /// keeping every worker instance away from it for at least `retry_after`.
+    pub async fn throttle_rate_limit(
+        &self,
+        provider_id: &str,
+        bucket: RateLimitBucket,
+        retry_after: Duration,
+    ) -> CacheResult<()> {
+        let mut conn = self.conn.clone();
+        let _: i64 = redis::Script::new(RATE_LIMIT_THROTTLE_SCRIPT)
+            .key(rate_limit_key(provider_id))
+            .arg(bucket.capacity())
+            .arg(bucket.tokens_per_ms())
+            .arg(retry_after.as_millis() as u64)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Append change events to `stream`, trimming it to about `CHANGE_STREAM_MAX_LEN` entries.
+    /// Each entry holds the `type` of the event and the `event` itself as JSON.
+    pub async fn publish_change_events(
+        &self,
+        stream: &str,
+        events: &[ChangeEvent],
+    ) -> CacheResult<()> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let (mut pipe, mut conn) = self.pipeline().await;
+        for event in events {
+            let payload = serde_json::to_string(event)
+                .map_err(|e| CacheError::Error(format!("Serialization error: {}", e)))?;
+            pipe.cmd("XADD")
+                .arg(stream)
+                .arg("MAXLEN")
+                .arg("~")
+                .arg(CHANGE_STREAM_MAX_LEN)
+                .arg("*")
+                .arg("type")
+                .arg(event.kind())
+                .arg("event")
+                .arg(payload)
+                .ignore();
+        }
+        pipe.query_async(&mut conn).await.map_err(|e| {
+            error!("Error publishing change events to {}: {}", stream, e);
+            CacheError::CannotXadd(stream.to_string())
+        })
+    }
+
+    /// Create a consumer group on `stream`, delivering the events published from now on.
+    /// Creating a group that already exists does nothing.
+    pub async fn create_change_consumer_group(&self, stream: &str, group: &str) -> CacheResult<()> {
+        let mut conn = self.conn.clone();
+        let result: redis::RedisResult<()> = redis::cmd("XGROUP")
+            .arg("CREATE")
+            .arg(stream)
+            .arg(group)
+            .arg("$")
+            .arg("MKSTREAM")
+            .query_async(&mut conn)
+            .await;
+        match result {
+            Err(e) if e.code() != Some("BUSYGROUP") => {
+                error!("Error creating group {} on {}: {}", group, stream, e);
+                Err(CacheError::CannotXgroup(
+                    stream.to_string(),
+                    group.to_string(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Open a reader of `stream` for `consumer` of `group`. The reader has a connection of
+    /// its own, so its blocking reads do not hold up the commands multiplexed on this one. Its
+    /// commands have no response timeout, as a blocking read may wait longer than one.
+    pub async fn change_stream_reader(
+        &self,
+        stream: &str,
+        group: &str,
+        consumer: &str,
+    ) -> CacheResult<ChangeStreamReader> {
+        let config = AsyncConnectionConfig::new().set_connection_timeout(CONNECTION_TIMEOUT);
+        let conn = self
+            .client
+            .get_multiplexed_async_connection_with_config(&config)
+            .await?;
+        Ok(ChangeStreamReader {
+            conn,
+            stream: stream.to_string(),
+            group: group.to_string(),
+            consumer: consumer.to_string(),
+        })
+    }
+
+    /// Reset a provider circuit breaker back to closed.
+    pub async fn reset_circuit_breaker(&self, provider_id: &str) -> CacheResult<()> {
+        let mut conn = self.conn.clone();
+        let key = circuit_breaker_key(provider_id);
+        let probe_key = format!("{}:probe", key);
+        conn.del(&[&key, &probe_key])
+            .await
+            .map_err(|_| CacheError::CannotDelete(key))
+    }
+}
+
+/// Consumer of a change stream within a consumer group. Events it reads stay pending until
+/// acknowledged with `ack`: a restarted consumer gets its own back with `read_pending`, and
+/// the events of a consumer that is gone are taken over with `claim`.
+pub struct ChangeStreamReader {
+    conn: MultiplexedConnection,
+    stream: String,
+    group: String,
+    consumer: String,
+}
+
+impl ChangeStreamReader {
+    /// Read up to `count` new change events, waiting at most `block` for one to be published
+    /// (forever when zero).
+    pub async fn read(&self, count: usize, block: Duration) -> CacheResult<Vec<ChangeStreamEntry>> {
+        let mut command = redis::cmd("XREADGROUP");
+        command.arg("BLOCK").arg(block.as_millis() as u64);
+        self.read_group(command, count, ">").await
+    }
+
+    /// Read up to `count` events delivered to this consumer and not acknowledged yet, oldest
+    /// first. Events trimmed from the stream since they were delivered are acknowledged.
+    pub async fn read_pending(&self, count: usize) -> CacheResult<Vec<ChangeStreamEntry>> {
+        self.read_group(redis::cmd("XREADGROUP"), count, "0").await
+    }
+
+    /// Take over up to `count` events delivered to other consumers of the group and left
+    /// unacknowledged for at least `min_idle`, e.g. by a consumer that stopped. They are
+    /// pending for this consumer from then on.
+    pub async fn claim(
+        &self,
+        min_idle: Duration,
+        count: usize,
+    ) -> CacheResult<Vec<ChangeStreamEntry>> {
+        let mut conn = self.conn.clone();
+        let mut cursor = "0-0".to_string();
+        loop {
+            let reply: redis::Value = redis::cmd("XAUTOCLAIM")
+                .arg(&self.stream)
+                .arg(&self.group)
+                .arg(&self.consumer)
+                .arg(min_idle.as_millis() as u64)
+                .arg(&cursor)
+                .arg("COUNT")
+                .arg(count.max(1))
+                .query_async(&mut conn)
+                .await
+                .map_err(|e| {
+                    error!("Error claiming change events of {}: {}", self.stream, e);
+                    CacheError::CannotXautoclaim(self.stream.clone())
+                })?;
+            let (next, entries, trimmed) = claimed_stream_entries(&reply)?;
+            self.ack(&trimmed).await?;
+            // The cursor comes back to 0-0 once the whole pending list was scanned
+            if !entries.is_empty() || next == "0-0" {
+                return Ok(entries);
+            }
+            cursor = next;
+        }
+    }
+
+    /// Acknowledge processed change events. Returns how many were pending.
+    pub async fn ack(&self, ids: &[String]) -> CacheResult<usize> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        let mut conn = self.conn.clone();
+        redis::cmd("XACK")
+            .arg(&self.stream)
+            .arg(&self.group)
+            .arg(ids)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!(
+                    "Error acknowledging events of stream {}: {}",
+                    self.stream, e
+                );
+                CacheError::CannotXack(self.stream.clone())
+            })
+    }
+
+    async fn read_group(
+        &self,
+        mut command: redis::Cmd,
+        count: usize,
+        id: &str,
+    ) -> CacheResult<Vec<ChangeStreamEntry>> {
+        let mut conn = self.conn.clone();
+        let reply: redis::Value = command
+            .arg("GROUP")
+            .arg(&self.group)
+            .arg(&self.consumer)
+            .arg("COUNT")
+            .arg(count.max(1))
+            .arg("STREAMS")
+            .arg(&self.stream)
+            .arg(id)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("Error reading change events from {}: {}", self.stream, e);
+                CacheError::CannotXreadgroup(self.stream.clone())
+            })?;
+        let (entries, trimmed) = change_stream_entries(&reply)?;
+        self.ack(&trimmed).await?;
+        Ok(entries)
+    }
+}
+
+fn malformed_stream_reply() -> CacheError {
+    CacheError::Error("Malformed change stream reply".to_string())
+}
+
+fn stream_list(value: &redis::Value) -> CacheResult<Vec<redis::Value>> {
+    match value {
+        redis::Value::Array(items) => Ok(items.clone()),
+        redis::Value::Nil => Ok(Vec::new()),
+        _ => Err(malformed_stream_reply()),
+    }
+}
+
+/// Parses an `XREADGROUP` reply: a list of streams, each with its list of `[id, [field,
+/// value, ...]]` entries. A timed out read replies nil. Returns the entries and the ids of
+/// the pending entries trimmed from the stream, which are replied without fields.
+fn change_stream_entries(
+    reply: &redis::Value,
+) -> CacheResult<(Vec<ChangeStreamEntry>, Vec<String>)> {
+    let mut entries = Vec::new();
+    let mut trimmed = Vec::new();
+    for stream in stream_list(reply)? {
+        let stream = stream_list(&stream)?;
+        let Some(stream_entries) = stream.get(1) else {
+            return Err(malformed_stream_reply());
+        };
+        parse_stream_entries(stream_entries, &mut entries, &mut trimmed)?;
+    }
+    Ok((entries, trimmed))
+}
+
+/// Parses an `XAUTOCLAIM` reply: the cursor of the next call, the claimed entries and the
+/// ids of the entries trimmed from the stream, which Redis drops from the pending list.
+fn claimed_stream_entries(
+    reply: &redis::Value,
+) -> CacheResult<(String, Vec<ChangeStreamEntry>, Vec<String>)> {
+    let reply = stream_list(reply)?;
+    let (Some(cursor), Some(claimed)) = (reply.first(), reply.get(1)) else {
+        return Err(malformed_stream_reply());
+    };
+    let cursor: String = redis::from_redis_value(cursor)?;
+    let mut entries = Vec::new();
+    let mut trimmed = Vec::new();
+    parse_stream_entries(claimed, &mut entries, &mut trimmed)?;
+    Ok((cursor, entries, trimmed))
+}
+
+fn parse_stream_entries(
+    stream_entries: &redis::Value,
+    entries: &mut Vec<ChangeStreamEntry>,
+    trimmed: &mut Vec<String>,
+) -> CacheResult<()> {
+    for entry in stream_list(stream_entries)? {
+        let entry = stream_list(&entry)?;
+        let (Some(id), Some(fields)) = (entry.first(), entry.get(1)) else {
+            return Err(malformed_stream_reply());
+        };
+        let id: String = redis::from_redis_value(id)?;
+        if matches!(fields, redis::Value::Nil) {
+            trimmed.push(id);
+            continue;
+        }
+        let fields: HashMap<String, String> = redis::from_redis_value(fields)?;
+        let payload = fields.get("event").ok_or_else(malformed_stream_reply)?;
+        let event = serde_json::from_str(payload).map_err(|e| {
+            error!("Error deserializing change event {}: {}", id, e);
+            CacheError::Error(format!("Deserialization error: {}", e))
+        })?;
+        entries.push(ChangeStreamEntry { id, event });
+    }
+    Ok(())
+}
+
+fn circuit_breaker_key(provider_id: &str) -> String {
+    format!("{}:{}", CIRCUIT_BREAKER_KEY, provider_id)
+}
+
+fn rate_limit_key(provider_id: &str) -> String {
+    format!("{}:{}", RATE_LIMIT_KEY, provider_id)
+}
+
+impl RateLimitBucket {
+    fn capacity(&self) -> u32 {
+        self.burst.max(1)
+    }
+
+    fn tokens_per_ms(&self) -> f64 {
+        f64::from(self.per_minute.max(1)) / 60_000.0
+    }
 }
 
 /// Queries the redis PING command to determine health
 pub async fn is_healthy(cache: &Cache) -> bool {
     let mut conn = cache.conn.clone();
     redis::cmd("PING")
-        .query_async::<MultiplexedConnection, String>(&mut conn)
+        .query_async::<String>(&mut conn)
         .await
         .is_ok()
 }
@@ -299,6 +929,27 @@ pub mod tests {
         Cache::new().await.unwrap()
     }
 
+    #[tokio::test]
+    async fn it_times_out_connecting_to_an_unresponsive_server() {
+        // Accepts connections but never answers the Redis handshake
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let redis_url = format!("redis://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        let connected = tokio::time::timeout(
+            CONNECTION_TIMEOUT + RESPONSE_TIMEOUT,
+            Cache::with_url(redis_url),
+        )
+        .await
+        .expect("connection attempt should time out on its own");
+        assert!(matches!(connected, Err(CacheError::NotConnected)));
+    }
+
     #[tokio::test]
     async fn it_gets_and_sets_a_value() {
         let cache = get_cache().await;
@@ -327,6 +978,153 @@ pub mod tests {
         let keys: Vec<String> = cache.get_keys_matching_pattern(&pattern).await.unwrap();
         assert_eq!(keys.len(), 20);
     }
+
+    fn changed_plan() -> ChangedPlan {
+        ChangedPlan {
+            provider_id: "provider_1".to_string(),
+            plans_id: "plan_row_1".to_string(),
+            event_base_id: "291".to_string(),
+            event_plan_id: "291".to_string(),
+        }
+    }
+
+    #[test]
+    fn it_serializes_change_events_with_their_type() {
+        let event = ChangeEvent::SoldOutChanged {
+            plan: changed_plan(),
+            sold_out: true,
+        };
+        let json: serde_json::Value = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], event.kind());
+        assert_eq!(json["event_plan_id"], "291");
+        assert_eq!(json["sold_out"], true);
+
+        let event = ChangeEvent::ZonePriceChanged {
+            plan: changed_plan(),
+            event_zone_id: "40".to_string(),
+            numbered: true,
+            old_price: Some("20.00".parse().unwrap()),
+            new_price: None,
+            currency: "EUR".to_string(),
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(serde_json::from_str::<ChangeEvent>(&json).unwrap(), event);
+    }
+
+    #[test]
+    fn it_parses_change_stream_replies() {
+        use redis::Value;
+        let data = |value: &str| Value::BulkString(value.as_bytes().to_vec());
+        let event = ChangeEvent::PlanRemoved {
+            plan: changed_plan(),
+        };
+        let entry = Value::Array(vec![
+            data("1700000000000-0"),
+            Value::Array(vec![
+                data("type"),
+                data(event.kind()),
+                data("event"),
+                data(&serde_json::to_string(&event).unwrap()),
+            ]),
+        ]);
+        let expected = vec![ChangeStreamEntry {
+            id: "1700000000000-0".to_string(),
+            event,
+        }];
+        // Pending entries trimmed from the stream are replied without fields
+        let trimmed = Value::Array(vec![data("1600000000000-0"), Value::Nil]);
+        let reply = Value::Array(vec![Value::Array(vec![
+            data(CHANGE_STREAM_KEY),
+            Value::Array(vec![trimmed, entry.clone()]),
+        ])]);
+        assert_eq!(
+            change_stream_entries(&reply).unwrap(),
+            (expected.clone(), vec!["1600000000000-0".to_string()])
+        );
+        assert_eq!(
+            change_stream_entries(&Value::Nil).unwrap(),
+            (Vec::new(), Vec::new())
+        );
+        assert!(change_stream_entries(&Value::Array(vec![data("plan_changes")])).is_err());
+
+        let reply = Value::Array(vec![
+            data("0-0"),
+            Value::Array(vec![entry]),
+            Value::Array(vec![data("1600000000000-0")]),
+        ]);
+        assert_eq!(
+            claimed_stream_entries(&reply).unwrap(),
+            ("0-0".to_string(), expected, Vec::new())
+        );
+        assert!(claimed_stream_entries(&Value::Nil).is_err());
+    }
+
+    #[tokio::test]
+    async fn it_publishes_change_events_to_consumer_groups() {
+        let cache = get_cache().await;
+        let stream = test_key();
+        cache
+            .create_change_consumer_group(&stream, "search")
+            .await
+            .unwrap();
+        // Creating the group again is a no-op
+        cache
+            .create_change_consumer_group(&stream, "search")
+            .await
+            .unwrap();
+        let event = ChangeEvent::PlanCreated {
+            plan: changed_plan(),
+        };
+        cache
+            .publish_change_events(&stream, std::slice::from_ref(&event))
+            .await
+            .unwrap();
+
+        let reader = cache
+            .change_stream_reader(&stream, "search", "worker_1")
+            .await
+            .unwrap();
+        let entries = reader.read(10, Duration::from_millis(100)).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].event, event);
+
+        // Unacknowledged events are read again after a restart, or claimed by another consumer
+        assert_eq!(reader.read_pending(10).await.unwrap(), entries);
+        let other = cache
+            .change_stream_reader(&stream, "search", "worker_2")
+            .await
+            .unwrap();
+        assert_eq!(
+            other.claim(Duration::from_millis(0), 10).await.unwrap(),
+            entries
+        );
+        assert!(reader.read_pending(10).await.unwrap().is_empty());
+
+        let ids = vec![entries[0].id.clone()];
+        assert_eq!(other.ack(&ids).await.unwrap(), 1);
+        assert!(other.read_pending(10).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_reads_cached_zone_values() {
+        let zone: Zone = serde_json::from_str(
+            r#"{"zone_id":"1","capacity":"243","price":"20.00","name":"Platea","numbered":true,"currency":"EUR"}"#,
+        )
+        .unwrap();
+        assert_eq!(zone.capacity, Some(243));
+        assert_eq!(zone.price, Some(BigDecimal::from(20)));
+        assert_eq!(zone.currency.as_deref(), Some("EUR"));
+
+        // Zones cached before their values were validated
+        let zone: Zone = serde_json::from_str(
+            r#"{"zone_id":"1","capacity":"","price":"n/a","name":"Platea","numbered":true}"#,
+        )
+        .unwrap();
+        assert_eq!(zone.capacity, None);
+        assert_eq!(zone.price, None);
+        assert_eq!(zone.currency, None);
+    }
+
     #[tokio::test]
     async fn it_caches_plan_dates() {
         init_env();
@@ -342,17 +1140,19 @@ pub mod tests {
                 plan_start_date: "2023-01-01T00:00:00Z".to_string(),
                 plan_end_date: "2023-12-31T23:59:59Z".to_string(),
                 plan_id: event_plan_id.clone(),
-                sell_from: "2023-01-01T00:00:00Z".to_string(),
-                sell_to: "2023-12-31T23:59:59Z".to_string(),
+                sell_from: Some("2023-01-01T00:00:00Z".to_string()),
+                sell_to: Some("2023-12-31T23:59:59Z".to_string()),
                 sold_out: false,
                 zones: vec![Zone {
                     zone_id: "zone_1".to_string(),
-                    capacity: "100".to_string(),
-                    price: "50.0".to_string(),
+                    capacity: Some(100),
+                    price: Some(BigDecimal::from(50)),
                     name: "Zone 1".to_string(),
                     numbered: false,
+                    currency: Some("EUR".to_string()),
                 }],
             },
+            organizer: None,
         };
         // Use a fixed start and end date for testing
         let start_date =
@@ -363,8 +1163,8 @@ pub mod tests {
             .cache_plan_dates(
                 event_base_id.clone(),
                 event_plan_id.clone(),
-                start_date.naive_utc(),
-                end_date.naive_utc(),
+                start_date,
+                end_date,
             )
             .await
             .unwrap();
@@ -377,10 +1177,7 @@ pub mod tests {
             .await
             .unwrap();
 
-        let plans = cache
-            .get_matched_plans(start_date.naive_utc(), end_date.naive_utc())
-            .await
-            .unwrap();
+        let plans = cache.get_matched_plans(start_date, end_date).await.unwrap();
 
         assert!(!plans.is_empty());
     }
@@ -392,6 +1189,73 @@ pub mod tests {
         assert!(healthy);
     }
 
+    #[tokio::test]
+    async fn it_tracks_circuit_breaker_state() {
+        let cache = get_cache().await;
+        let provider_id = test_key();
+
+        let record = cache.get_circuit_breaker(&provider_id).await.unwrap();
+        assert_eq!(record, CircuitBreakerRecord::default());
+
+        cache
+            .incr_circuit_breaker_failures(&provider_id)
+            .await
+            .unwrap();
+        let record = cache
+            .incr_circuit_breaker_failures(&provider_id)
+            .await
+            .unwrap();
+        assert_eq!(record.failures, 2);
+        assert_eq!(record.state, CircuitState::Closed);
+
+        cache
+            .set_circuit_breaker_state(&provider_id, CircuitState::Open, Some(1_700_000_000))
+            .await
+            .unwrap();
+        let record = cache.get_circuit_breaker(&provider_id).await.unwrap();
+        assert_eq!(record.state, CircuitState::Open);
+        assert_eq!(record.opened_at, Some(1_700_000_000));
+
+        cache.reset_circuit_breaker(&provider_id).await.unwrap();
+        let record = cache.get_circuit_breaker(&provider_id).await.unwrap();
+        assert_eq!(record, CircuitBreakerRecord::default());
+    }
+
+    #[tokio::test]
+    async fn it_shares_rate_limit_tokens() {
+        let cache = get_cache().await;
+        let provider_id = test_key();
+        let bucket = RateLimitBucket {
+            per_minute: 60,
+            burst: 2,
+        };
+
+        for _ in 0..2 {
+            let wait = cache
+                .acquire_rate_limit_token(&provider_id, bucket)
+                .await
+                .unwrap();
+            assert_eq!(wait, None);
+        }
+        let wait = cache
+            .acquire_rate_limit_token(&provider_id, bucket)
+            .await
+            .unwrap()
+            .expect("Expected an empty bucket");
+        assert!(wait <= Duration::from_secs(1));
+
+        cache
+            .throttle_rate_limit(&provider_id, bucket, Duration::from_secs(30))
+            .await
+            .unwrap();
+        let wait = cache
+            .acquire_rate_limit_token(&provider_id, bucket)
+            .await
+            .unwrap()
+            .expect("Expected a throttled bucket");
+        assert!(wait > Duration::from_secs(29));
+    }
+
     #[tokio::test]
     async fn it_handles_empty_plan() {
         let cache = get_cache().await;
@@ -1,10 +1,46 @@
-use diesel::pg::PgConnection;
-use diesel::r2d2::{ConnectionManager, Pool, PoolError, PooledConnection};
+use diesel_async::pooled_connection::bb8::{Pool, PooledConnection};
+use diesel_async::pooled_connection::{AsyncDieselConnectionManager, PoolError};
+use diesel_async::AsyncPgConnection;
 use dotenv::dotenv;
 use std::env;
+use std::time::Duration;
 
-pub type PgPool = Pool<ConnectionManager<PgConnection>>;
-pub type PgPooledConnection = PooledConnection<ConnectionManager<PgConnection>>;
+pub type PgPool = Pool<AsyncPgConnection>;
+pub type PgPooledConnection = PooledConnection<'static, AsyncPgConnection>;
+
+/// Sizing of the connection pool.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolSettings {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    /// How long a checkout waits for a free connection.
+    pub checkout_timeout: Duration,
+    /// Idle connections above `min_connections` are closed after this; `None` keeps them.
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        PoolSettings {
+            max_connections: 10,
+            min_connections: 1,
+            checkout_timeout: Duration::from_secs(30),
+            idle_timeout: Some(Duration::from_secs(600)),
+        }
+    }
+}
+
+/// Builds a long-lived bb8 Postgres DB Pool. Connections are opened lazily,
+/// so the pool can be built while Postgres is down.
+pub fn build_pool(database_url: &str, settings: &PoolSettings) -> PgPool {
+    let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new(database_url);
+    Pool::builder()
+        .max_size(settings.max_connections.max(1))
+        .min_idle(Some(settings.min_connections.min(settings.max_connections)))
+        .connection_timeout(settings.checkout_timeout)
+        .idle_timeout(settings.idle_timeout)
+        .build_unchecked(manager)
+}
 
 //Connects to Postgres and call init pool
 pub async fn establish_connection() -> PgPool {
@@ -16,10 +52,10 @@ pub async fn establish_connection() -> PgPool {
         .expect("Failed to create pool")
 }
 
-//Creates a default R2D2 Postgres DB Pool
+//Creates a default bb8 Postgres DB Pool, failing unless a first connection opens
 async fn init_pool(database_url: &str) -> Result<PgPool, PoolError> {
-    let manager = ConnectionManager::<PgConnection>::new(database_url);
-    Pool::builder().build(manager)
+    let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new(database_url);
+    Pool::builder().min_idle(Some(1)).build(manager).await
 }
 
 #[cfg(test)]
@@ -40,7 +76,7 @@ mod tests {
     async fn test_establish_connection() {
         init_env();
         let pool = establish_connection().await;
-        let conn = pool.get();
+        let conn = pool.get_owned().await;
         assert!(
             conn.is_ok(),
             "Failed to get connection from pool: {:?}",
@@ -56,6 +92,29 @@ mod tests {
         assert!(pool.is_ok(), "Failed to create pool: {:?}", pool.err());
     }
 
+    #[tokio::test]
+    async fn test_build_pool_from_settings() {
+        init_env();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let settings = PoolSettings {
+            max_connections: 2,
+            ..PoolSettings::default()
+        };
+        let pool = build_pool(&database_url, &settings);
+        assert_eq!(pool.state().connections, 0);
+        let first = pool
+            .get_owned()
+            .await
+            .expect("Failed to get connection from pool");
+        let _second = pool
+            .get_owned()
+            .await
+            .expect("Failed to get connection from pool");
+        assert_eq!(pool.state().connections, 2);
+        drop(first);
+        assert!(pool.get_owned().await.is_ok());
+    }
+
     #[tokio::test]
     async fn test_init_pool_invalid_url() {
         let database_url = "invalid_url";
@@ -0,0 +1,83 @@
+use crate::error::StorageError;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fmt;
+
+/// Length of the AES-GCM nonce prepended to every sealed secret.
+const NONCE_LEN: usize = 12;
+
+/// AES-256-GCM key encrypting secrets at rest, e.g. provider credentials.
+#[derive(Clone)]
+pub struct SecretKey(Aes256Gcm);
+
+impl SecretKey {
+    /// Reads a key encoded as the base64 of 32 random bytes, e.g. `openssl rand -base64 32`.
+    pub fn from_base64(encoded: &str) -> Result<Self, StorageError> {
+        let bytes = STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| StorageError::Crypto(format!("Invalid key encoding: {}", e)))?;
+        if bytes.len() != 32 {
+            return Err(StorageError::Crypto(format!(
+                "Key must be 32 bytes long, got {}",
+                bytes.len()
+            )));
+        }
+        Ok(SecretKey(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(
+            &bytes,
+        ))))
+    }
+
+    /// Encrypts `plaintext` with a random nonce. Returns the nonce followed by the ciphertext.
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>, StorageError> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .0
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| StorageError::Crypto("Failed to encrypt secret".to_string()))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypts a secret sealed by `seal`. Fails with a different key or a tampered secret.
+    pub fn open(&self, sealed: &[u8]) -> Result<Vec<u8>, StorageError> {
+        if sealed.len() < NONCE_LEN {
+            return Err(StorageError::Crypto(
+                "Sealed secret is too short".to_string(),
+            ));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.0
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| StorageError::Crypto("Failed to decrypt secret".to_string()))
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretKey(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=";
+
+    #[test]
+    fn test_sealed_secrets_only_open_with_their_key() {
+        let key = SecretKey::from_base64(KEY).unwrap();
+        let sealed = key.seal(b"s3cr3t").unwrap();
+        assert_ne!(&sealed[NONCE_LEN..], b"s3cr3t");
+        assert_eq!(key.open(&sealed).unwrap(), b"s3cr3t");
+        // Every seal uses a fresh nonce
+        assert_ne!(key.seal(b"s3cr3t").unwrap(), sealed);
+
+        let other = SecretKey::from_base64(&STANDARD.encode([7u8; 32])).unwrap();
+        assert!(other.open(&sealed).is_err());
+        assert!(SecretKey::from_base64("c2hvcnQ=").is_err());
+    }
+}
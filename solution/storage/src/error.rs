@@ -11,6 +11,7 @@ pub enum StorageError {
     NotFound(String),
     InvalidInput(String),
     PoolError(String),
+    Crypto(String),
     Other(String),
 }
 
@@ -21,6 +22,7 @@ impl fmt::Display for StorageError {
             StorageError::NotFound(msg) => write!(f, "Not found: {}", msg),
             StorageError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
             StorageError::PoolError(msg) => write!(f, "Pool error: {}", msg),
+            StorageError::Crypto(msg) => write!(f, "Crypto error: {}", msg),
             StorageError::Other(msg) => write!(f, "Other error: {}", msg),
         }
     }
@@ -98,6 +100,21 @@ pub enum CacheError {
     #[error("Cannot watch key {}", _0)]
     CannotWatch(String),
 
+    #[error("Cannot xack stream {}", _0)]
+    CannotXack(String),
+
+    #[error("Cannot xadd stream {}", _0)]
+    CannotXadd(String),
+
+    #[error("Cannot xautoclaim stream {}", _0)]
+    CannotXautoclaim(String),
+
+    #[error("Cannot create group {} on stream {}", _1, _0)]
+    CannotXgroup(String, String),
+
+    #[error("Cannot xreadgroup stream {}", _0)]
+    CannotXreadgroup(String),
+
     #[error("Cannot zadd key {}", _0)]
     CannotZadd(String),
 
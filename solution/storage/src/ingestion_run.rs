@@ -0,0 +1,148 @@
+use crate::error::StorageError;
+use crate::models::ingestion_runs::*;
+use crate::schema::ingestion_runs;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+pub async fn add_ingestion_run(
+    connection: &mut AsyncPgConnection,
+    new_run: NewIngestionRun,
+) -> Result<IngestionRun, StorageError> {
+    diesel::insert_into(ingestion_runs::table)
+        .values(&new_run)
+        .get_result(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+/// Latest runs, optionally of a single provider, newest first.
+pub async fn list_ingestion_runs(
+    connection: &mut AsyncPgConnection,
+    provider_id: Option<Uuid>,
+    limit: i64,
+) -> Result<Vec<IngestionRun>, StorageError> {
+    let mut query = ingestion_runs::table.into_boxed();
+    if let Some(provider_id) = provider_id {
+        query = query.filter(ingestion_runs::providers_id.eq(provider_id));
+    }
+    query
+        .order(ingestion_runs::started_at.desc())
+        .limit(limit)
+        .load::<IngestionRun>(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+/// Latest run of a provider with one of `statuses`.
+pub async fn get_last_ingestion_run(
+    connection: &mut AsyncPgConnection,
+    provider_id: Uuid,
+    statuses: &[IngestionRunStatus],
+) -> Result<Option<IngestionRun>, StorageError> {
+    let statuses: Vec<String> = statuses.iter().map(ToString::to_string).collect();
+    ingestion_runs::table
+        .filter(ingestion_runs::providers_id.eq(provider_id))
+        .filter(ingestion_runs::status.eq_any(statuses))
+        .order(ingestion_runs::started_at.desc())
+        .first::<IngestionRun>(connection)
+        .await
+        .optional()
+        .map_err(StorageError::from)
+}
+
+/// Latest run of a provider that did not fail, i.e. when the provider last succeeded.
+pub async fn get_last_successful_ingestion_run(
+    connection: &mut AsyncPgConnection,
+    provider_id: Uuid,
+) -> Result<Option<IngestionRun>, StorageError> {
+    get_last_ingestion_run(connection, provider_id, &IngestionRunStatus::SUCCESSFUL).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::db::establish_connection;
+    use crate::provider::add_or_update_provider;
+    use crate::test_support::new_provider;
+
+    fn new_run(provider_id: Uuid, status: IngestionRunStatus, minutes_ago: i64) -> NewIngestionRun {
+        let started_at = chrono::Utc::now().naive_utc() - chrono::Duration::minutes(minutes_ago);
+        NewIngestionRun {
+            ingestion_runs_id: Uuid::new_v4(),
+            providers_id: provider_id,
+            status: status.to_string(),
+            started_at,
+            finished_at: started_at + chrono::Duration::seconds(1),
+            http_status: Some(200),
+            bytes: Some(1_024),
+            parse_duration_ms: Some(12),
+            base_plans_inserted: 1,
+            base_plans_updated: 0,
+            base_plans_unchanged: 0,
+            plans_inserted: 2,
+            plans_updated: 0,
+            plans_unchanged: 0,
+            zones_inserted: 3,
+            zones_updated: 0,
+            zones_unchanged: 0,
+            quarantined: 0,
+            error_kind: None,
+            error_message: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_list_and_last_successful_ingestion_run() {
+        let connection = establish_connection().await;
+        let mut pg_pool = connection
+            .get_owned()
+            .await
+            .expect("Failed to get connection from pool");
+
+        let provider = add_or_update_provider(&mut pg_pool, new_provider("Ingestion run test"))
+            .await
+            .expect("Expected Ok result");
+        let provider_id = provider.providers_id;
+        assert_eq!(
+            get_last_successful_ingestion_run(&mut pg_pool, provider_id)
+                .await
+                .unwrap(),
+            None
+        );
+
+        let succeeded = add_ingestion_run(
+            &mut pg_pool,
+            new_run(provider_id, IngestionRunStatus::Succeeded, 10),
+        )
+        .await
+        .expect("Expected Ok result");
+        let failed = add_ingestion_run(
+            &mut pg_pool,
+            NewIngestionRun {
+                error_kind: Some("timeout".to_string()),
+                error_message: Some("request timed out".to_string()),
+                ..new_run(provider_id, IngestionRunStatus::Failed, 5)
+            },
+        )
+        .await
+        .expect("Expected Ok result");
+
+        let runs = list_ingestion_runs(&mut pg_pool, Some(provider_id), 10)
+            .await
+            .unwrap();
+        assert_eq!(runs, vec![failed.clone(), succeeded.clone()]);
+        assert_eq!(
+            get_last_successful_ingestion_run(&mut pg_pool, provider_id)
+                .await
+                .unwrap(),
+            Some(succeeded)
+        );
+        assert_eq!(
+            get_last_ingestion_run(&mut pg_pool, provider_id, &[IngestionRunStatus::Failed])
+                .await
+                .unwrap(),
+            Some(failed)
+        );
+    }
+}
@@ -9,11 +9,27 @@ extern crate dotenv;
 extern crate rand;
 extern crate uuid;
 
+pub mod availability;
 pub mod base_plan;
+pub mod bulk;
+pub mod change_event;
 pub mod connections;
+pub mod crypto;
 pub mod error;
+pub mod ingestion_run;
 pub mod models;
+pub mod organizer;
+pub mod payload_archive;
 pub mod plan;
 pub mod provider;
+pub mod provider_credential;
+pub mod provider_fetch_state;
+pub mod quarantined_record;
 pub mod schema;
+#[cfg(test)]
+mod test_support;
+pub mod transaction;
 pub mod zone;
+
+pub use bigdecimal::{self, BigDecimal};
+pub use diesel_async::AsyncPgConnection;
@@ -0,0 +1,9 @@
+/// Whether a plan or zone is part of the latest snapshot of its provider feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum_macros::Display, strum_macros::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum Availability {
+    /// Present in the latest snapshot: currently offered.
+    Available,
+    /// Dropped out of the feed: historically offered only.
+    Unavailable,
+}
@@ -18,6 +18,7 @@ pub struct BasePlan {
     pub sell_mode: String,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
+    pub organizers_id: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Insertable)]
@@ -28,6 +29,7 @@ pub struct NewBasePlan {
     pub event_base_id: String,
     pub title: String,
     pub sell_mode: String,
+    pub organizers_id: Option<Uuid>,
 }
 
 impl From<NewBasePlan> for BasePlan {
@@ -42,6 +44,7 @@ impl From<NewBasePlan> for BasePlan {
             providers_id: base_plan.providers_id,
             created_at: now,
             updated_at: now,
+            organizers_id: base_plan.organizers_id,
         }
     }
 }
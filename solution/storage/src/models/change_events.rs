@@ -0,0 +1,25 @@
+use crate::models::providers::Provider;
+use crate::schema::change_events;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Change event waiting in the outbox to be relayed to the change stream.
+#[derive(
+    Debug, Serialize, Deserialize, Associations, Identifiable, Queryable, PartialEq, Clone,
+)]
+#[diesel(belongs_to(Provider, foreign_key = providers_id))]
+#[diesel(table_name = change_events)]
+#[diesel(primary_key(change_events_id))]
+pub struct StoredChangeEvent {
+    pub change_events_id: i64,
+    pub providers_id: Uuid,
+    pub event: serde_json::Value,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Insertable)]
+#[diesel(table_name = change_events)]
+pub struct NewChangeEvent {
+    pub providers_id: Uuid,
+    pub event: serde_json::Value,
+}
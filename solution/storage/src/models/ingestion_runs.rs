@@ -0,0 +1,82 @@
+use crate::models::providers::Provider;
+use crate::schema::ingestion_runs;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Outcome of a worker run of a provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum_macros::Display, strum_macros::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum IngestionRunStatus {
+    /// A changed payload was parsed and persisted.
+    Succeeded,
+    /// The provider answered `304 Not Modified`.
+    NotModified,
+    /// The payload hash matched the last persisted payload.
+    Unchanged,
+    /// The run stopped on an error, see `error_kind`.
+    Failed,
+}
+
+impl IngestionRunStatus {
+    /// Statuses of runs that reached the provider and left the database up to date.
+    pub const SUCCESSFUL: [IngestionRunStatus; 3] = [
+        IngestionRunStatus::Succeeded,
+        IngestionRunStatus::NotModified,
+        IngestionRunStatus::Unchanged,
+    ];
+}
+
+#[derive(
+    Debug, Serialize, Deserialize, Associations, Identifiable, Queryable, PartialEq, Clone,
+)]
+#[diesel(belongs_to(Provider, foreign_key = providers_id))]
+#[diesel(table_name = ingestion_runs)]
+#[diesel(primary_key(ingestion_runs_id))]
+pub struct IngestionRun {
+    pub ingestion_runs_id: Uuid,
+    pub providers_id: Uuid,
+    pub status: String,
+    pub started_at: chrono::NaiveDateTime,
+    pub finished_at: chrono::NaiveDateTime,
+    pub http_status: Option<i32>,
+    pub bytes: Option<i64>,
+    pub parse_duration_ms: Option<i64>,
+    pub base_plans_inserted: i32,
+    pub base_plans_updated: i32,
+    pub base_plans_unchanged: i32,
+    pub plans_inserted: i32,
+    pub plans_updated: i32,
+    pub plans_unchanged: i32,
+    pub zones_inserted: i32,
+    pub zones_updated: i32,
+    pub zones_unchanged: i32,
+    pub quarantined: i32,
+    pub error_kind: Option<String>,
+    pub error_message: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Insertable)]
+#[diesel(table_name = ingestion_runs)]
+pub struct NewIngestionRun {
+    pub ingestion_runs_id: Uuid,
+    pub providers_id: Uuid,
+    pub status: String,
+    pub started_at: chrono::NaiveDateTime,
+    pub finished_at: chrono::NaiveDateTime,
+    pub http_status: Option<i32>,
+    pub bytes: Option<i64>,
+    pub parse_duration_ms: Option<i64>,
+    pub base_plans_inserted: i32,
+    pub base_plans_updated: i32,
+    pub base_plans_unchanged: i32,
+    pub plans_inserted: i32,
+    pub plans_updated: i32,
+    pub plans_unchanged: i32,
+    pub zones_inserted: i32,
+    pub zones_updated: i32,
+    pub zones_unchanged: i32,
+    pub quarantined: i32,
+    pub error_kind: Option<String>,
+    pub error_message: Option<String>,
+}
@@ -1,4 +1,12 @@
+pub mod availability;
 pub mod base_plans;
+pub mod change_events;
+pub mod ingestion_runs;
+pub mod organizers;
+pub mod payload_archives;
 pub mod plans;
+pub mod provider_credentials;
+pub mod provider_fetch_states;
 pub mod providers;
+pub mod quarantined_records;
 pub mod zones;
@@ -0,0 +1,27 @@
+use crate::models::providers::Provider;
+use crate::schema::organizers;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Organizer of events, identified by the `organizer_company_id` of its provider.
+#[derive(
+    Debug, Serialize, Deserialize, Associations, Identifiable, Queryable, PartialEq, Clone,
+)]
+#[diesel(belongs_to(Provider, foreign_key = providers_id))]
+#[diesel(table_name = organizers)]
+#[diesel(primary_key(organizers_id))]
+pub struct Organizer {
+    pub organizers_id: Uuid,
+    pub providers_id: Uuid,
+    pub event_organizer_id: String,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Insertable)]
+#[diesel(table_name = organizers)]
+pub struct NewOrganizer {
+    pub organizers_id: Uuid,
+    pub providers_id: Uuid,
+    pub event_organizer_id: String,
+}
@@ -0,0 +1,57 @@
+use crate::models::providers::Provider;
+use crate::schema::payload_archives;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Associations,
+    Identifiable,
+    Queryable,
+    Selectable,
+    PartialEq,
+    Clone,
+)]
+#[diesel(belongs_to(Provider, foreign_key = providers_id))]
+#[diesel(table_name = payload_archives)]
+#[diesel(primary_key(payload_archives_id))]
+pub struct PayloadArchive {
+    pub payload_archives_id: Uuid,
+    pub providers_id: Uuid,
+    pub fetched_at: chrono::NaiveDateTime,
+    pub status: i32,
+    pub content_hash: String,
+    pub size: i64,
+    #[serde(skip)]
+    pub body: Option<Vec<u8>>,
+    pub path: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+/// Metadata of an archived payload, without its body.
+#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, PartialEq, Clone)]
+#[diesel(table_name = payload_archives)]
+pub struct PayloadArchiveEntry {
+    pub payload_archives_id: Uuid,
+    pub providers_id: Uuid,
+    pub fetched_at: chrono::NaiveDateTime,
+    pub status: i32,
+    pub content_hash: String,
+    pub size: i64,
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Insertable)]
+#[diesel(table_name = payload_archives)]
+pub struct NewPayloadArchive {
+    pub payload_archives_id: Uuid,
+    pub providers_id: Uuid,
+    pub status: i32,
+    pub content_hash: String,
+    pub size: i64,
+    #[serde(skip)]
+    pub body: Option<Vec<u8>>,
+    pub path: Option<String>,
+}
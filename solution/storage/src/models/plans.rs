@@ -1,3 +1,4 @@
+use crate::models::availability::Availability;
 use crate::models::base_plans::BasePlan;
 use crate::schema::plans;
 use chrono::prelude::*;
@@ -14,13 +15,16 @@ pub struct Plan {
     pub plans_id: Uuid,
     pub base_plans_id: Uuid,
     pub event_plan_id: String,
-    pub plan_start_date: chrono::NaiveDateTime,
-    pub plan_end_date: chrono::NaiveDateTime,
-    pub sell_from: chrono::NaiveDateTime,
-    pub sell_to: chrono::NaiveDateTime,
+    pub plan_start_date: DateTime<Utc>,
+    pub plan_end_date: DateTime<Utc>,
+    pub sell_from: Option<DateTime<Utc>>,
+    pub sell_to: Option<DateTime<Utc>>,
     pub sold_out: bool,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
+    pub first_seen_at: chrono::NaiveDateTime,
+    pub last_seen_at: chrono::NaiveDateTime,
+    pub availability: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Insertable)]
@@ -29,10 +33,10 @@ pub struct NewPlan {
     pub plans_id: uuid::Uuid,
     pub base_plans_id: uuid::Uuid,
     pub event_plan_id: String,
-    pub plan_start_date: chrono::NaiveDateTime,
-    pub plan_end_date: chrono::NaiveDateTime,
-    pub sell_from: chrono::NaiveDateTime,
-    pub sell_to: chrono::NaiveDateTime,
+    pub plan_start_date: DateTime<Utc>,
+    pub plan_end_date: DateTime<Utc>,
+    pub sell_from: Option<DateTime<Utc>>,
+    pub sell_to: Option<DateTime<Utc>>,
     pub sold_out: bool,
 }
 
@@ -51,6 +55,9 @@ impl From<NewPlan> for Plan {
             sold_out: plan.sold_out,
             created_at: now,
             updated_at: now,
+            first_seen_at: now,
+            last_seen_at: now,
+            availability: Availability::Available.to_string(),
         }
     }
 }
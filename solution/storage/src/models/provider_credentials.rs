@@ -0,0 +1,28 @@
+use crate::models::providers::Provider;
+use crate::schema::provider_credentials;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Stored authentication of a provider feed. `secret` is sealed with a `crypto::SecretKey`.
+#[derive(
+    Debug, Serialize, Deserialize, Associations, Identifiable, Queryable, PartialEq, Clone,
+)]
+#[diesel(belongs_to(Provider, foreign_key = providers_id))]
+#[diesel(table_name = provider_credentials)]
+#[diesel(primary_key(providers_id))]
+pub struct ProviderCredential {
+    pub providers_id: Uuid,
+    pub auth_type: String,
+    #[serde(skip_serializing)]
+    pub secret: Vec<u8>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, PartialEq, Clone, Insertable)]
+#[diesel(table_name = provider_credentials)]
+pub struct NewProviderCredential {
+    pub providers_id: Uuid,
+    pub auth_type: String,
+    pub secret: Vec<u8>,
+}
@@ -0,0 +1,44 @@
+use crate::models::providers::Provider;
+use crate::schema::provider_fetch_states;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Outcome of the last fetch of a provider feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum_macros::Display, strum_macros::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum FetchOutcome {
+    /// The payload changed and was parsed and persisted.
+    Changed,
+    /// The provider answered `304 Not Modified`.
+    NotModified,
+    /// The payload hash matched the last persisted payload.
+    Unchanged,
+}
+
+#[derive(
+    Debug, Serialize, Deserialize, Associations, Identifiable, Queryable, PartialEq, Clone,
+)]
+#[diesel(belongs_to(Provider, foreign_key = providers_id))]
+#[diesel(table_name = provider_fetch_states)]
+#[diesel(primary_key(providers_id))]
+pub struct ProviderFetchState {
+    pub providers_id: Uuid,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub content_hash: Option<String>,
+    pub last_outcome: String,
+    pub checked_at: chrono::NaiveDateTime,
+    pub changed_at: chrono::NaiveDateTime,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Insertable)]
+#[diesel(table_name = provider_fetch_states)]
+pub struct NewProviderFetchState {
+    pub providers_id: Uuid,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub content_hash: Option<String>,
+    pub last_outcome: String,
+}
@@ -33,6 +33,52 @@ pub struct Provider {
     pub created_at: chrono::NaiveDateTime,
     #[serde(rename = "updated_at")]
     pub updated_at: chrono::NaiveDateTime,
+    #[serde(rename = "adapter")]
+    pub adapter: String,
+    #[serde(rename = "adapter_config")]
+    #[serde(default)]
+    pub adapter_config: Option<serde_json::Value>,
+    #[serde(rename = "schedule_interval_sec")]
+    #[serde(default)]
+    pub schedule_interval_sec: Option<i32>,
+    #[serde(rename = "schedule_cron")]
+    #[serde(default)]
+    pub schedule_cron: Option<String>,
+    #[serde(rename = "active_from")]
+    #[serde(default)]
+    pub active_from: Option<chrono::NaiveTime>,
+    #[serde(rename = "active_until")]
+    #[serde(default)]
+    pub active_until: Option<chrono::NaiveTime>,
+    #[serde(rename = "rate_limit_per_minute")]
+    #[serde(default)]
+    pub rate_limit_per_minute: Option<i32>,
+    #[serde(rename = "rate_limit_burst")]
+    #[serde(default)]
+    pub rate_limit_burst: Option<i32>,
+    #[serde(rename = "pagination")]
+    #[serde(default)]
+    pub pagination: Option<serde_json::Value>,
+    /// IANA timezone of the local times sent by the provider feed.
+    #[serde(rename = "timezone")]
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    /// Formats of the dates sent by the provider feed, as a JSON array.
+    #[serde(rename = "date_formats")]
+    #[serde(default)]
+    pub date_formats: Option<serde_json::Value>,
+    /// ISO 4217 currency of the prices sent by the provider feed.
+    #[serde(rename = "currency")]
+    #[serde(default = "default_currency")]
+    pub currency: String,
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+fn default_currency() -> String {
+    "EUR".to_string()
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Insertable)]
@@ -43,6 +89,18 @@ pub struct NewProvider {
     pub description: String,
     pub url: String,
     pub is_active: bool,
+    pub adapter: String,
+    pub adapter_config: Option<serde_json::Value>,
+    pub schedule_interval_sec: Option<i32>,
+    pub schedule_cron: Option<String>,
+    pub active_from: Option<chrono::NaiveTime>,
+    pub active_until: Option<chrono::NaiveTime>,
+    pub rate_limit_per_minute: Option<i32>,
+    pub rate_limit_burst: Option<i32>,
+    pub pagination: Option<serde_json::Value>,
+    pub timezone: String,
+    pub date_formats: Option<serde_json::Value>,
+    pub currency: String,
 }
 
 impl From<NewProvider> for Provider {
@@ -57,6 +115,18 @@ impl From<NewProvider> for Provider {
             is_active: new_provider.is_active,
             created_at: now,
             updated_at: now,
+            adapter: new_provider.adapter,
+            adapter_config: new_provider.adapter_config,
+            schedule_interval_sec: new_provider.schedule_interval_sec,
+            schedule_cron: new_provider.schedule_cron,
+            active_from: new_provider.active_from,
+            active_until: new_provider.active_until,
+            rate_limit_per_minute: new_provider.rate_limit_per_minute,
+            rate_limit_burst: new_provider.rate_limit_burst,
+            pagination: new_provider.pagination,
+            timezone: new_provider.timezone,
+            date_formats: new_provider.date_formats,
+            currency: new_provider.currency,
         }
     }
 }
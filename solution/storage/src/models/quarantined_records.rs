@@ -0,0 +1,53 @@
+use crate::models::providers::Provider;
+use crate::schema::quarantined_records;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Lifecycle of a quarantined record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum_macros::Display, strum_macros::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum QuarantineStatus {
+    /// Waiting to be fixed and re-processed.
+    Pending,
+    /// Re-processed and persisted successfully.
+    Resolved,
+}
+
+#[derive(
+    Debug, Serialize, Deserialize, Associations, Identifiable, Queryable, PartialEq, Clone,
+)]
+#[diesel(belongs_to(Provider, foreign_key = providers_id))]
+#[diesel(table_name = quarantined_records)]
+#[diesel(primary_key(quarantined_records_id))]
+pub struct QuarantinedRecord {
+    pub quarantined_records_id: Uuid,
+    pub providers_id: Uuid,
+    pub payload_archives_id: Option<Uuid>,
+    pub record_hash: String,
+    pub raw: String,
+    pub reason: serde_json::Value,
+    pub status: String,
+    pub occurrences: i32,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Insertable)]
+#[diesel(table_name = quarantined_records)]
+pub struct NewQuarantinedRecord {
+    pub quarantined_records_id: Uuid,
+    pub providers_id: Uuid,
+    pub payload_archives_id: Option<Uuid>,
+    pub record_hash: String,
+    pub raw: String,
+    pub reason: serde_json::Value,
+    pub status: String,
+}
+
+/// Changes applied when a quarantined record is re-processed. `None` fields are left as is.
+#[derive(Debug, Clone, AsChangeset)]
+#[diesel(table_name = quarantined_records)]
+pub struct QuarantinedRecordChanges {
+    pub status: String,
+    pub reason: Option<serde_json::Value>,
+}
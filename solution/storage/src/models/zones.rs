@@ -1,5 +1,7 @@
+use crate::models::availability::Availability;
 use crate::models::plans::Plan;
 use crate::schema::zones;
+use bigdecimal::BigDecimal;
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -24,11 +26,16 @@ pub struct Zone {
     pub plans_id: Uuid,
     pub event_zone_id: String,
     pub name: String,
-    pub capacity: String,
-    pub price: String,
+    pub capacity: Option<i32>,
+    pub price: Option<BigDecimal>,
     pub numbered: bool,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
+    pub first_seen_at: chrono::NaiveDateTime,
+    pub last_seen_at: chrono::NaiveDateTime,
+    pub availability: String,
+    /// ISO 4217 currency of `price`.
+    pub currency: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Insertable)]
@@ -38,9 +45,10 @@ pub struct NewZone {
     pub plans_id: Uuid,
     pub event_zone_id: String,
     pub name: String,
-    pub capacity: String,
-    pub price: String,
+    pub capacity: Option<i32>,
+    pub price: Option<BigDecimal>,
     pub numbered: bool,
+    pub currency: String,
 }
 
 impl From<NewZone> for Zone {
@@ -57,6 +65,10 @@ impl From<NewZone> for Zone {
             numbered: zone.numbered,
             created_at: now,
             updated_at: now,
+            first_seen_at: now,
+            last_seen_at: now,
+            availability: Availability::Available.to_string(),
+            currency: zone.currency,
         }
     }
 }
@@ -0,0 +1,81 @@
+use crate::bulk::{last_by_key, rows_per_chunk};
+use crate::error::StorageError;
+use crate::models::organizers::*;
+use crate::schema::organizers;
+use diesel::insert_into;
+use diesel::pg::upsert::excluded;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+/// Bind parameters of one `NewOrganizer` row.
+pub(crate) const ORGANIZER_PARAMS: usize = 3;
+
+/// Registers the organizers of a provider, one statement per chunk of rows. Returns the
+/// stored rows, which keep their previous id when the organizer is already known.
+pub async fn add_or_update_organizers(
+    connection: &mut AsyncPgConnection,
+    new_organizers: &[NewOrganizer],
+) -> Result<Vec<Organizer>, StorageError> {
+    let rows = last_by_key(new_organizers, |organizer| {
+        (organizer.providers_id, organizer.event_organizer_id.clone())
+    });
+    let mut stored = Vec::with_capacity(rows.len());
+    for chunk in rows.chunks(rows_per_chunk(ORGANIZER_PARAMS)) {
+        let chunk_rows: Vec<Organizer> = insert_into(organizers::table)
+            .values(chunk.to_vec())
+            .on_conflict((organizers::providers_id, organizers::event_organizer_id))
+            .do_update()
+            // No-op update, so that known organizers are returned too
+            .set(organizers::event_organizer_id.eq(excluded(organizers::event_organizer_id)))
+            .get_results(connection)
+            .await?;
+        stored.extend(chunk_rows);
+    }
+    Ok(stored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::db::establish_connection;
+    use crate::provider::add_or_update_provider;
+    use crate::test_support::new_provider;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_add_or_update_organizers_keeps_known_ids() {
+        let pool = establish_connection().await;
+        let mut conn = pool
+            .get_owned()
+            .await
+            .expect("Failed to get connection from pool");
+        let provider = add_or_update_provider(&mut conn, new_provider("Organizers test"))
+            .await
+            .expect("Expected Ok result");
+        let new_organizer = |event_organizer_id: &str| NewOrganizer {
+            organizers_id: Uuid::new_v4(),
+            providers_id: provider.providers_id,
+            event_organizer_id: event_organizer_id.to_string(),
+        };
+
+        let first = add_or_update_organizers(&mut conn, &[new_organizer("1")])
+            .await
+            .expect("Expected Ok result");
+        let second = add_or_update_organizers(&mut conn, &[new_organizer("1"), new_organizer("2")])
+            .await
+            .expect("Expected Ok result");
+
+        assert_eq!(second.len(), 2);
+        let known = second
+            .iter()
+            .find(|organizer| organizer.event_organizer_id == "1")
+            .unwrap();
+        assert_eq!(known.organizers_id, first[0].organizers_id);
+        let stored: Organizer = organizers::table
+            .find(known.organizers_id)
+            .first(&mut conn)
+            .await
+            .expect("Expected Ok result");
+        assert_eq!(&stored, known);
+    }
+}
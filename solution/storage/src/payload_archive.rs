@@ -0,0 +1,106 @@
+use crate::error::StorageError;
+use crate::models::payload_archives::*;
+use crate::schema::payload_archives;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+pub async fn add_payload_archive(
+    connection: &mut AsyncPgConnection,
+    new_archive: NewPayloadArchive,
+) -> Result<PayloadArchiveEntry, StorageError> {
+    diesel::insert_into(payload_archives::table)
+        .values(&new_archive)
+        .returning(PayloadArchiveEntry::as_returning())
+        .get_result(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+pub async fn get_payload_archive(
+    connection: &mut AsyncPgConnection,
+    archive_id: Uuid,
+) -> Result<Option<PayloadArchive>, StorageError> {
+    payload_archives::table
+        .find(archive_id)
+        .select(PayloadArchive::as_select())
+        .first(connection)
+        .await
+        .optional()
+        .map_err(StorageError::from)
+}
+
+/// Latest archived payloads of a provider, newest first.
+pub async fn list_payload_archives(
+    connection: &mut AsyncPgConnection,
+    provider_id: Uuid,
+    limit: i64,
+) -> Result<Vec<PayloadArchiveEntry>, StorageError> {
+    payload_archives::table
+        .filter(payload_archives::providers_id.eq(provider_id))
+        .order(payload_archives::fetched_at.desc())
+        .limit(limit)
+        .select(PayloadArchiveEntry::as_select())
+        .load(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+/// Deletes the payloads archived before `cutoff`. Returns the deleted entries so the
+/// files of directory archives can be removed as well.
+pub async fn delete_payload_archives_before(
+    connection: &mut AsyncPgConnection,
+    cutoff: chrono::NaiveDateTime,
+) -> Result<Vec<PayloadArchiveEntry>, StorageError> {
+    diesel::delete(payload_archives::table.filter(payload_archives::fetched_at.lt(cutoff)))
+        .returning(PayloadArchiveEntry::as_returning())
+        .get_results(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::db::establish_connection;
+    use crate::provider::add_or_update_provider;
+    use crate::test_support::new_provider;
+
+    #[tokio::test]
+    async fn test_add_get_and_list_payload_archives() {
+        let connection = establish_connection().await;
+        let mut pg_pool = connection
+            .get_owned()
+            .await
+            .expect("Failed to get connection from pool");
+
+        let provider = add_or_update_provider(&mut pg_pool, new_provider("Payload archive test"))
+            .await
+            .expect("Expected Ok result");
+        let entry = add_payload_archive(
+            &mut pg_pool,
+            NewPayloadArchive {
+                payload_archives_id: Uuid::new_v4(),
+                providers_id: provider.providers_id,
+                status: 200,
+                content_hash: "abc".to_string(),
+                size: 11,
+                body: Some(b"<planList/>".to_vec()),
+                path: None,
+            },
+        )
+        .await
+        .expect("Expected Ok result");
+
+        let archive = get_payload_archive(&mut pg_pool, entry.payload_archives_id)
+            .await
+            .unwrap()
+            .expect("Expected an archive");
+        assert_eq!(archive.body.as_deref(), Some(&b"<planList/>"[..]));
+
+        let entries = list_payload_archives(&mut pg_pool, provider.providers_id, 10)
+            .await
+            .unwrap();
+        assert_eq!(entries, vec![entry]);
+    }
+}
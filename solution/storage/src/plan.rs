@@ -1,18 +1,48 @@
-use crate::connections::db::PgPooledConnection;
+use crate::bulk::{last_by_key, rows_per_chunk, updated_at_if_changed};
 use crate::error::StorageError;
+use crate::models::availability::Availability;
 use crate::models::plans::{NewPlan, Plan};
 use crate::schema::plans::{self, plan_end_date, plan_start_date};
 use diesel::insert_into;
+use diesel::pg::upsert::excluded;
 use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 
-pub fn get_plans(connection: &mut PgPooledConnection) -> Result<Vec<Plan>, StorageError> {
+/// Bind parameters of one `NewPlan` row.
+pub(crate) const PLAN_PARAMS: usize = 8;
+
+/// Columns whose change bumps `updated_at` on upsert. A plan coming back to the feed counts
+/// as updated through `availability`.
+pub(crate) const PLAN_CHANGE_COLUMNS: &[&str] = &[
+    "plan_start_date",
+    "plan_end_date",
+    "sell_from",
+    "sell_to",
+    "sold_out",
+    "availability",
+];
+
+pub async fn get_plans(connection: &mut AsyncPgConnection) -> Result<Vec<Plan>, StorageError> {
     plans::table
         .load::<Plan>(connection)
+        .await
         .map_err(StorageError::from)
 }
 
-pub fn add_or_update_plan(
-    connection: &mut PgPooledConnection,
+/// Loads the stored plans of the given base plans.
+pub async fn get_plans_of_base_plans(
+    connection: &mut AsyncPgConnection,
+    base_plans_ids: &[uuid::Uuid],
+) -> Result<Vec<Plan>, StorageError> {
+    plans::table
+        .filter(plans::base_plans_id.eq_any(base_plans_ids))
+        .load::<Plan>(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+pub async fn add_or_update_plan(
+    connection: &mut AsyncPgConnection,
     new_plan: NewPlan,
 ) -> Result<Plan, StorageError> {
     insert_into(plans::table)
@@ -25,8 +55,43 @@ pub fn add_or_update_plan(
             plans::sell_from.eq(&new_plan.sell_from),
             plans::sell_to.eq(&new_plan.sell_to),
             plans::sold_out.eq(&new_plan.sold_out),
-            plans::updated_at.eq(diesel::dsl::now), // Use current time for updated_at
-        )) // Handle conflict if the event already exists
+            plans::updated_at.eq(diesel::dsl::now),
+            plans::last_seen_at.eq(diesel::dsl::now),
+            plans::availability.eq(Availability::Available.to_string()),
+        ))
         .get_result(connection)
+        .await
         .map_err(StorageError::from)
 }
+
+/// Multi-row variant of `add_or_update_plan`, one statement per chunk of rows.
+/// When a plan appears more than once, its last occurrence wins.
+pub async fn add_or_update_plans(
+    connection: &mut AsyncPgConnection,
+    new_plans: &[NewPlan],
+) -> Result<Vec<Plan>, StorageError> {
+    let rows = last_by_key(new_plans, |plan| {
+        (plan.base_plans_id, plan.event_plan_id.clone())
+    });
+    let mut inserted = Vec::with_capacity(rows.len());
+    for chunk in rows.chunks(rows_per_chunk(PLAN_PARAMS)) {
+        let chunk_rows: Vec<Plan> = insert_into(plans::table)
+            .values(chunk.to_vec())
+            .on_conflict((plans::base_plans_id, plans::event_plan_id))
+            .do_update()
+            .set((
+                plan_start_date.eq(excluded(plan_start_date)),
+                plan_end_date.eq(excluded(plan_end_date)),
+                plans::sell_from.eq(excluded(plans::sell_from)),
+                plans::sell_to.eq(excluded(plans::sell_to)),
+                plans::sold_out.eq(excluded(plans::sold_out)),
+                plans::updated_at.eq(updated_at_if_changed("plans", PLAN_CHANGE_COLUMNS)),
+                plans::last_seen_at.eq(diesel::dsl::now),
+                plans::availability.eq(Availability::Available.to_string()),
+            ))
+            .get_results(connection)
+            .await?;
+        inserted.extend(chunk_rows);
+    }
+    Ok(inserted)
+}
@@ -1,21 +1,33 @@
-use crate::connections::db::PgPooledConnection;
 use crate::error::StorageError;
 use crate::models::providers::*;
 use crate::schema::providers;
 use diesel::prelude::*;
-use diesel::ExpressionMethods;
-use diesel::RunQueryDsl;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 
-pub fn get_active_providers(
-    connection: &mut PgPooledConnection,
+pub async fn get_active_providers(
+    connection: &mut AsyncPgConnection,
 ) -> Result<Vec<Provider>, StorageError> {
     providers::table
         .filter(providers::is_active.eq(true))
         .load::<Provider>(connection)
+        .await
         .map_err(StorageError::from)
 }
-pub fn add_or_update_provider(
-    connection: &mut PgPooledConnection,
+
+pub async fn get_provider(
+    connection: &mut AsyncPgConnection,
+    provider_id: uuid::Uuid,
+) -> Result<Option<Provider>, StorageError> {
+    providers::table
+        .find(provider_id)
+        .first::<Provider>(connection)
+        .await
+        .optional()
+        .map_err(StorageError::from)
+}
+
+pub async fn add_or_update_provider(
+    connection: &mut AsyncPgConnection,
     new_provider: NewProvider,
 ) -> Result<Provider, StorageError> {
     diesel::insert_into(providers::table)
@@ -25,9 +37,22 @@ pub fn add_or_update_provider(
         .set((
             providers::name.eq(&new_provider.name),
             providers::is_active.eq(&new_provider.is_active),
+            providers::adapter.eq(&new_provider.adapter),
+            providers::adapter_config.eq(&new_provider.adapter_config),
+            providers::schedule_interval_sec.eq(&new_provider.schedule_interval_sec),
+            providers::schedule_cron.eq(&new_provider.schedule_cron),
+            providers::active_from.eq(&new_provider.active_from),
+            providers::active_until.eq(&new_provider.active_until),
+            providers::rate_limit_per_minute.eq(&new_provider.rate_limit_per_minute),
+            providers::rate_limit_burst.eq(&new_provider.rate_limit_burst),
+            providers::pagination.eq(&new_provider.pagination),
+            providers::timezone.eq(&new_provider.timezone),
+            providers::date_formats.eq(&new_provider.date_formats),
+            providers::currency.eq(&new_provider.currency),
             providers::updated_at.eq(diesel::dsl::now), // Use current time for updated_at
         ))
         .get_result(connection)
+        .await
         .map_err(StorageError::from)
 }
 
@@ -35,15 +60,17 @@ pub fn add_or_update_provider(
 mod tests {
     use super::*;
     use crate::connections::db::establish_connection;
+    use crate::test_support::new_provider;
 
     #[tokio::test]
     async fn test_get_providers_returns_ok() {
         let connection = establish_connection().await;
         let mut pg_pool = connection
-            .get()
+            .get_owned()
+            .await
             .expect("Failed to get connection from pool");
 
-        let result = get_active_providers(&mut pg_pool);
+        let result = get_active_providers(&mut pg_pool).await;
         assert!(result.is_ok(), "Expected Ok, got {:?}", result);
     }
 
@@ -51,11 +78,46 @@ mod tests {
     async fn test_get_providers_returns_vec() {
         let connection = establish_connection().await;
         let mut pg_pool = connection
-            .get()
+            .get_owned()
+            .await
             .expect("Failed to get connection from pool");
 
-        let result = get_active_providers(&mut pg_pool).expect("Expected Ok result");
+        let result = get_active_providers(&mut pg_pool)
+            .await
+            .expect("Expected Ok result");
         // This just checks that the result is a Vec (could be empty)
         assert!(result.is_empty() || !result.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_add_or_update_provider_updates_every_setting() {
+        let connection = establish_connection().await;
+        let mut pg_pool = connection
+            .get_owned()
+            .await
+            .expect("Failed to get connection from pool");
+
+        let provider = new_provider("Provider update test");
+        add_or_update_provider(&mut pg_pool, provider.clone())
+            .await
+            .expect("Expected Ok result");
+        let updated = add_or_update_provider(
+            &mut pg_pool,
+            NewProvider {
+                timezone: "Europe/Madrid".to_string(),
+                date_formats: Some(serde_json::json!(["%d/%m/%Y %H:%M"])),
+                currency: "USD".to_string(),
+                ..provider
+            },
+        )
+        .await
+        .expect("Expected Ok result");
+
+        assert_eq!(updated.timezone, "Europe/Madrid");
+        assert_eq!(
+            updated.date_formats,
+            Some(serde_json::json!(["%d/%m/%Y %H:%M"]))
+        );
+        assert_eq!(updated.currency, "USD");
+    }
 }
@@ -0,0 +1,135 @@
+use crate::crypto::SecretKey;
+use crate::error::StorageError;
+use crate::models::provider_credentials::*;
+use crate::schema::provider_credentials;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+/// Stores the auth configuration of a provider, encrypted with `key`. It replaces any
+/// previous configuration.
+pub async fn save_provider_credential(
+    connection: &mut AsyncPgConnection,
+    key: &SecretKey,
+    provider_id: Uuid,
+    auth_type: &str,
+    config: &serde_json::Value,
+) -> Result<ProviderCredential, StorageError> {
+    let plaintext =
+        serde_json::to_vec(config).map_err(|e| StorageError::InvalidInput(e.to_string()))?;
+    let new_credential = NewProviderCredential {
+        providers_id: provider_id,
+        auth_type: auth_type.to_string(),
+        secret: key.seal(&plaintext)?,
+    };
+    diesel::insert_into(provider_credentials::table)
+        .values(&new_credential)
+        .on_conflict(provider_credentials::providers_id)
+        .do_update()
+        .set((
+            provider_credentials::auth_type.eq(&new_credential.auth_type),
+            provider_credentials::secret.eq(&new_credential.secret),
+            provider_credentials::updated_at.eq(diesel::dsl::now),
+        ))
+        .get_result(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+/// Decrypted auth configuration of a provider, if it has one.
+pub async fn get_provider_credential_config(
+    connection: &mut AsyncPgConnection,
+    key: &SecretKey,
+    provider_id: Uuid,
+) -> Result<Option<serde_json::Value>, StorageError> {
+    let Some(credential) = provider_credentials::table
+        .find(provider_id)
+        .first::<ProviderCredential>(connection)
+        .await
+        .optional()?
+    else {
+        return Ok(None);
+    };
+    let plaintext = key.open(&credential.secret)?;
+    serde_json::from_slice(&plaintext)
+        .map(Some)
+        .map_err(|e| StorageError::Crypto(format!("Invalid credential: {}", e)))
+}
+
+pub async fn has_provider_credential(
+    connection: &mut AsyncPgConnection,
+    provider_id: Uuid,
+) -> Result<bool, StorageError> {
+    diesel::select(diesel::dsl::exists(
+        provider_credentials::table.find(provider_id),
+    ))
+    .get_result(connection)
+    .await
+    .map_err(StorageError::from)
+}
+
+pub async fn delete_provider_credential(
+    connection: &mut AsyncPgConnection,
+    provider_id: Uuid,
+) -> Result<usize, StorageError> {
+    diesel::delete(provider_credentials::table.find(provider_id))
+        .execute(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::db::establish_connection;
+    use crate::provider::add_or_update_provider;
+    use crate::test_support::new_provider;
+
+    #[tokio::test]
+    async fn test_credentials_are_encrypted_at_rest() {
+        let connection = establish_connection().await;
+        let mut pg_pool = connection
+            .get_owned()
+            .await
+            .expect("Failed to get connection from pool");
+
+        let provider = add_or_update_provider(&mut pg_pool, new_provider("Credential test"))
+            .await
+            .expect("Expected Ok result");
+        let provider_id = provider.providers_id;
+        let key = SecretKey::from_base64("MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=").unwrap();
+        let config = serde_json::json!({ "type": "bearer", "token": "s3cr3t" });
+
+        let saved = save_provider_credential(&mut pg_pool, &key, provider_id, "bearer", &config)
+            .await
+            .expect("Expected Ok result");
+        assert_eq!(saved.auth_type, "bearer");
+        assert!(!String::from_utf8_lossy(&saved.secret).contains("s3cr3t"));
+        assert_eq!(
+            get_provider_credential_config(&mut pg_pool, &key, provider_id)
+                .await
+                .unwrap(),
+            Some(config)
+        );
+
+        let other = SecretKey::from_base64("ZmVkY2JhOTg3NjU0MzIxMGZlZGNiYTk4NzY1NDMyMTA=").unwrap();
+        assert!(
+            get_provider_credential_config(&mut pg_pool, &other, provider_id)
+                .await
+                .is_err()
+        );
+
+        assert_eq!(
+            delete_provider_credential(&mut pg_pool, provider_id)
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            get_provider_credential_config(&mut pg_pool, &key, provider_id)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+}
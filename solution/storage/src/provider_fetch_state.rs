@@ -0,0 +1,111 @@
+use crate::error::StorageError;
+use crate::models::provider_fetch_states::*;
+use crate::schema::provider_fetch_states;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+pub async fn get_provider_fetch_state(
+    connection: &mut AsyncPgConnection,
+    provider_id: Uuid,
+) -> Result<Option<ProviderFetchState>, StorageError> {
+    provider_fetch_states::table
+        .find(provider_id)
+        .first::<ProviderFetchState>(connection)
+        .await
+        .optional()
+        .map_err(StorageError::from)
+}
+
+/// Stores the validators and content hash of a payload that was just persisted.
+pub async fn save_provider_fetch_state(
+    connection: &mut AsyncPgConnection,
+    new_state: NewProviderFetchState,
+) -> Result<ProviderFetchState, StorageError> {
+    diesel::insert_into(provider_fetch_states::table)
+        .values(&new_state)
+        .on_conflict(provider_fetch_states::providers_id)
+        .do_update()
+        .set((
+            provider_fetch_states::etag.eq(&new_state.etag),
+            provider_fetch_states::last_modified.eq(&new_state.last_modified),
+            provider_fetch_states::content_hash.eq(&new_state.content_hash),
+            provider_fetch_states::last_outcome.eq(&new_state.last_outcome),
+            provider_fetch_states::checked_at.eq(diesel::dsl::now),
+            provider_fetch_states::changed_at.eq(diesel::dsl::now),
+            provider_fetch_states::updated_at.eq(diesel::dsl::now),
+        ))
+        .get_result(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+/// Records that a fetch was skipped without touching the stored validators.
+pub async fn record_provider_fetch_outcome(
+    connection: &mut AsyncPgConnection,
+    provider_id: Uuid,
+    outcome: FetchOutcome,
+) -> Result<usize, StorageError> {
+    diesel::update(provider_fetch_states::table.find(provider_id))
+        .set((
+            provider_fetch_states::last_outcome.eq(outcome.to_string()),
+            provider_fetch_states::checked_at.eq(diesel::dsl::now),
+            provider_fetch_states::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::db::establish_connection;
+    use crate::provider::add_or_update_provider;
+    use crate::test_support::new_provider;
+
+    #[tokio::test]
+    async fn test_save_and_record_provider_fetch_state() {
+        let connection = establish_connection().await;
+        let mut pg_pool = connection
+            .get_owned()
+            .await
+            .expect("Failed to get connection from pool");
+
+        let provider = add_or_update_provider(&mut pg_pool, new_provider("Fetch state test"))
+            .await
+            .expect("Expected Ok result");
+        let provider_id = provider.providers_id;
+        assert_eq!(
+            get_provider_fetch_state(&mut pg_pool, provider_id)
+                .await
+                .unwrap(),
+            None
+        );
+
+        let saved = save_provider_fetch_state(
+            &mut pg_pool,
+            NewProviderFetchState {
+                providers_id: provider_id,
+                etag: Some("\"v1\"".to_string()),
+                last_modified: None,
+                content_hash: Some("abc".to_string()),
+                last_outcome: FetchOutcome::Changed.to_string(),
+            },
+        )
+        .await
+        .expect("Expected Ok result");
+        assert_eq!(saved.etag.as_deref(), Some("\"v1\""));
+
+        record_provider_fetch_outcome(&mut pg_pool, provider_id, FetchOutcome::NotModified)
+            .await
+            .expect("Expected Ok result");
+        let state = get_provider_fetch_state(&mut pg_pool, provider_id)
+            .await
+            .unwrap()
+            .expect("Expected a fetch state");
+        assert_eq!(state.last_outcome, "not_modified");
+        assert_eq!(state.content_hash.as_deref(), Some("abc"));
+        assert_eq!(state.changed_at, saved.changed_at);
+    }
+}
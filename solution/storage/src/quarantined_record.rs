@@ -0,0 +1,247 @@
+use crate::error::StorageError;
+use crate::models::quarantined_records::*;
+use crate::schema::quarantined_records;
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use uuid::Uuid;
+
+/// Quarantines records. A record already quarantined for the provider gets its reason
+/// refreshed, its occurrences bumped and goes back to pending.
+pub async fn add_or_update_quarantined_records(
+    connection: &mut AsyncPgConnection,
+    new_records: &[NewQuarantinedRecord],
+) -> Result<usize, StorageError> {
+    diesel::insert_into(quarantined_records::table)
+        .values(new_records)
+        .on_conflict((
+            quarantined_records::providers_id,
+            quarantined_records::record_hash,
+        ))
+        .do_update()
+        .set((
+            quarantined_records::payload_archives_id
+                .eq(excluded(quarantined_records::payload_archives_id)),
+            quarantined_records::reason.eq(excluded(quarantined_records::reason)),
+            quarantined_records::status.eq(excluded(quarantined_records::status)),
+            quarantined_records::occurrences.eq(quarantined_records::occurrences + 1),
+            quarantined_records::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+pub async fn get_quarantined_record(
+    connection: &mut AsyncPgConnection,
+    record_id: Uuid,
+) -> Result<Option<QuarantinedRecord>, StorageError> {
+    quarantined_records::table
+        .find(record_id)
+        .first::<QuarantinedRecord>(connection)
+        .await
+        .optional()
+        .map_err(StorageError::from)
+}
+
+/// Quarantined records with the given status, optionally for a single provider, newest first.
+pub async fn list_quarantined_records(
+    connection: &mut AsyncPgConnection,
+    provider_id: Option<Uuid>,
+    status: QuarantineStatus,
+    limit: i64,
+) -> Result<Vec<QuarantinedRecord>, StorageError> {
+    let mut query = quarantined_records::table
+        .filter(quarantined_records::status.eq(status.to_string()))
+        .into_boxed();
+    if let Some(provider_id) = provider_id {
+        query = query.filter(quarantined_records::providers_id.eq(provider_id));
+    }
+    query
+        .order(quarantined_records::updated_at.desc())
+        .limit(limit)
+        .load::<QuarantinedRecord>(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+/// Pending quarantined records of a provider in id order, after the record `after` if given.
+/// Paging with the id of the last record returned goes through every pending record once,
+/// whether or not the records already returned are still pending.
+pub async fn list_pending_quarantined_records_after(
+    connection: &mut AsyncPgConnection,
+    provider_id: Uuid,
+    after: Option<Uuid>,
+    limit: i64,
+) -> Result<Vec<QuarantinedRecord>, StorageError> {
+    let mut query = quarantined_records::table
+        .filter(quarantined_records::status.eq(QuarantineStatus::Pending.to_string()))
+        .filter(quarantined_records::providers_id.eq(provider_id))
+        .into_boxed();
+    if let Some(after) = after {
+        query = query.filter(quarantined_records::quarantined_records_id.gt(after));
+    }
+    query
+        .order(quarantined_records::quarantined_records_id.asc())
+        .limit(limit)
+        .load::<QuarantinedRecord>(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+/// Records the outcome of re-processing a quarantined record. The reason is kept unless a
+/// new one is given.
+pub async fn update_quarantined_record(
+    connection: &mut AsyncPgConnection,
+    record_id: Uuid,
+    status: QuarantineStatus,
+    reason: Option<serde_json::Value>,
+) -> Result<QuarantinedRecord, StorageError> {
+    let changes = QuarantinedRecordChanges {
+        status: status.to_string(),
+        reason,
+    };
+    diesel::update(quarantined_records::table.find(record_id))
+        .set((
+            changes,
+            quarantined_records::updated_at.eq(diesel::dsl::now),
+        ))
+        .get_result(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::db::establish_connection;
+    use crate::provider::add_or_update_provider;
+    use crate::test_support::new_provider;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_quarantine_list_and_resolve_records() {
+        let connection = establish_connection().await;
+        let mut pg_pool = connection
+            .get_owned()
+            .await
+            .expect("Failed to get connection from pool");
+
+        let provider = add_or_update_provider(&mut pg_pool, new_provider("Quarantine test"))
+            .await
+            .expect("Expected Ok result");
+        let new_record = NewQuarantinedRecord {
+            quarantined_records_id: Uuid::new_v4(),
+            providers_id: provider.providers_id,
+            payload_archives_id: None,
+            record_hash: "abc".to_string(),
+            raw: "<base_plan/>".to_string(),
+            reason: json!({ "kind": "missing_field", "field": "@title" }),
+            status: QuarantineStatus::Pending.to_string(),
+        };
+        add_or_update_quarantined_records(&mut pg_pool, std::slice::from_ref(&new_record))
+            .await
+            .unwrap();
+        add_or_update_quarantined_records(
+            &mut pg_pool,
+            &[NewQuarantinedRecord {
+                quarantined_records_id: Uuid::new_v4(),
+                ..new_record.clone()
+            }],
+        )
+        .await
+        .unwrap();
+
+        let pending = list_quarantined_records(
+            &mut pg_pool,
+            Some(provider.providers_id),
+            QuarantineStatus::Pending,
+            10,
+        )
+        .await
+        .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(
+            pending[0].quarantined_records_id,
+            new_record.quarantined_records_id
+        );
+        assert_eq!(pending[0].occurrences, 2);
+
+        let resolved = update_quarantined_record(
+            &mut pg_pool,
+            new_record.quarantined_records_id,
+            QuarantineStatus::Resolved,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(resolved.status, "resolved");
+        assert_eq!(
+            get_quarantined_record(&mut pg_pool, new_record.quarantined_records_id)
+                .await
+                .unwrap()
+                .map(|r| r.status),
+            Some("resolved".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_page_through_pending_records() {
+        let connection = establish_connection().await;
+        let mut pg_pool = connection
+            .get_owned()
+            .await
+            .expect("Failed to get connection from pool");
+
+        let provider = add_or_update_provider(&mut pg_pool, new_provider("Quarantine paging test"))
+            .await
+            .expect("Expected Ok result");
+        let new_records: Vec<NewQuarantinedRecord> = (0..5)
+            .map(|i| NewQuarantinedRecord {
+                quarantined_records_id: Uuid::new_v4(),
+                providers_id: provider.providers_id,
+                payload_archives_id: None,
+                record_hash: format!("hash-{}", i),
+                raw: "<base_plan/>".to_string(),
+                reason: json!({ "kind": "missing_field", "field": "@title" }),
+                status: QuarantineStatus::Pending.to_string(),
+            })
+            .collect();
+        add_or_update_quarantined_records(&mut pg_pool, &new_records)
+            .await
+            .unwrap();
+        update_quarantined_record(
+            &mut pg_pool,
+            new_records[0].quarantined_records_id,
+            QuarantineStatus::Resolved,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let mut ids = Vec::new();
+        let mut after = None;
+        loop {
+            let page = list_pending_quarantined_records_after(
+                &mut pg_pool,
+                provider.providers_id,
+                after,
+                2,
+            )
+            .await
+            .unwrap();
+            let Some(last) = page.last() else {
+                break;
+            };
+            after = Some(last.quarantined_records_id);
+            ids.extend(page.iter().map(|record| record.quarantined_records_id));
+        }
+
+        let mut expected: Vec<Uuid> = new_records[1..]
+            .iter()
+            .map(|record| record.quarantined_records_id)
+            .collect();
+        expected.sort();
+        assert_eq!(ids, expected);
+    }
+}
@@ -9,6 +9,66 @@ diesel::table! {
         sell_mode -> Text,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        organizers_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    change_events (change_events_id) {
+        change_events_id -> Int8,
+        providers_id -> Uuid,
+        event -> Jsonb,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    ingestion_runs (ingestion_runs_id) {
+        ingestion_runs_id -> Uuid,
+        providers_id -> Uuid,
+        status -> Text,
+        started_at -> Timestamp,
+        finished_at -> Timestamp,
+        http_status -> Nullable<Int4>,
+        bytes -> Nullable<Int8>,
+        parse_duration_ms -> Nullable<Int8>,
+        base_plans_inserted -> Int4,
+        base_plans_updated -> Int4,
+        base_plans_unchanged -> Int4,
+        plans_inserted -> Int4,
+        plans_updated -> Int4,
+        plans_unchanged -> Int4,
+        zones_inserted -> Int4,
+        zones_updated -> Int4,
+        zones_unchanged -> Int4,
+        quarantined -> Int4,
+        error_kind -> Nullable<Text>,
+        error_message -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    organizers (organizers_id) {
+        organizers_id -> Uuid,
+        providers_id -> Uuid,
+        event_organizer_id -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    payload_archives (payload_archives_id) {
+        payload_archives_id -> Uuid,
+        providers_id -> Uuid,
+        fetched_at -> Timestamp,
+        status -> Int4,
+        content_hash -> Text,
+        size -> Int8,
+        body -> Nullable<Bytea>,
+        path -> Nullable<Text>,
+        created_at -> Timestamp,
     }
 }
 
@@ -17,13 +77,40 @@ diesel::table! {
         plans_id -> Uuid,
         base_plans_id -> Uuid,
         event_plan_id -> Text,
-        plan_start_date -> Timestamp,
-        plan_end_date -> Timestamp,
-        sell_from -> Timestamp,
-        sell_to -> Timestamp,
+        plan_start_date -> Timestamptz,
+        plan_end_date -> Timestamptz,
+        sell_from -> Nullable<Timestamptz>,
+        sell_to -> Nullable<Timestamptz>,
         sold_out -> Bool,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        first_seen_at -> Timestamp,
+        last_seen_at -> Timestamp,
+        availability -> Text,
+    }
+}
+
+diesel::table! {
+    provider_credentials (providers_id) {
+        providers_id -> Uuid,
+        auth_type -> Text,
+        secret -> Bytea,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    provider_fetch_states (providers_id) {
+        providers_id -> Uuid,
+        etag -> Nullable<Text>,
+        last_modified -> Nullable<Text>,
+        content_hash -> Nullable<Text>,
+        last_outcome -> Text,
+        checked_at -> Timestamp,
+        changed_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -36,6 +123,33 @@ diesel::table! {
         is_active -> Bool,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        adapter -> Text,
+        adapter_config -> Nullable<Jsonb>,
+        schedule_interval_sec -> Nullable<Int4>,
+        schedule_cron -> Nullable<Text>,
+        active_from -> Nullable<Time>,
+        active_until -> Nullable<Time>,
+        rate_limit_per_minute -> Nullable<Int4>,
+        rate_limit_burst -> Nullable<Int4>,
+        pagination -> Nullable<Jsonb>,
+        timezone -> Text,
+        date_formats -> Nullable<Jsonb>,
+        currency -> Text,
+    }
+}
+
+diesel::table! {
+    quarantined_records (quarantined_records_id) {
+        quarantined_records_id -> Uuid,
+        providers_id -> Uuid,
+        payload_archives_id -> Nullable<Uuid>,
+        record_hash -> Text,
+        raw -> Text,
+        reason -> Jsonb,
+        status -> Text,
+        occurrences -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -46,15 +160,51 @@ diesel::table! {
         event_zone_id -> Text,
         name -> Text,
         numbered -> Bool,
-        capacity -> Text,
-        price -> Text,
+        capacity -> Nullable<Int4>,
+        price -> Nullable<Numeric>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        first_seen_at -> Timestamp,
+        last_seen_at -> Timestamp,
+        availability -> Text,
+        currency -> Text,
+    }
+}
+
+diesel::table! {
+    zone_conversion_failures (zones_id, column_name) {
+        zones_id -> Uuid,
+        column_name -> Text,
+        raw_value -> Text,
+        created_at -> Timestamp,
     }
 }
 
+diesel::joinable!(base_plans -> organizers (organizers_id));
 diesel::joinable!(base_plans -> providers (providers_id));
+diesel::joinable!(change_events -> providers (providers_id));
+diesel::joinable!(ingestion_runs -> providers (providers_id));
+diesel::joinable!(organizers -> providers (providers_id));
+diesel::joinable!(payload_archives -> providers (providers_id));
+diesel::joinable!(provider_credentials -> providers (providers_id));
+diesel::joinable!(provider_fetch_states -> providers (providers_id));
 diesel::joinable!(plans -> base_plans (base_plans_id));
+diesel::joinable!(quarantined_records -> payload_archives (payload_archives_id));
+diesel::joinable!(quarantined_records -> providers (providers_id));
+diesel::joinable!(zone_conversion_failures -> zones (zones_id));
 diesel::joinable!(zones -> plans (plans_id));
 
-diesel::allow_tables_to_appear_in_same_query!(base_plans, plans, providers, zones,);
+diesel::allow_tables_to_appear_in_same_query!(
+    base_plans,
+    change_events,
+    ingestion_runs,
+    organizers,
+    payload_archives,
+    plans,
+    provider_credentials,
+    provider_fetch_states,
+    providers,
+    quarantined_records,
+    zone_conversion_failures,
+    zones,
+);
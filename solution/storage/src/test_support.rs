@@ -0,0 +1,27 @@
+//! Fixtures shared by the tests of the repository modules.
+
+use crate::models::providers::NewProvider;
+use uuid::Uuid;
+
+/// An inactive provider of the FeverUp XML feed, with a fresh id so tests never collide.
+pub fn new_provider(name: &str) -> NewProvider {
+    NewProvider {
+        providers_id: Uuid::new_v4(),
+        name: name.to_string(),
+        description: name.to_string(),
+        url: "http://localhost/events".to_string(),
+        is_active: false,
+        adapter: "feverup_xml".to_string(),
+        adapter_config: None,
+        schedule_interval_sec: None,
+        schedule_cron: None,
+        active_from: None,
+        active_until: None,
+        rate_limit_per_minute: None,
+        rate_limit_burst: None,
+        pagination: None,
+        timezone: "UTC".to_string(),
+        date_formats: None,
+        currency: "EUR".to_string(),
+    }
+}
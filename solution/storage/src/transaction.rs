@@ -0,0 +1,110 @@
+use crate::error::StorageError;
+use diesel_async::{
+    AnsiTransactionManager, AsyncConnection, AsyncPgConnection, TransactionManager,
+};
+
+pub use diesel_async::scoped_futures::{ScopedBoxFuture, ScopedFutureExt};
+
+/// Runs `f` inside a transaction, committed when it returns `Ok` and rolled back otherwise.
+/// The future is boxed with `ScopedFutureExt::scope_boxed`.
+pub async fn run_in_transaction<'a, T, E, F>(
+    connection: &mut AsyncPgConnection,
+    f: F,
+) -> Result<T, E>
+where
+    F: for<'r> FnOnce(&'r mut AsyncPgConnection) -> ScopedBoxFuture<'a, 'r, Result<T, E>>
+        + Send
+        + 'a,
+    E: From<diesel::result::Error> + Send + 'a,
+    T: Send + 'a,
+{
+    connection.transaction(f).await
+}
+
+/// Opens a transaction that spans several calls. It must be closed with
+/// `commit_transaction` or `rollback_transaction`; a pooled connection dropped with an
+/// open transaction is discarded by the pool, which rolls the transaction back.
+pub async fn begin_transaction(connection: &mut AsyncPgConnection) -> Result<(), StorageError> {
+    AnsiTransactionManager::begin_transaction(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+pub async fn commit_transaction(connection: &mut AsyncPgConnection) -> Result<(), StorageError> {
+    AnsiTransactionManager::commit_transaction(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+pub async fn rollback_transaction(connection: &mut AsyncPgConnection) -> Result<(), StorageError> {
+    AnsiTransactionManager::rollback_transaction(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connections::db::establish_connection;
+    use crate::provider::{add_or_update_provider, get_provider};
+    use crate::test_support::new_provider;
+
+    #[tokio::test]
+    async fn test_rolled_back_writes_are_discarded() {
+        let pool = establish_connection().await;
+        let mut conn = pool
+            .get_owned()
+            .await
+            .expect("Failed to get connection from pool");
+
+        let failed = new_provider("Transaction test");
+        let inserted = failed.clone();
+        let result: Result<(), StorageError> = run_in_transaction(&mut conn, |conn| {
+            async move {
+                add_or_update_provider(conn, inserted).await?;
+                Err(StorageError::Other(
+                    "Failure after the first write".to_string(),
+                ))
+            }
+            .scope_boxed()
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(
+            get_provider(&mut conn, failed.providers_id).await.unwrap(),
+            None
+        );
+
+        let rolled_back = new_provider("Transaction test");
+        begin_transaction(&mut conn)
+            .await
+            .expect("Expected Ok result");
+        add_or_update_provider(&mut conn, rolled_back.clone())
+            .await
+            .expect("Expected Ok result");
+        rollback_transaction(&mut conn)
+            .await
+            .expect("Expected Ok result");
+        assert_eq!(
+            get_provider(&mut conn, rolled_back.providers_id)
+                .await
+                .unwrap(),
+            None
+        );
+
+        let committed = new_provider("Transaction test");
+        begin_transaction(&mut conn)
+            .await
+            .expect("Expected Ok result");
+        add_or_update_provider(&mut conn, committed.clone())
+            .await
+            .expect("Expected Ok result");
+        commit_transaction(&mut conn)
+            .await
+            .expect("Expected Ok result");
+        assert!(get_provider(&mut conn, committed.providers_id)
+            .await
+            .unwrap()
+            .is_some());
+    }
+}
@@ -1,19 +1,44 @@
-use crate::connections::db::PgPooledConnection;
+use crate::bulk::{last_by_key, rows_per_chunk, updated_at_if_changed};
 use crate::error::StorageError;
+use crate::models::availability::Availability;
 use crate::models::zones::{NewZone, Zone};
 use crate::schema::zones;
 use diesel::insert_into;
+use diesel::pg::upsert::excluded;
 use diesel::prelude::*;
-use diesel::RunQueryDsl;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
 
-pub fn get_zones(connection: &mut PgPooledConnection) -> Result<Vec<Zone>, StorageError> {
+/// Bind parameters of one `NewZone` row.
+pub(crate) const ZONE_PARAMS: usize = 8;
+
+/// Columns whose change bumps `updated_at` on upsert. A zone coming back to the feed counts
+/// as updated through `availability`.
+pub(crate) const ZONE_CHANGE_COLUMNS: &[&str] =
+    &["name", "capacity", "price", "currency", "availability"];
+
+pub async fn get_zones(connection: &mut AsyncPgConnection) -> Result<Vec<Zone>, StorageError> {
+    zones::table
+        .select(Zone::as_select())
+        .load::<Zone>(connection)
+        .await
+        .map_err(StorageError::from)
+}
+
+/// Loads the stored zones of the given plans.
+pub async fn get_zones_of_plans(
+    connection: &mut AsyncPgConnection,
+    plans_ids: &[uuid::Uuid],
+) -> Result<Vec<Zone>, StorageError> {
     zones::table
+        .filter(zones::plans_id.eq_any(plans_ids))
         .select(Zone::as_select())
         .load::<Zone>(connection)
+        .await
         .map_err(StorageError::from)
 }
-pub fn add_or_update_zone(
-    connection: &mut PgPooledConnection,
+
+pub async fn add_or_update_zone(
+    connection: &mut AsyncPgConnection,
     new_zone: NewZone,
 ) -> Result<Zone, StorageError> {
     insert_into(zones::table)
@@ -24,9 +49,149 @@ pub fn add_or_update_zone(
             zones::name.eq(&new_zone.name),
             zones::capacity.eq(&new_zone.capacity),
             zones::price.eq(&new_zone.price),
+            zones::currency.eq(&new_zone.currency),
             zones::updated_at.eq(diesel::dsl::now),
+            zones::last_seen_at.eq(diesel::dsl::now),
+            zones::availability.eq(Availability::Available.to_string()),
         ))
         .returning(Zone::as_returning())
         .get_result::<Zone>(connection)
+        .await
         .map_err(StorageError::from)
 }
+
+/// Multi-row variant of `add_or_update_zone`, one statement per chunk of rows.
+/// When a zone appears more than once, its last occurrence wins.
+pub async fn add_or_update_zones(
+    connection: &mut AsyncPgConnection,
+    new_zones: &[NewZone],
+) -> Result<Vec<Zone>, StorageError> {
+    let rows = last_by_key(new_zones, |zone| {
+        (zone.plans_id, zone.event_zone_id.clone(), zone.numbered)
+    });
+    let mut inserted = Vec::with_capacity(rows.len());
+    for chunk in rows.chunks(rows_per_chunk(ZONE_PARAMS)) {
+        let chunk_rows = insert_into(zones::table)
+            .values(chunk.to_vec())
+            .on_conflict((zones::plans_id, zones::event_zone_id, zones::numbered))
+            .do_update()
+            .set((
+                zones::name.eq(excluded(zones::name)),
+                zones::capacity.eq(excluded(zones::capacity)),
+                zones::price.eq(excluded(zones::price)),
+                zones::currency.eq(excluded(zones::currency)),
+                zones::updated_at.eq(updated_at_if_changed("zones", ZONE_CHANGE_COLUMNS)),
+                zones::last_seen_at.eq(diesel::dsl::now),
+                zones::availability.eq(Availability::Available.to_string()),
+            ))
+            .returning(Zone::as_returning())
+            .get_results::<Zone>(connection)
+            .await?;
+        inserted.extend(chunk_rows);
+    }
+    Ok(inserted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base_plan::add_or_update_base_plans;
+    use crate::connections::db::establish_connection;
+    use crate::models::base_plans::NewBasePlan;
+    use crate::models::plans::NewPlan;
+    use crate::plan::add_or_update_plans;
+    use crate::provider::add_or_update_provider;
+    use crate::test_support::new_provider;
+    use bigdecimal::BigDecimal;
+    use uuid::Uuid;
+
+    fn price(value: &str) -> BigDecimal {
+        value.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_bulk_upserts_span_several_chunks() {
+        let connection = establish_connection().await;
+        let mut pg_pool = connection
+            .get_owned()
+            .await
+            .expect("Failed to get connection from pool");
+
+        let provider = add_or_update_provider(&mut pg_pool, new_provider("Bulk test"))
+            .await
+            .expect("Expected Ok result");
+        let base_plans = add_or_update_base_plans(
+            &mut pg_pool,
+            &[NewBasePlan {
+                base_plans_id: Uuid::new_v4(),
+                providers_id: provider.providers_id,
+                event_base_id: "1".to_string(),
+                title: "Bulk test".to_string(),
+                sell_mode: "online".to_string(),
+                organizers_id: None,
+            }],
+        )
+        .await
+        .expect("Expected Ok result");
+        let now = chrono::Utc::now();
+        let plans = add_or_update_plans(
+            &mut pg_pool,
+            &[NewPlan {
+                plans_id: Uuid::new_v4(),
+                base_plans_id: base_plans[0].base_plans_id,
+                event_plan_id: "1".to_string(),
+                plan_start_date: now,
+                plan_end_date: now,
+                sell_from: Some(now),
+                sell_to: Some(now),
+                sold_out: false,
+            }],
+        )
+        .await
+        .expect("Expected Ok result");
+
+        let zone_count = rows_per_chunk(ZONE_PARAMS) + 10;
+        let mut new_zones: Vec<NewZone> = (0..zone_count)
+            .map(|i| NewZone {
+                zones_id: Uuid::new_v4(),
+                plans_id: plans[0].plans_id,
+                event_zone_id: i.to_string(),
+                name: "Platea".to_string(),
+                capacity: Some(10),
+                price: Some(price("20.00")),
+                numbered: true,
+                currency: "EUR".to_string(),
+            })
+            .collect();
+        // The same zone twice in a batch: the last occurrence wins
+        let mut duplicate = new_zones[0].clone();
+        duplicate.zones_id = Uuid::new_v4();
+        duplicate.price = Some(price("25.00"));
+        new_zones.push(duplicate);
+
+        let inserted = add_or_update_zones(&mut pg_pool, &new_zones)
+            .await
+            .expect("Expected Ok result");
+        assert_eq!(inserted.len(), zone_count);
+        let first = inserted
+            .iter()
+            .find(|zone| zone.event_zone_id == "0")
+            .unwrap();
+        assert_eq!(first.price, Some(price("25.00")));
+
+        // Upserting again updates the existing rows and keeps their ids
+        let updated = add_or_update_zones(&mut pg_pool, &new_zones[..1])
+            .await
+            .unwrap();
+        assert_eq!(updated[0].zones_id, first.zones_id);
+        assert_eq!(updated[0].price, Some(price("20.00")));
+        assert!(updated[0].updated_at > first.updated_at);
+
+        // An identical row keeps its update time
+        let unchanged = add_or_update_zones(&mut pg_pool, &new_zones[..1])
+            .await
+            .unwrap();
+        assert_eq!(unchanged[0].updated_at, updated[0].updated_at);
+        assert!(unchanged[0].last_seen_at > updated[0].last_seen_at);
+    }
+}
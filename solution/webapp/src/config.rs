@@ -43,6 +43,8 @@ pub struct Config {
 
     #[serde(default = "web_app_server")]
     pub web_app_server: String,
+
+    pub redis_uri: String,
 }
 
 pub fn build() -> Config {
@@ -1,5 +1,9 @@
+use chrono::DateTime;
+use chrono_tz::Tz;
 use serde::Serialize;
+use storage::bigdecimal::ToPrimitive;
 use storage::connections::cache::ProviderABaseEvent;
+use storage::BigDecimal;
 use utoipa::ToSchema;
 
 #[derive(Serialize, ToSchema)]
@@ -21,12 +25,32 @@ pub struct EventDTO {
     pub start_time: String,
     pub end_date: String,
     pub end_time: String,
-    pub min_price: f64,
-    pub max_price: f64,
+    /// Lowest zone price in `currency`, absent when no zone has a price.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_price: Option<f64>,
+    /// Highest zone price in `currency`, absent when no zone has a price.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_price: Option<f64>,
+    /// ISO 4217 currency of `min_price` and `max_price`: that of the first priced zone, whose
+    /// price range leaves out the zones priced in another currency. Absent when no zone has a
+    /// price.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub organizer: Option<OrganizerDTO>,
 }
 
+#[derive(Serialize, ToSchema)]
+pub struct OrganizerDTO {
+    pub id: String,
+    /// Id of the organizer in the feed of its provider
+    pub external_id: String,
+}
+
+/// Maps cached events to the search response, with their dates rendered in `timezone`.
 pub fn map_provider_events_to_response_dto(
     base_events: &[ProviderABaseEvent],
+    timezone: Tz,
 ) -> ApiResponse<EventsData> {
     let mut events_data = Vec::new();
 
@@ -43,18 +67,24 @@ pub fn map_provider_events_to_response_dto(
         let plan = &base_event.plan;
 
         // Parse start and end datetime
-        let (start_date, start_time) = split_datetime(&plan.plan_start_date);
-        let (end_date, end_time) = split_datetime(&plan.plan_end_date);
+        let (start_date, start_time) = split_datetime(&plan.plan_start_date, timezone);
+        let (end_date, end_time) = split_datetime(&plan.plan_end_date, timezone);
 
-        // Compute min and max price
-        let prices: Vec<f64> = plan
+        // Compute min and max price in the currency of the first priced zone. Prices are
+        // validated at ingestion; zones without one are left out, so that a plan with no priced
+        // zone is not reported as free, and so are zones priced in another currency.
+        let currency = plan
             .zones
             .iter()
-            .filter_map(|z| z.price.parse::<f64>().ok())
+            .find(|z| z.price.is_some())
+            .and_then(|z| z.currency.clone());
+        let prices: Vec<&BigDecimal> = plan
+            .zones
+            .iter()
+            .filter(|z| z.currency == currency)
+            .filter_map(|z| z.price.as_ref())
             .collect();
-
-        let min_price = prices.iter().cloned().fold(f64::INFINITY, f64::min);
-        let max_price = prices.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let to_f64 = |price: Option<&&BigDecimal>| price.and_then(|p| p.to_f64());
 
         // Create EventDTO and push to the events_data vector
         events_data.push(EventDTO {
@@ -64,8 +94,13 @@ pub fn map_provider_events_to_response_dto(
             start_time,
             end_date,
             end_time,
-            min_price: if prices.is_empty() { 0.0 } else { min_price },
-            max_price: if prices.is_empty() { 0.0 } else { max_price },
+            min_price: to_f64(prices.iter().min()),
+            max_price: to_f64(prices.iter().max()),
+            currency,
+            organizer: base_event.organizer.as_ref().map(|organizer| OrganizerDTO {
+                id: organizer.id.clone(),
+                external_id: organizer.external_id.clone(),
+            }),
         });
     }
 
@@ -78,9 +113,121 @@ pub fn map_provider_events_to_response_dto(
     }
 }
 
-fn split_datetime(dt: &str) -> (String, String) {
+/// Keeps the events of an organizer, given its id.
+pub fn filter_by_organizer(
+    events: Vec<ProviderABaseEvent>,
+    organizer_id: &str,
+) -> Vec<ProviderABaseEvent> {
+    events
+        .into_iter()
+        .filter(|event| {
+            event
+                .organizer
+                .as_ref()
+                .is_some_and(|organizer| organizer.id.eq_ignore_ascii_case(organizer_id))
+        })
+        .collect()
+}
+
+/// Splits an RFC 3339 date into the date and time of `timezone`. Dates cached without an
+/// offset are split as they are.
+fn split_datetime(dt: &str, timezone: Tz) -> (String, String) {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(dt) {
+        let local = dt.with_timezone(&timezone);
+        return (
+            local.format("%Y-%m-%d").to_string(),
+            local.format("%H:%M:%S").to_string(),
+        );
+    }
     match dt.split_once('T') {
         Some((date, time)) => (date.to_string(), time.to_string()),
         None => (dt.to_string(), "".to_string()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use storage::connections::cache::{EventOrganizer, Plan, Zone};
+
+    fn event(id: &str, organizer_id: Option<&str>) -> ProviderABaseEvent {
+        ProviderABaseEvent {
+            id: id.to_string(),
+            title: format!("Event {}", id),
+            sell_mode: "online".to_string(),
+            plan: Plan {
+                plan_start_date: "2021-06-30T21:00:00Z".to_string(),
+                plan_end_date: "2021-06-30T23:00:00Z".to_string(),
+                plan_id: id.to_string(),
+                sell_from: None,
+                sell_to: None,
+                sold_out: false,
+                zones: Vec::new(),
+            },
+            organizer: organizer_id.map(|organizer_id| EventOrganizer {
+                id: organizer_id.to_string(),
+                external_id: "1".to_string(),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_filter_by_organizer() {
+        let organizer_id = "6a1e4c1e-5b7f-4a56-9d1c-0d3f4a2b9e10";
+        let events = vec![
+            event("1", Some(organizer_id)),
+            event("2", Some("0f9b1d2e-3c4a-4b5d-8e6f-7a8b9c0d1e2f")),
+            event("3", None),
+        ];
+
+        let kept = filter_by_organizer(events, &organizer_id.to_uppercase());
+
+        let ids: Vec<&str> = kept.iter().map(|event| event.id.as_str()).collect();
+        assert_eq!(ids, vec!["1"]);
+    }
+
+    #[test]
+    fn test_price_range_per_event() {
+        let zone = |zone_id: &str, price: Option<&str>| Zone {
+            zone_id: zone_id.to_string(),
+            capacity: Some(10),
+            price: price.map(|price| price.parse().unwrap()),
+            name: "Platea".to_string(),
+            numbered: false,
+            currency: Some("EUR".to_string()),
+        };
+        let dollars = |zone_id: &str, price: &str| Zone {
+            currency: Some("USD".to_string()),
+            ..zone(zone_id, Some(price))
+        };
+        let mut priced = event("1", None);
+        priced.plan.zones = vec![
+            zone("1", Some("20.50")),
+            zone("2", None),
+            zone("3", Some("0")),
+        ];
+        let mut unpriced = event("2", None);
+        unpriced.plan.zones = vec![zone("1", None)];
+        let mut mixed = event("3", None);
+        mixed.plan.zones = vec![
+            zone("1", None),
+            dollars("2", "99"),
+            zone("3", Some("30")),
+            dollars("4", "5"),
+            zone("5", Some("45")),
+        ];
+
+        let response = map_provider_events_to_response_dto(&[priced, unpriced, mixed], Tz::UTC);
+
+        let events = serde_json::to_value(&response.data.events).unwrap();
+        assert_eq!(events[0]["min_price"], 0.0);
+        assert_eq!(events[0]["max_price"], 20.5);
+        assert_eq!(events[0]["currency"], "EUR");
+        assert!(events[1].get("min_price").is_none());
+        assert!(events[1].get("max_price").is_none());
+        assert!(events[1].get("currency").is_none());
+        assert_eq!(events[2]["min_price"], 5.0);
+        assert_eq!(events[2]["max_price"], 99.0);
+        assert_eq!(events[2]["currency"], "USD");
+    }
+}
@@ -1,9 +1,6 @@
 use actix_web::{web, App, HttpServer};
 use anyhow::Result;
 use dotenv::dotenv;
-use storage::connections::cache::Cache;
-use storage::error::StorageError;
-use tokio::sync::Mutex;
 
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
@@ -14,24 +11,16 @@ mod handler;
 mod service;
 use service::ApiDoc;
 
-async fn get_cache() -> Cache {
-    Cache::new()
-        .await
-        .map_err(|e| {
-            log::error!("Failed to connect to Redis: {}", e);
-            StorageError::from(e)
-        })
-        .unwrap()
-}
-
 #[actix_web::main]
 async fn main() -> Result<()> {
     dotenv().ok();
     env_logger::init();
     let config = config::build();
 
-    let redis_conn = get_cache().await;
-    let app_data = web::Data::new(Mutex::new(redis_conn));
+    // Redis may still be down: searches connect on demand and answer 503 until it is up
+    let cache = service::CacheState::new(config.redis_uri.clone());
+    cache.get().await;
+    let app_data = web::Data::new(cache);
 
     log::info!("Starting webapp on {}", config.web_app_server);
 
@@ -47,14 +36,14 @@ async fn main() -> Result<()> {
     .disable_signals()
     .bind(config.web_app_server)?
     .client_disconnect_timeout(std::time::Duration::from_millis(
-        config.actix_client_shutdown_ms as u64,
+        config.actix_client_shutdown_ms,
     ))
     .client_request_timeout(std::time::Duration::from_millis(
-        config.actix_client_timeout_ms as u64,
+        config.actix_client_timeout_ms,
     ))
     .shutdown_timeout(config.actix_shutdown_timeout_s)
     .keep_alive(std::time::Duration::from_secs(
-        config.actix_keepalive_seconds as u64,
+        config.actix_keepalive_seconds,
     ))
     .workers(config.actix_num_workers)
     .run()
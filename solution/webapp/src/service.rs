@@ -4,14 +4,19 @@ use crate::handler::EventsData;
 use crate::handler::*;
 use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use actix_web::{web::Json, web::Query, Result};
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
 use storage::connections::cache::is_healthy;
 use storage::connections::cache::Cache;
-use tokio::sync::Mutex;
 use utoipa::OpenApi;
 use utoipa::ToSchema;
 
+/// Time a search waits for Redis to reconnect before answering 503.
+const CACHE_CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
 #[derive(OpenApi)]
 #[openapi(
     paths(
@@ -29,7 +34,8 @@ pub struct ApiDoc;
 
 /// Configures the web service routes.
 /// It registers the `/search` route for searching available events and the `/health` route for health checks.
-/// The `/search` route accepts GET requests with query parameters for `starts_at` and `ends_at`.
+/// The `/search` route accepts GET requests with query parameters for `starts_at` and `ends_at`,
+/// an optional `timezone` the results are rendered in and an optional `organizer_id` filter.
 /// The `/health` route provides a basic health check response.
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(web::resource("/search").route(web::get().to(search_available_events)));
@@ -45,6 +51,10 @@ pub struct HealthResponse {
 pub struct GetSearchRequest {
     starts_at: String,
     ends_at: String,
+    #[serde(default)]
+    timezone: Option<String>,
+    #[serde(default)]
+    organizer_id: Option<String>,
 }
 
 #[utoipa::path(
@@ -66,8 +76,10 @@ pub async fn get_health(_req: HttpRequest) -> Result<Json<HealthResponse>> {
     get,
     path = "/search",
     params(
-        ("starts_at" = String, Query, description = "Start datetime in %Y-%m-%dT%H:%M:%S format"),
-        ("ends_at" = String, Query, description = "End datetime in %Y-%m-%dT%H:%M:%S format")
+        ("starts_at" = String, Query, description = "Start datetime in RFC 3339 format, e.g. 2021-06-30T21:00:00%2B02:00 (`+` URL-encoded). Datetimes without an offset are UTC"),
+        ("ends_at" = String, Query, description = "End datetime in RFC 3339 format, e.g. 2021-06-30T23:00:00%2B02:00 (`+` URL-encoded). Datetimes without an offset are UTC"),
+        ("timezone" = Option<String>, Query, description = "IANA timezone the dates of the results are rendered in (default: UTC)"),
+        ("organizer_id" = Option<String>, Query, description = "Only return the events of this organizer, given its id")
     ),
     responses(
         (status = 200, description = "List of available plans", body = ApiResponse<EventsData>),
@@ -78,47 +90,184 @@ pub async fn get_health(_req: HttpRequest) -> Result<Json<HealthResponse>> {
     tag = "api"
 )]
 /// Search for available events based on the provided time range.
-/// Query parameters: starts_at and ends_at
+/// Query parameters: starts_at, ends_at, timezone and organizer_id
 pub async fn search_available_events(
-    state: web::Data<Mutex<Cache>>,
+    state: web::Data<CacheState>,
     req: Query<GetSearchRequest>,
 ) -> impl Responder {
     // Validate the time range
     if req.starts_at.is_empty() || req.ends_at.is_empty() {
         return ErrorResponse::bad_request("Both starts_at and ends_at must be provided.");
     }
-    if req.starts_at >= req.ends_at {
-        return ErrorResponse::bad_request("starts_at must be before ends_at.");
-    }
     // Parse the datetime strings
-    let starts_at = match NaiveDateTime::parse_from_str(&req.starts_at, "%Y-%m-%dT%H:%M:%S") {
-        Ok(dt) => dt,
-        Err(_) => {
-            return ErrorResponse::bad_request("Invalid starts_at format. Use %Y-%m-%dT%H:%M:%S");
-        }
+    let Some(starts_at) = parse_search_date(&req.starts_at) else {
+        return ErrorResponse::bad_request(
+            "Invalid starts_at format. Use RFC 3339, e.g. 2021-06-30T21:00:00%2B02:00",
+        );
+    };
+    let Some(ends_at) = parse_search_date(&req.ends_at) else {
+        return ErrorResponse::bad_request(
+            "Invalid ends_at format. Use RFC 3339, e.g. 2021-06-30T23:00:00%2B02:00",
+        );
     };
-    let ends_at = match NaiveDateTime::parse_from_str(&req.ends_at, "%Y-%m-%dT%H:%M:%S") {
-        Ok(dt) => dt,
-        Err(_) => {
-            return ErrorResponse::bad_request("Invalid ends_at format. Use %Y-%m-%dT%H:%M:%S");
+    let timezone = match req.timezone.as_deref().map(str::parse::<Tz>) {
+        None => Tz::UTC,
+        Some(Ok(timezone)) => timezone,
+        Some(Err(_)) => {
+            return ErrorResponse::bad_request(
+                "Invalid timezone. Use an IANA name, e.g. Europe/Madrid",
+            );
         }
     };
 
     if starts_at >= ends_at {
         return ErrorResponse::bad_request("starts_at must be before ends_at.");
     }
-    // Lock the cache state to ensure thread safety
-    let cache = state.lock().await;
-    // Check if the cache is healthy
+    let Some(cache) = state.get().await else {
+        return ErrorResponse::service_unavailable("Cache is not reachable.");
+    };
+    // Check if the cache is healthy; a lost connection is reopened by a later search
     if !is_healthy(&cache).await {
+        state.reset();
         return ErrorResponse::service_unavailable("Cache is not healthy.");
     }
     // Fetch matched plans from the cache
     match cache.get_matched_plans(starts_at, ends_at).await {
         Ok(events) => {
-            let response = map_provider_events_to_response_dto(&events);
+            let events = match req.organizer_id.as_deref() {
+                Some(organizer_id) => filter_by_organizer(events, organizer_id),
+                None => events,
+            };
+            let response = map_provider_events_to_response_dto(&events, timezone);
             HttpResponse::Ok().json(response)
         }
         Err(_) => ErrorResponse::internal_error("Failed to fetch events"),
     }
 }
+
+/// Redis connection shared by the searches. It connects on first use and reconnects after
+/// the connection is lost; searches are not held up by one another while it does.
+pub struct CacheState {
+    redis_url: String,
+    cache: Mutex<Option<Cache>>,
+}
+
+impl CacheState {
+    pub fn new(redis_url: String) -> Self {
+        CacheState {
+            redis_url,
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// The shared connection, or `None` while Redis is unreachable.
+    pub async fn get(&self) -> Option<Cache> {
+        if let Some(cache) = self.lock().clone() {
+            return Some(cache);
+        }
+        let connected = tokio::time::timeout(
+            CACHE_CONNECT_TIMEOUT,
+            Cache::with_url(self.redis_url.clone()),
+        )
+        .await;
+        match connected {
+            Ok(Ok(cache)) => {
+                *self.lock() = Some(cache.clone());
+                Some(cache)
+            }
+            Ok(Err(e)) => {
+                log::error!("Failed to connect to Redis: {}", e);
+                None
+            }
+            Err(_) => {
+                log::error!("Timed out connecting to Redis");
+                None
+            }
+        }
+    }
+
+    /// Drops the shared connection, so the next `get` reconnects.
+    pub fn reset(&self) {
+        self.lock().take();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Cache>> {
+        self.cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Parses an RFC 3339 datetime. Datetimes without an offset, in `%Y-%m-%dT%H:%M:%S` format,
+/// are taken as UTC. A positive offset whose `+` was not URL-encoded reaches the query as a
+/// space, e.g. `2021-06-30T21:00:00 02:00`, and is read as such.
+fn parse_search_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = match value.rsplit_once(' ') {
+        Some((datetime, offset)) if is_offset(offset) => format!("{}+{}", datetime, offset),
+        _ => value.to_string(),
+    };
+    let value = value.as_str();
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S").map(|dt| dt.and_utc())
+        })
+        .ok()
+}
+
+/// Whether `value` is a `HH:MM` UTC offset without its sign.
+fn is_offset(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    bytes.len() == 5 && bytes[2] == b':' && [0, 1, 3, 4].iter().all(|&i| bytes[i].is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_search_date_from_query_string() {
+        let expected = "2021-06-30T19:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        for query in [
+            "starts_at=2021-06-30T21:00:00+02:00&ends_at=2021-06-30T23:00:00Z",
+            "starts_at=2021-06-30T21:00:00%2B02:00&ends_at=2021-06-30T23:00:00Z",
+            "starts_at=2021-06-30 21:00:00%2B02:00&ends_at=2021-06-30T23:00:00Z",
+            "starts_at=2021-06-30T19:00:00&ends_at=2021-06-30T23:00:00Z",
+        ] {
+            let request = Query::<GetSearchRequest>::from_query(query).unwrap();
+            assert_eq!(
+                parse_search_date(&request.starts_at),
+                Some(expected),
+                "{}",
+                query
+            );
+        }
+        let request =
+            Query::<GetSearchRequest>::from_query("starts_at=2021-06-30T21:00:00-02:00&ends_at=x")
+                .unwrap();
+        assert_eq!(
+            parse_search_date(&request.starts_at),
+            "2021-06-30T23:00:00Z".parse().ok()
+        );
+        assert_eq!(parse_search_date("2021-06-30T21:00:00 2:00"), None);
+    }
+
+    #[tokio::test]
+    async fn test_cache_state_does_not_wait_for_an_unresponsive_cache() {
+        // Accepts connections but never answers the Redis handshake
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let redis_url = format!("redis://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+        let state = CacheState::new(redis_url);
+
+        let cache = tokio::time::timeout(CACHE_CONNECT_TIMEOUT * 2, state.get())
+            .await
+            .expect("reconnecting should time out on its own");
+        assert!(cache.is_none());
+    }
+}